@@ -1,19 +1,29 @@
 // This bot answers how many messages it received in total on every message.
 
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
 
-use lazy_static::lazy_static;
-use teloxide::prelude::*;
-
-lazy_static! {
-    static ref MESSAGES_TOTAL: AtomicU64 = AtomicU64::new(0);
-}
+use teloxide::{dispatching::Router, prelude::*};
 
 #[tokio::main]
 async fn main() {
     run().await;
 }
 
+// The counter is extracted from the dependencies registered below. Inside a
+// plain handler, use `cx.try_dependency::<Arc<AtomicU64>>()`, which returns
+// `None` instead of panicking if a dependency hasn't been registered.
+async fn count(
+    message: UpdateWithCx<Message>,
+    (messages_total,): (Arc<AtomicU64>,),
+) -> ResponseResult<()> {
+    let previous = messages_total.fetch_add(1, Ordering::Relaxed);
+    message.answer_str(format!("I received {} messages in total.", previous)).await?;
+    Ok(())
+}
+
 async fn run() {
     teloxide::enable_logging!();
     log::info!("Starting shared_state_bot...");
@@ -21,17 +31,8 @@ async fn run() {
     let bot = Bot::from_env();
 
     Dispatcher::new(bot)
-        .messages_handler(|rx: DispatcherHandlerRx<Message>| {
-            rx.for_each_concurrent(None, |message| async move {
-                let previous = MESSAGES_TOTAL.fetch_add(1, Ordering::Relaxed);
-
-                message
-                    .answer_str(format!("I received {} messages in total.", previous))
-                    .await
-                    .log_on_error()
-                    .await;
-            })
-        })
+        .dependency(Arc::new(AtomicU64::new(0)))
+        .messages_handler(Router::new().endpoint_with(count))
         .dispatch()
         .await;
 }
@@ -17,11 +17,10 @@ use crate::{
     },
     types::{
         BotCommand, ChatId, ChatOrInlineMessage, ChatPermissions, InlineQueryResult, InputFile,
-        InputMedia, LabeledPrice, ParseMode, StickerType,
+        InputMedia, LabeledPrice, StickerType,
     },
     Bot,
 };
-use std::ops::Deref;
 
 impl Bot {
     /// Use this method to receive incoming updates using long polling ([wiki]).
@@ -115,10 +114,7 @@ impl Bot {
         C: Into<ChatId>,
         T: Into<String>,
     {
-        self.with_default_parse_mode_if_specified(
-            SendMessage::new(self.clone(), chat_id, text),
-            SendMessage::parse_mode,
-        )
+        SendMessage::new(self.clone(), chat_id, text)
     }
 
     /// Use this method to forward messages of any kind.
@@ -177,10 +173,7 @@ impl Bot {
     where
         C: Into<ChatId>,
     {
-        self.with_default_parse_mode_if_specified(
-            SendPhoto::new(self.clone(), chat_id, photo),
-            SendPhoto::parse_mode,
-        )
+        SendPhoto::new(self.clone(), chat_id, photo)
     }
 
     ///
@@ -198,10 +191,7 @@ impl Bot {
     where
         C: Into<ChatId>,
     {
-        self.with_default_parse_mode_if_specified(
-            SendAudio::new(self.clone(), chat_id, audio),
-            SendAudio::parse_mode,
-        )
+        SendAudio::new(self.clone(), chat_id, audio)
     }
 
     /// Use this method to send general files.
@@ -232,10 +222,7 @@ impl Bot {
     where
         C: Into<ChatId>,
     {
-        self.with_default_parse_mode_if_specified(
-            SendDocument::new(self.clone(), chat_id, document),
-            SendDocument::parse_mode,
-        )
+        SendDocument::new(self.clone(), chat_id, document)
     }
 
     /// Use this method to send video files, Telegram clients support mp4 videos
@@ -269,10 +256,7 @@ impl Bot {
     where
         C: Into<ChatId>,
     {
-        self.with_default_parse_mode_if_specified(
-            SendVideo::new(self.clone(), chat_id, video),
-            SendVideo::parse_mode,
-        )
+        SendVideo::new(self.clone(), chat_id, video)
     }
 
     /// Use this method to send animation files (GIF or H.264/MPEG-4 AVC video
@@ -297,10 +281,7 @@ impl Bot {
     where
         C: Into<ChatId>,
     {
-        self.with_default_parse_mode_if_specified(
-            SendAnimation::new(self.clone(), chat_id, animation),
-            SendAnimation::parse_mode,
-        )
+        SendAnimation::new(self.clone(), chat_id, animation)
     }
 
     /// Use this method to send audio files, if you want Telegram clients to
@@ -340,10 +321,7 @@ impl Bot {
     where
         C: Into<ChatId>,
     {
-        self.with_default_parse_mode_if_specified(
-            SendVoice::new(self.clone(), chat_id, voice),
-            SendVoice::parse_mode,
-        )
+        SendVoice::new(self.clone(), chat_id, voice)
     }
 
     /// As of [v.4.0], Telegram clients support rounded square mp4 videos of up
@@ -521,10 +499,7 @@ impl Bot {
         Q: Into<String>,
         O: Into<Vec<String>>,
     {
-        self.with_default_parse_mode_if_specified(
-            SendPoll::new(self.clone(), chat_id, question, options),
-            SendPoll::explanation_parse_mode,
-        )
+        SendPoll::new(self.clone(), chat_id, question, options)
     }
 
     /// Use this method when you need to tell the user that something is
@@ -1007,11 +982,7 @@ impl Bot {
     where
         T: Into<String>,
     {
-        match self.parse_mode.deref() {
-            None => EditMessageText::new(self.clone(), chat_or_inline_message, text),
-            Some(parse_mode) => EditMessageText::new(self.clone(), chat_or_inline_message, text)
-                .parse_mode(*parse_mode.deref()),
-        }
+        EditMessageText::new(self.clone(), chat_or_inline_message, text)
     }
 
     /// Use this method to edit captions of messages.
@@ -1033,11 +1004,7 @@ impl Bot {
         &self,
         chat_or_inline_message: ChatOrInlineMessage,
     ) -> EditMessageCaption {
-        match self.parse_mode.deref() {
-            None => EditMessageCaption::new(self.clone(), chat_or_inline_message),
-            Some(parse_mode) => EditMessageCaption::new(self.clone(), chat_or_inline_message)
-                .parse_mode(*parse_mode.deref()),
-        }
+        EditMessageCaption::new(self.clone(), chat_or_inline_message)
     }
 
     /// Use this method to edit animation, audio, document, photo, or video
@@ -1524,15 +1491,4 @@ impl Bot {
     {
         SetStickerSetThumb::new(self.clone(), name, user_id)
     }
-
-    fn with_default_parse_mode_if_specified<Builder>(
-        &self,
-        builder: Builder,
-        f: fn(Builder, ParseMode) -> Builder,
-    ) -> Builder {
-        match self.parse_mode.deref() {
-            None => builder,
-            Some(parse_mode) => f(builder, *parse_mode.deref()),
-        }
-    }
 }
@@ -41,7 +41,7 @@ impl Bot {
     where
         D: AsyncWrite + Unpin,
     {
-        download_file(&self.client, &self.token, path, destination).await
+        download_file(self, path, destination).await
     }
 
     /// Download a file from Telegram.
@@ -60,6 +60,6 @@ impl Bot {
         &self,
         path: &str,
     ) -> Result<impl Stream<Item = Result<Bytes, reqwest::Error>>, reqwest::Error> {
-        download_file_stream(&self.client, &self.token, path).await
+        download_file_stream(self, path).await
     }
 }
@@ -1,8 +1,17 @@
-use crate::types::ParseMode;
+use crate::{
+    layers::{
+        DefaultParseMode, Layer, Limits, Next, RawRequest, Retry, RetryPolicy,
+        Throttle,
+    },
+    net::TELEGRAM_API_URL,
+    requests::ResponseResult,
+    types::ParseMode,
+};
 use reqwest::{
     header::{HeaderMap, CONNECTION},
-    Client, ClientBuilder,
+    Client, ClientBuilder, Url,
 };
+use serde_json::value::RawValue;
 use std::{sync::Arc, time::Duration};
 
 mod api;
@@ -19,8 +28,9 @@ pub(crate) const TELOXIDE_PROXY: &str = "TELOXIDE_PROXY";
 #[derive(Debug, Clone)]
 pub struct Bot {
     token: Arc<str>,
+    api_url: Arc<Url>,
     client: Client,
-    parse_mode: Arc<Option<ParseMode>>,
+    layers: Arc<Vec<Arc<dyn Layer>>>,
 }
 
 impl Bot {
@@ -94,8 +104,9 @@ impl Bot {
     {
         Self {
             token: Into::<Arc<str>>::into(Into::<String>::into(token)),
+            api_url: Arc::new(default_api_url()),
             client,
-            parse_mode: Arc::new(None),
+            layers: Arc::new(Vec::new()),
         }
     }
 }
@@ -124,6 +135,10 @@ pub(crate) fn build_sound_bot() -> Client {
     sound_bot().build().expect("creating reqwest::Client")
 }
 
+fn default_api_url() -> Url {
+    Url::parse(TELEGRAM_API_URL).expect("parsing the Telegram API URL")
+}
+
 fn get_env(env: &'static str) -> String {
     std::env::var(env).unwrap_or_else(|_| panic!("Cannot get the {} env variable", env))
 }
@@ -138,6 +153,47 @@ impl Bot {
     pub fn client(&self) -> &Client {
         &self.client
     }
+
+    /// Returns the URL of the Bot API server this bot sends requests to.
+    pub fn api_url(&self) -> &Url {
+        &self.api_url
+    }
+
+    /// Returns a bot, which sends all requests through `layer`.
+    ///
+    /// The layer is applied before the layers added previously and the layers
+    /// configured in [`BotBuilder`], so it sees the final result of a request
+    /// (e.g. after all retries).
+    ///
+    /// See [`teloxide::layers`].
+    ///
+    /// [`BotBuilder`]: crate::BotBuilder
+    /// [`teloxide::layers`]: crate::layers
+    #[must_use]
+    pub fn layer<L>(mut self, layer: L) -> Self
+    where
+        L: Layer,
+    {
+        let mut layers = Vec::with_capacity(self.layers.len() + 1);
+        layers.push(Arc::new(layer) as Arc<dyn Layer>);
+        layers.extend(self.layers.iter().cloned());
+
+        self.layers = Arc::new(layers);
+        self
+    }
+
+    /// Sends `request` through all the layers of this bot.
+    pub(crate) async fn execute(&self, request: RawRequest) -> ResponseResult<Box<RawValue>> {
+        Next::new(self, &self.layers).run(request).await
+    }
+}
+
+/// Returns `true` if a request named `method_name` sends a message.
+///
+/// Only such requests are limited by Telegram and they are not idempotent.
+pub(crate) fn sends_message(method_name: &str) -> bool {
+    (method_name.starts_with("send") && method_name != "sendChatAction")
+        || method_name == "forwardMessage"
 }
 
 /// A builder of [`Bot`], supporting some extra settings.
@@ -146,8 +202,11 @@ impl Bot {
 #[derive(Debug, Default)]
 pub struct BotBuilder {
     token: Option<String>,
+    api_url: Option<Url>,
     client: Option<Client>,
     parse_mode: Option<ParseMode>,
+    throttle: Option<Limits>,
+    retry: Option<Arc<dyn Layer>>,
 }
 
 impl BotBuilder {
@@ -187,6 +246,30 @@ impl BotBuilder {
         self
     }
 
+    /// Specifies a custom URL of the Bot API server.
+    ///
+    /// Otherwise, `https://api.telegram.org` will be used. It's useful to send
+    /// requests to a [self-hosted Bot API server] or to a mock server in
+    /// tests. Both requests and file downloads use this URL.
+    ///
+    /// ## Example
+    /// ```
+    /// use teloxide::BotBuilder;
+    ///
+    /// let bot = BotBuilder::new()
+    ///     .token("TOKEN")
+    ///     .api_url("http://localhost:8081".parse().unwrap())
+    ///     .build();
+    /// assert_eq!(bot.api_url().as_str(), "http://localhost:8081/");
+    /// ```
+    ///
+    /// [self-hosted Bot API server]: https://github.com/tdlib/telegram-bot-api
+    #[must_use]
+    pub fn api_url(mut self, api_url: Url) -> Self {
+        self.api_url = Some(api_url);
+        self
+    }
+
     /// Specifies [`ParseMode`], which will be used during all calls to:
     ///
     ///  - [`send_message`]
@@ -216,6 +299,55 @@ impl BotBuilder {
         self
     }
 
+    /// Enables throttling of outgoing requests according to `limits`.
+    ///
+    /// Requests which send messages (`send*`, except `sendChatAction`, and
+    /// `forwardMessage`) are queued per chat and globally, so that they don't
+    /// exceed Telegram limits. If Telegram still responds with
+    /// [`RequestError::RetryAfter`], all such requests are delayed for the
+    /// specified amount of time and the failed request is resent. The error
+    /// is returned from [`Request::send`] only if it persists after 3
+    /// attempts.
+    ///
+    /// Throttling is disabled by default.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use teloxide::{layers::Limits, BotBuilder};
+    ///
+    /// let bot = BotBuilder::new().throttle(Limits::default()).build();
+    /// ```
+    ///
+    /// [`RequestError::RetryAfter`]: crate::RequestError::RetryAfter
+    /// [`Request::send`]: crate::requests::Request::send
+    #[must_use]
+    pub fn throttle(mut self, limits: Limits) -> Self {
+        self.throttle = Some(limits);
+        self
+    }
+
+    /// Specifies a policy of retrying failed requests.
+    ///
+    /// Otherwise, failed requests aren't retried.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use teloxide::{layers::ExponentialBackoff, BotBuilder};
+    ///
+    /// let bot = BotBuilder::new()
+    ///     .retry_policy(ExponentialBackoff::new().max_attempts(5).max_delay(Duration::from_secs(10)))
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn retry_policy<P>(mut self, retry_policy: P) -> Self
+    where
+        P: RetryPolicy,
+    {
+        self.retry = Some(Arc::new(Retry::new(retry_policy)));
+        self
+    }
+
     /// Builds [`Bot`].
     ///
     /// This method will attempt to build a new client with a proxy, specified
@@ -233,10 +365,20 @@ impl BotBuilder {
     /// [`reqwest::Proxy::all`]: https://docs.rs/reqwest/latest/reqwest/struct.Proxy.html#method.all
     #[must_use]
     pub fn build(self) -> Bot {
+        let Self { token, api_url, client, parse_mode, throttle, retry } = self;
+
+        let parse_mode = parse_mode
+            .map(|parse_mode| Arc::new(DefaultParseMode::new(parse_mode)) as Arc<dyn Layer>);
+        let throttle = throttle.map(|limits| Arc::new(Throttle::new(limits)) as Arc<dyn Layer>);
+
+        // Throttling is the innermost layer, so that every retry is throttled.
+        let layers = parse_mode.into_iter().chain(retry).chain(throttle).collect();
+
         Bot {
-            client: self.client.unwrap_or_else(crate::utils::client_from_env),
-            token: self.token.unwrap_or_else(|| get_env(TELOXIDE_TOKEN)).into(),
-            parse_mode: Arc::new(self.parse_mode),
+            client: client.unwrap_or_else(crate::utils::client_from_env),
+            token: token.unwrap_or_else(|| get_env(TELOXIDE_TOKEN)).into(),
+            api_url: Arc::new(api_url.unwrap_or_else(default_api_url)),
+            layers: Arc::new(layers),
         }
     }
 }
@@ -0,0 +1,391 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use thiserror::Error;
+
+use crate::{
+    dispatching::{Middleware, MissingDependency, UpdateWithCx},
+    requests::{Request, ResponseResult},
+    types::{AdminRight, ChatMember, Update, UpdateKind},
+    Bot, RequestError,
+};
+
+/// Cached administrators by bot and chat IDs.
+///
+/// `tokio::sync::Mutex` is held while administrators are requested, so that
+/// only one request per chat is sent.
+struct Chats {
+    entries: HashMap<(i32, i64), Arc<tokio::sync::Mutex<Option<Cached>>>>,
+    swept: Instant,
+}
+
+impl Chats {
+    /// Removes expired entries, at most once per `ttl`.
+    ///
+    /// Entries being requested right now are kept.
+    fn sweep(&mut self, ttl: Duration) {
+        if self.swept.elapsed() < ttl {
+            return;
+        }
+        self.swept = Instant::now();
+        self.entries.retain(|_, chat| match chat.try_lock() {
+            Ok(cached) => matches!(&*cached, Some(cached) if cached.fetched.elapsed() < ttl),
+            Err(_) => true,
+        });
+    }
+}
+
+struct Cached {
+    admins: Arc<Vec<ChatMember>>,
+    fetched: Instant,
+}
+
+/// A cache of administrators of chats.
+///
+/// Administrators of a chat are requested via `getChatAdministrators` once
+/// and then kept for 10 minutes by default, see [`AdminCache::ttl`].
+/// Concurrent lookups in the same chat share a single request. Expired
+/// entries are removed on later lookups, so the cache doesn't grow with every
+/// chat ever seen.
+///
+/// Administrators are cached per bot, so one cache can be shared by the
+/// dispatchers of a [`MultiDispatcher`].
+///
+/// The cache is enabled via [`Dispatcher::admin_cache`], which adds it both
+/// as a dependency (for [`UpdateWithCx::sender_is_admin`] and the other
+/// helpers) and as a [`Middleware`]. The middleware invalidates a chat when
+/// users join or leave it and when a group is migrated to a supergroup.
+/// Telegram doesn't notify bots about promotions and demotions, so those are
+/// noticed only after the TTL expires or [`AdminCache::invalidate`] is
+/// called.
+///
+/// ## Example
+/// ```
+/// use teloxide::{
+///     dispatching::{AdminCache, AdminCheckError, Router},
+///     prelude::*,
+///     types::AdminRight,
+/// };
+///
+/// async fn ban(cx: UpdateWithCx<Message>) -> Result<(), AdminCheckError> {
+///     if !cx.sender_can(AdminRight::RestrictMembers).await? {
+///         cx.reply_to("You cannot ban users").send().await?;
+///         return Ok(());
+///     }
+///     if !cx.bot_can(AdminRight::RestrictMembers).await? {
+///         cx.reply_to("I cannot ban users, promote me first").send().await?;
+///         return Ok(());
+///     }
+///
+///     // Ban a user...
+///     Ok(())
+/// }
+///
+/// # async fn run() {
+/// Dispatcher::new(Bot::from_env())
+///     .admin_cache(AdminCache::new())
+///     .messages_handler(Router::new().endpoint(ban))
+///     .dispatch()
+///     .await;
+/// # }
+/// ```
+///
+/// [`AdminCache::ttl`]: crate::dispatching::AdminCache::ttl
+/// [`Dispatcher::admin_cache`]: crate::dispatching::Dispatcher::admin_cache
+/// [`UpdateWithCx::sender_is_admin`]: crate::dispatching::UpdateWithCx::sender_is_admin
+/// [`Middleware`]: crate::dispatching::Middleware
+/// [`AdminCache::invalidate`]: crate::dispatching::AdminCache::invalidate
+/// [`MultiDispatcher`]: crate::dispatching::MultiDispatcher
+#[derive(Clone)]
+pub struct AdminCache {
+    ttl: Duration,
+    chats: Arc<Mutex<Chats>>,
+}
+
+impl AdminCache {
+    #[must_use]
+    pub fn new() -> Self {
+        let chats = Chats { entries: HashMap::new(), swept: Instant::now() };
+        Self { ttl: Duration::from_secs(10 * 60), chats: Arc::new(Mutex::new(chats)) }
+    }
+
+    /// Sets how long administrators of a chat are cached.
+    ///
+    /// By default, it's 10 minutes.
+    #[must_use]
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Returns administrators of `chat_id`, including the creator.
+    pub async fn admins(&self, bot: &Bot, chat_id: i64) -> ResponseResult<Arc<Vec<ChatMember>>> {
+        let bot_id = bot_id(bot).await?;
+        let chat = {
+            let mut chats = self.chats.lock().unwrap();
+            chats.sweep(self.ttl);
+            Arc::clone(chats.entries.entry((bot_id, chat_id)).or_default())
+        };
+        let mut cached = chat.lock().await;
+
+        if let Some(cached) = &*cached {
+            if cached.fetched.elapsed() < self.ttl {
+                return Ok(Arc::clone(&cached.admins));
+            }
+        }
+
+        let admins = Arc::new(bot.get_chat_administrators(chat_id).send().await?);
+        *cached = Some(Cached { admins: Arc::clone(&admins), fetched: Instant::now() });
+        Ok(admins)
+    }
+
+    /// Returns `user_id` as an administrator of `chat_id`, if they are one.
+    pub async fn admin(
+        &self,
+        bot: &Bot,
+        chat_id: i64,
+        user_id: i32,
+    ) -> ResponseResult<Option<ChatMember>> {
+        let admins = self.admins(bot, chat_id).await?;
+        Ok(admins.iter().find(|member| member.user.id == user_id).cloned())
+    }
+
+    /// Returns `true` if `user_id` is the creator or an administrator of
+    /// `chat_id`.
+    pub async fn is_admin(&self, bot: &Bot, chat_id: i64, user_id: i32) -> ResponseResult<bool> {
+        Ok(self.admin(bot, chat_id, user_id).await?.is_some())
+    }
+
+    /// Returns `true` if `user_id` has `right` in `chat_id`.
+    ///
+    /// See [`ChatMember::can`].
+    ///
+    /// [`ChatMember::can`]: crate::types::ChatMember::can
+    pub async fn can(
+        &self,
+        bot: &Bot,
+        chat_id: i64,
+        user_id: i32,
+        right: AdminRight,
+    ) -> ResponseResult<bool> {
+        let admin = self.admin(bot, chat_id, user_id).await?;
+        Ok(matches!(admin, Some(member) if member.can(right)))
+    }
+
+    /// Removes cached administrators of `chat_id` for all bots, so that they
+    /// are requested again on the next lookup.
+    pub fn invalidate(&self, chat_id: i64) {
+        self.chats.lock().unwrap().entries.retain(|&(_, id), _| id != chat_id);
+    }
+}
+
+/// Returns the ID of `bot`.
+///
+/// A token starts with the bot's ID, so `getMe` is rarely needed.
+pub(crate) async fn bot_id(bot: &Bot) -> ResponseResult<i32> {
+    match bot.token().split(':').next().and_then(|id| id.parse().ok()) {
+        Some(id) => Ok(id),
+        None => Ok(bot.get_me().send().await?.user.id),
+    }
+}
+
+impl Default for AdminCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An error returned from [`UpdateWithCx::sender_is_admin`] and the other
+/// helpers.
+///
+/// [`UpdateWithCx::sender_is_admin`]: crate::dispatching::UpdateWithCx::sender_is_admin
+#[derive(Debug, Error)]
+pub enum AdminCheckError {
+    #[error("A request error: {0}")]
+    Request(#[from] RequestError),
+
+    /// The cache hasn't been enabled via [`Dispatcher::admin_cache`].
+    ///
+    /// [`Dispatcher::admin_cache`]: crate::dispatching::Dispatcher::admin_cache
+    #[error("{0}")]
+    NotEnabled(#[from] MissingDependency),
+}
+
+#[async_trait::async_trait]
+impl Middleware for AdminCache {
+    async fn handle(&self, cx: UpdateWithCx<Update>) -> Option<Update> {
+        if let UpdateKind::Message(message) = &cx.update.kind {
+            if message.new_chat_members().is_some() || message.left_chat_member().is_some() {
+                self.invalidate(message.chat.id);
+            }
+            if let Some(chat_id) = message.migrate_to_chat_id().or(message.migrate_from_chat_id()) {
+                self.invalidate(message.chat.id);
+                self.invalidate(chat_id);
+            }
+        }
+
+        Some(cx.update)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dispatching::{Dependencies, Dispatcher},
+        error_handlers::IgnoringErrorHandlerSafe,
+        testing::{self, mock_user, MockMessage, MockServer, MOCK_BOT_ID},
+        types::{ChatMemberStatus, Message, User},
+        BotBuilder,
+    };
+    use futures::future;
+    use serde_json::json;
+
+    fn admins() -> Vec<ChatMember> {
+        vec![
+            ChatMember::new(User::new(1, false, "Creator"), ChatMemberStatus::Creator),
+            ChatMember::new(User::new(MOCK_BOT_ID, true, "Bot"), ChatMemberStatus::Administrator)
+                .can_delete_messages(true),
+        ]
+    }
+
+    fn count(server: &MockServer) -> usize {
+        server.requests().iter().filter(|request| request.method == "getChatAdministrators").count()
+    }
+
+    #[tokio::test]
+    async fn admins_are_cached() {
+        let server = MockServer::start();
+        let bot = server.bot();
+        let cache = AdminCache::new();
+        server.respond("getChatAdministrators", admins());
+
+        let (first, second) =
+            future::join(cache.is_admin(&bot, -1, 1), cache.is_admin(&bot, -1, 2)).await;
+        assert!(first.unwrap());
+        assert!(!second.unwrap());
+        assert!(cache.can(&bot, -1, MOCK_BOT_ID, AdminRight::DeleteMessages).await.unwrap());
+        assert!(!cache.can(&bot, -1, MOCK_BOT_ID, AdminRight::PinMessages).await.unwrap());
+        assert_eq!(count(&server), 1);
+        assert_eq!(server.requests()[0].params["chat_id"], -1);
+
+        let cache = cache.ttl(Duration::from_millis(0));
+        server.respond("getChatAdministrators", admins());
+        cache.admins(&bot, -1).await.unwrap();
+        assert_eq!(count(&server), 2);
+    }
+
+    #[tokio::test]
+    async fn service_messages_invalidate_chats() {
+        let server = MockServer::start();
+        let bot = server.bot();
+        let cache = AdminCache::new();
+
+        for chat_id in &[-1, -2, -3] {
+            server.respond("getChatAdministrators", admins());
+            cache.admins(&bot, *chat_id).await.unwrap();
+        }
+
+        let joined: Message = serde_json::from_value(json!({
+            "message_id": 1,
+            "date": 0,
+            "chat": { "id": -1, "type": "group", "title": "Group" },
+            "from": mock_user(),
+            "new_chat_members": [mock_user()],
+        }))
+        .unwrap();
+        let text = MockMessage::new("text").chat_id(-3).build();
+
+        Dispatcher::new(bot.clone())
+            .admin_cache(cache.clone())
+            .dispatch_with_listener(
+                testing::updates(vec![UpdateKind::Message(joined), UpdateKind::Message(text)]),
+                IgnoringErrorHandlerSafe::new(),
+            )
+            .await;
+
+        let chats = cache.chats.lock().unwrap();
+        assert!(!chats.entries.contains_key(&(MOCK_BOT_ID, -1)));
+        assert!(chats.entries.contains_key(&(MOCK_BOT_ID, -2)));
+        assert!(chats.entries.contains_key(&(MOCK_BOT_ID, -3)));
+    }
+
+    #[tokio::test]
+    async fn bots_have_separate_admins() {
+        let server = MockServer::start();
+        let bot = server.bot();
+        let other = BotBuilder::new().token("42:OTHER").api_url(server.url()).build();
+        let cache = AdminCache::new();
+
+        server.respond("getChatAdministrators", admins());
+        assert!(cache.can(&bot, -1, MOCK_BOT_ID, AdminRight::DeleteMessages).await.unwrap());
+        server.respond("getChatAdministrators", Vec::<ChatMember>::new());
+        assert!(!cache.is_admin(&other, -1, 1).await.unwrap());
+        assert_eq!(count(&server), 2);
+
+        cache.invalidate(-1);
+        assert!(cache.chats.lock().unwrap().entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn expired_chats_are_removed() {
+        let server = MockServer::start();
+        let bot = server.bot();
+        let cache = AdminCache::new().ttl(Duration::from_millis(50));
+
+        server.respond("getChatAdministrators", admins());
+        cache.admins(&bot, -1).await.unwrap();
+        tokio::time::delay_for(Duration::from_millis(100)).await;
+
+        server.respond("getChatAdministrators", admins());
+        cache.admins(&bot, -2).await.unwrap();
+        let chats = cache.chats.lock().unwrap();
+        assert!(!chats.entries.contains_key(&(MOCK_BOT_ID, -1)));
+        assert!(chats.entries.contains_key(&(MOCK_BOT_ID, -2)));
+    }
+
+    #[tokio::test]
+    async fn helpers_check_senders_and_the_bot() {
+        let server = MockServer::start();
+        let cache = AdminCache::new();
+        server.respond("getChatAdministrators", admins());
+
+        let cx = |message| UpdateWithCx {
+            bot: server.bot(),
+            update: message,
+            dependencies: Dependencies::new().with(cache.clone()),
+            completion: Default::default(),
+        };
+        let group: Message = serde_json::from_value(json!({
+            "message_id": 1,
+            "date": 0,
+            "chat": { "id": -1, "type": "group", "title": "Group" },
+            "from": User::new(1, false, "Creator"),
+            "text": "/ban",
+        }))
+        .unwrap();
+        let private = MockMessage::new("/ban").from(User::new(1, false, "Creator")).build();
+
+        let group = cx(group);
+        assert!(group.sender_is_admin().await.unwrap());
+        assert!(group.sender_can(AdminRight::PinMessages).await.unwrap());
+        assert!(group.bot_can(AdminRight::DeleteMessages).await.unwrap());
+        assert!(!group.bot_can(AdminRight::RestrictMembers).await.unwrap());
+
+        let private = cx(private);
+        assert!(!private.sender_is_admin().await.unwrap());
+        assert!(!private.bot_can(AdminRight::DeleteMessages).await.unwrap());
+
+        assert_eq!(count(&server), 1);
+
+        let disabled = UpdateWithCx { dependencies: Dependencies::new(), ..group };
+        assert!(matches!(disabled.sender_is_admin().await, Err(AdminCheckError::NotEnabled(_))));
+        assert!(matches!(
+            disabled.bot_can(AdminRight::DeleteMessages).await,
+            Err(AdminCheckError::NotEnabled(_))
+        ));
+    }
+}
@@ -0,0 +1,89 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use crate::{dispatching::update_listeners::AckGuard, metrics::HandlingGuard};
+
+/// A guard of handling of an update, stored in its [`UpdateWithCx`].
+///
+/// [`Dispatcher`] creates a completion for every update. The update is
+/// considered handled after its completion and all the clones of it have been
+/// dropped, which normally happens when a handler drops the [`UpdateWithCx`].
+/// Then the update is acknowledged to its update listener (see
+/// [`Acknowledger`]) and the time of its handling is recorded (see the
+/// [`metrics`] module). A media group is handled after the [`UpdateWithCx`] of
+/// the whole group has been dropped.
+///
+/// [`Dependencies`] don't hold a completion, so stashing `cx.dependencies`
+/// (e.g. in a scheduled job) doesn't delay either. To handle an update in a
+/// spawned task, move the [`UpdateWithCx`] (or a clone of its completion)
+/// into the task.
+///
+/// An update, whose handler waits for a reply via [`UpdateWithCx::ask`] or
+/// [`UpdateWithCx::wait_reply`], is acknowledged when waiting starts, since
+/// an update listener may not receive the reply until then.
+///
+/// A default completion guards nothing, e.g. for an [`UpdateWithCx`]
+/// constructed in tests.
+///
+/// [`UpdateWithCx`]: crate::dispatching::UpdateWithCx
+/// [`Dispatcher`]: crate::dispatching::Dispatcher
+/// [`Acknowledger`]: crate::dispatching::update_listeners::Acknowledger
+/// [`metrics`]: crate::metrics
+/// [`Dependencies`]: crate::dispatching::Dependencies
+/// [`UpdateWithCx::ask`]: crate::dispatching::UpdateWithCx::ask
+/// [`UpdateWithCx::wait_reply`]: crate::dispatching::UpdateWithCx::wait_reply
+#[derive(Clone, Default)]
+pub struct Completion {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    ack: Mutex<Option<AckGuard>>,
+    handling: Option<HandlingGuard>,
+
+    /// Completions of the messages of a media group.
+    parts: Vec<Completion>,
+}
+
+impl Completion {
+    pub(crate) fn new(ack: Option<AckGuard>, handling: Option<HandlingGuard>) -> Self {
+        Self { inner: Arc::new(Inner { ack: Mutex::new(ack), handling, parts: Vec::new() }) }
+    }
+
+    /// Returns a completion of updates, which are handled together.
+    pub(crate) fn group(parts: Vec<Completion>) -> Self {
+        Self { inner: Arc::new(Inner { ack: Mutex::new(None), handling: None, parts }) }
+    }
+
+    /// Acknowledges the update before it has been handled.
+    pub(crate) fn acknowledge(&self) {
+        self.inner.ack.lock().unwrap().take();
+        self.inner.parts.iter().for_each(Completion::acknowledge);
+    }
+
+    /// Marks the update as failed in metrics.
+    pub(crate) fn fail(&self) {
+        if let Some(handling) = &self.inner.handling {
+            handling.fail();
+        }
+        self.inner.parts.iter().for_each(Completion::fail);
+    }
+
+    /// Records the depth of the queue, which the update has been pushed to.
+    pub(crate) fn record_queue_depth(&self, depth: usize) {
+        match (&self.inner.handling, self.inner.parts.first()) {
+            (Some(handling), _) => handling.record_queue_depth(depth),
+            (None, Some(part)) => part.record_queue_depth(depth),
+            (None, None) => {}
+        }
+    }
+}
+
+impl fmt::Debug for Completion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Completion")
+    }
+}
@@ -0,0 +1,313 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use thiserror::Error;
+use tokio::sync::oneshot;
+
+use crate::{
+    dispatching::{Middleware, MissingDependency, UpdateWithCx},
+    types::{Message, Update, UpdateKind},
+    RequestError,
+};
+
+/// A chat ID and an ID of a user (if any) whose reply is awaited.
+type Key = (i64, Option<i32>);
+
+struct Waiter {
+    id: u64,
+    tx: oneshot::Sender<Message>,
+}
+
+/// Replies awaited by handlers via [`UpdateWithCx::ask`].
+///
+/// Conversations are enabled via [`Dispatcher::conversations`], which adds
+/// them both as a dependency and as a [`Middleware`]. While a handler waits
+/// for a reply, the next message from the same user in the same chat is
+/// passed to the handler instead of the other handlers (and the middlewares
+/// added after the conversations). Any message is a reply, so check it
+/// yourself, e.g. for a `/cancel` command.
+///
+/// Waiting is cancelled by dropping the future of [`UpdateWithCx::ask`], by
+/// [`Conversations::cancel`] or by a new [`UpdateWithCx::ask`] in the same
+/// chat with the same user. It times out after 5 minutes by default, see
+/// [`Conversations::timeout`].
+///
+/// An update of a waiting handler is acknowledged to its update listener
+/// when waiting starts, see [`Completion`].
+///
+/// ## Example
+/// ```
+/// use teloxide::{
+///     dispatching::{AskError, Conversations, Router},
+///     prelude::*,
+/// };
+///
+/// async fn register(cx: UpdateWithCx<Message>) -> Result<(), AskError> {
+///     let name = cx.ask("What's your name?").await?;
+///     let age = cx.ask("How old are you?").await?;
+///
+///     let text = format!("{}, {}", name.text().unwrap_or("?"), age.text().unwrap_or("?"));
+///     cx.answer_str(text).await?;
+///     Ok(())
+/// }
+///
+/// # async fn run() {
+/// Dispatcher::new(Bot::from_env())
+///     .conversations(Conversations::new())
+///     .messages_handler(Router::new().endpoint(register))
+///     .dispatch()
+///     .await;
+/// # }
+/// ```
+///
+/// [`UpdateWithCx::ask`]: crate::dispatching::UpdateWithCx::ask
+/// [`Dispatcher::conversations`]: crate::dispatching::Dispatcher::conversations
+/// [`Middleware`]: crate::dispatching::Middleware
+/// [`Conversations::cancel`]: crate::dispatching::Conversations::cancel
+/// [`Conversations::timeout`]: crate::dispatching::Conversations::timeout
+/// [`Completion`]: crate::dispatching::Completion
+#[derive(Clone)]
+pub struct Conversations {
+    timeout: Duration,
+    waiters: Arc<Mutex<HashMap<Key, Waiter>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Conversations {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            timeout: Duration::from_secs(5 * 60),
+            waiters: Arc::new(Mutex::new(HashMap::new())),
+            next_id: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Sets how long a reply is awaited.
+    ///
+    /// By default, it's 5 minutes.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Cancels waiting for a reply of `user_id` in `chat_id`.
+    ///
+    /// Returns `true` if a reply has been awaited.
+    pub fn cancel(&self, chat_id: i64, user_id: Option<i32>) -> bool {
+        self.waiters.lock().unwrap().remove(&(chat_id, user_id)).is_some()
+    }
+
+    /// Starts waiting for the next message of `user_id` in `chat_id`.
+    ///
+    /// Messages are awaited right after this call, so a reply to a question
+    /// sent afterwards cannot be missed.
+    pub(crate) fn expect(&self, chat_id: i64, user_id: Option<i32>) -> Reply {
+        let key = (chat_id, user_id);
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+
+        // A previous waiter is dropped, so its `ask` is cancelled.
+        self.waiters.lock().unwrap().insert(key, Waiter { id, tx });
+        Reply { rx, timeout: self.timeout, waiters: Arc::clone(&self.waiters), key, id }
+    }
+}
+
+impl Default for Conversations {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An awaited reply.
+///
+/// Waiting is cancelled when it's dropped.
+pub(crate) struct Reply {
+    rx: oneshot::Receiver<Message>,
+    timeout: Duration,
+    waiters: Arc<Mutex<HashMap<Key, Waiter>>>,
+    key: Key,
+    id: u64,
+}
+
+impl Reply {
+    pub(crate) async fn recv(mut self) -> Result<Message, AskError> {
+        match tokio::time::timeout(self.timeout, &mut self.rx).await {
+            Ok(Ok(message)) => Ok(message),
+            Ok(Err(_)) => Err(AskError::Cancelled),
+            Err(_) => Err(AskError::Timeout),
+        }
+    }
+}
+
+impl Drop for Reply {
+    fn drop(&mut self) {
+        let mut waiters = self.waiters.lock().unwrap();
+        if waiters.get(&self.key).map(|waiter| waiter.id) == Some(self.id) {
+            waiters.remove(&self.key);
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for Conversations {
+    async fn handle(&self, cx: UpdateWithCx<Update>) -> Option<Update> {
+        let Update { id, kind } = cx.update;
+
+        let message = match kind {
+            UpdateKind::Message(message) => message,
+            kind => return Some(Update { id, kind }),
+        };
+
+        let key = (message.chat.id, message.from().map(|user| user.id));
+        let waiter = self.waiters.lock().unwrap().remove(&key);
+
+        let message = match waiter {
+            Some(waiter) => match waiter.tx.send(message) {
+                Ok(()) => {
+                    log::trace!("The update {} is passed to a waiting handler", id);
+                    return None;
+                }
+                // The waiting handler has just given up.
+                Err(message) => message,
+            },
+            None => message,
+        };
+
+        Some(Update { id, kind: UpdateKind::Message(message) })
+    }
+}
+
+/// An error returned from [`UpdateWithCx::ask`].
+///
+/// [`UpdateWithCx::ask`]: crate::dispatching::UpdateWithCx::ask
+#[derive(Debug, Error)]
+pub enum AskError {
+    #[error("A request error: {0}")]
+    Request(#[from] RequestError),
+
+    #[error("A reply has not been received in time")]
+    Timeout,
+
+    #[error("Waiting for a reply has been cancelled")]
+    Cancelled,
+
+    /// Conversations haven't been enabled via [`Dispatcher::conversations`].
+    ///
+    /// [`Dispatcher::conversations`]: crate::dispatching::Dispatcher::conversations
+    #[error("{0}")]
+    NotEnabled(#[from] MissingDependency),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dispatching::{update_listeners, Dispatcher, DispatcherHandlerRx},
+        error_handlers::IgnoringErrorHandlerSafe,
+        testing::{MockMessage, MockServer},
+    };
+    use futures::{stream, StreamExt};
+    use std::convert::Infallible;
+    use tokio::time::delay_for;
+
+    #[tokio::test]
+    async fn replies_are_passed_to_waiting_handlers() {
+        let server = MockServer::start();
+        let conversations = Conversations::new();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        // Replies are sent after the questions have been asked.
+        let updates = stream::iter(vec![
+            MockMessage::new("/start").chat_id(1).build(),
+            MockMessage::new("/start").chat_id(2).build(),
+            MockMessage::new("Alice").chat_id(1).build(),
+            MockMessage::new("hello").chat_id(3).build(),
+        ])
+        .enumerate()
+        .then(|(i, message)| async move {
+            if i == 2 {
+                delay_for(Duration::from_millis(50)).await;
+            }
+            Ok::<_, Infallible>(Update::new(i as i32, UpdateKind::Message(message)))
+        });
+
+        Dispatcher::new(server.bot())
+            .conversations(conversations.clone().timeout(Duration::from_millis(200)))
+            .messages_handler(move |rx: DispatcherHandlerRx<Message>| {
+                rx.for_each_concurrent(None, move |cx| {
+                    let tx = tx.clone();
+
+                    async move {
+                        let result = match cx.update.text() {
+                            Some("/start") => cx
+                                .ask("Name?")
+                                .await
+                                .map(|reply| reply.text().unwrap().to_owned())
+                                .map_err(|error| error.to_string()),
+                            text => Ok(format!("handled {}", text.unwrap())),
+                        };
+                        tx.send((cx.update.chat.id, result)).unwrap();
+                    }
+                })
+            })
+            .dispatch_with_listener(
+                update_listeners::from_stream(updates),
+                IgnoringErrorHandlerSafe::new(),
+            )
+            .await;
+
+        let mut results = Vec::new();
+        while let Some(result) = rx.recv().await {
+            results.push(result);
+        }
+        results.sort();
+
+        assert_eq!(
+            results,
+            [
+                (1, Ok("Alice".to_owned())),
+                (2, Err("A reply has not been received in time".to_owned())),
+                (3, Ok("handled hello".to_owned())),
+            ]
+        );
+        assert_eq!(server.requests().len(), 2);
+        assert!(conversations.waiters.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn asking_without_conversations_is_an_error() {
+        let server = MockServer::start();
+        let cx = UpdateWithCx {
+            bot: server.bot(),
+            update: MockMessage::new("/start").build(),
+            dependencies: Default::default(),
+            completion: Default::default(),
+        };
+
+        assert!(matches!(cx.ask("Name?").await, Err(AskError::NotEnabled(_))));
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn waiting_is_cancelled() {
+        let conversations = Conversations::new();
+
+        let waiting = tokio::spawn({
+            let conversations = conversations.clone();
+            async move { conversations.expect(1, Some(2)).recv().await }
+        });
+        delay_for(Duration::from_millis(10)).await;
+
+        assert!(conversations.cancel(1, Some(2)));
+        assert!(matches!(waiting.await.unwrap(), Err(AskError::Cancelled)));
+        assert!(!conversations.cancel(1, Some(2)));
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::HashMap,
+    fmt,
+    sync::Arc,
+};
+
+use thiserror::Error;
+
+/// A map of values of distinct types, shared between handlers.
+///
+/// Dependencies are registered via [`Dispatcher::dependency`] and extracted
+/// in handlers by their types via [`UpdateWithCx::dependency`]. A dependency
+/// is cloned on extraction, so expensive values (database pools, caches)
+/// should be wrapped into [`Arc`].
+///
+/// ## Example
+/// ```
+/// use std::sync::Arc;
+/// use teloxide::dispatching::Dependencies;
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Config {
+///     admin_id: i32,
+/// }
+///
+/// let deps = Dependencies::new().with(Config { admin_id: 42 }).with(Arc::new(vec![1, 2, 3]));
+///
+/// assert_eq!(deps.get::<Config>(), Some(Config { admin_id: 42 }));
+/// assert_eq!(deps.get::<Arc<Vec<i32>>>().unwrap().len(), 3);
+/// assert_eq!(deps.get::<String>(), None);
+/// ```
+///
+/// [`Dispatcher::dependency`]: crate::dispatching::Dispatcher::dependency
+/// [`UpdateWithCx::dependency`]: crate::dispatching::UpdateWithCx::dependency
+/// [`Arc`]: std::sync::Arc
+#[derive(Clone, Default)]
+pub struct Dependencies {
+    map: Arc<HashMap<TypeId, Entry>>,
+}
+
+/// A name of a type and a value of this type.
+type Entry = (&'static str, Arc<dyn Any + Send + Sync>);
+
+impl Dependencies {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `value`, replacing a previous value of the same type.
+    #[must_use]
+    pub fn with<T>(mut self, value: T) -> Self
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.map).insert(TypeId::of::<T>(), (type_name::<T>(), Arc::new(value)));
+        self
+    }
+
+    /// Adds all the values of `other`, replacing values of the same types.
+    #[must_use]
+    pub fn extend(mut self, other: Dependencies) -> Self {
+        let map = Arc::make_mut(&mut self.map);
+        map.extend(other.map.iter().map(|(id, value)| (*id, value.clone())));
+        self
+    }
+
+    /// Returns a value of type `T`, if any.
+    #[must_use]
+    pub fn get<T>(&self) -> Option<T>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.map.get(&TypeId::of::<T>())?.1.downcast_ref::<T>().cloned()
+    }
+
+    /// Returns a value of type `T` or [`MissingDependency`] if there's none.
+    ///
+    /// [`MissingDependency`]: crate::dispatching::MissingDependency
+    pub fn require<T>(&self) -> Result<T, MissingDependency>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.get().ok_or_else(|| MissingDependency(type_name::<T>()))
+    }
+}
+
+impl fmt::Debug for Dependencies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.map.values().map(|(name, _)| name)).finish()
+    }
+}
+
+/// An error returned when a required dependency hasn't been registered.
+///
+/// Contains the name of the missing type.
+#[derive(Debug, Clone, Error)]
+#[error("A dependency of type {0} is not registered")]
+pub struct MissingDependency(pub &'static str);
+
+/// A tuple of dependencies extracted together, e.g. `(Arc<Db>, Config)`.
+///
+/// Implemented for tuples of up to 6 types. See [`Router::endpoint_with`].
+///
+/// [`Router::endpoint_with`]: crate::dispatching::Router::endpoint_with
+pub trait FromDependencies: Sized {
+    /// Extracts all the values or returns the first missing one.
+    fn from_dependencies(dependencies: &Dependencies) -> Result<Self, MissingDependency>;
+}
+
+macro_rules! impl_from_dependencies {
+    ($($T:ident),+) => {
+        impl<$($T),+> FromDependencies for ($($T,)+)
+        where
+            $($T: Clone + Send + Sync + 'static),+
+        {
+            fn from_dependencies(dependencies: &Dependencies) -> Result<Self, MissingDependency> {
+                Ok(($(dependencies.require::<$T>()?,)+))
+            }
+        }
+    };
+}
+
+impl_from_dependencies!(A);
+impl_from_dependencies!(A, B);
+impl_from_dependencies!(A, B, C);
+impl_from_dependencies!(A, B, C, D);
+impl_from_dependencies!(A, B, C, D, E);
+impl_from_dependencies!(A, B, C, D, E, F);
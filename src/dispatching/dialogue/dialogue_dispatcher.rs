@@ -1,8 +1,14 @@
-use crate::dispatching::{
-    dialogue::{
-        DialogueDispatcherHandler, DialogueStage, DialogueWithCx, GetChatId, InMemStorage, Storage,
+use crate::{
+    dispatching::{
+        dialogue::{
+            DialogueDispatcherHandler, DialogueStage, DialogueWithCx, GetChatId, InMemStorage,
+            Storage,
+        },
+        panics::catch_panic,
+        queue::{self, QueueOptions},
+        DispatcherHandler, DispatcherHandlerRx, UpdateWithCx,
     },
-    DispatcherHandler, UpdateWithCx,
+    metrics::Metrics,
 };
 use std::{convert::Infallible, marker::PhantomData};
 
@@ -25,15 +31,15 @@ use std::sync::{Arc, Mutex};
 pub struct DialogueDispatcher<D, S, H, Upd> {
     storage: Arc<S>,
     handler: Arc<H>,
+    queue_options: QueueOptions,
     _phantom: PhantomData<Mutex<D>>,
 
     /// A lock-free map to handle updates from the same chat sequentially, but
     /// concurrently from different chats.
     ///
-    /// A value is the TX part of an unbounded asynchronous MPSC channel. A
-    /// handler that executes updates from the same chat ID sequentially
-    /// handles the RX part.
-    senders: Arc<Map<i64, mpsc::UnboundedSender<UpdateWithCx<Upd>>>>,
+    /// A value is the TX part of a queue. A handler that executes updates from
+    /// the same chat ID sequentially handles the RX part.
+    senders: Arc<Map<i64, queue::Sender<UpdateWithCx<Upd>>>>,
 }
 
 impl<D, H, Upd> DialogueDispatcher<D, InMemStorage<D>, H, Upd>
@@ -51,6 +57,7 @@ where
         Self {
             storage: InMemStorage::new(),
             handler: Arc::new(handler),
+            queue_options: QueueOptions::unbounded(),
             senders: Arc::new(Map::new()),
             _phantom: PhantomData,
         }
@@ -71,58 +78,101 @@ where
         Self {
             storage,
             handler: Arc::new(handler),
+            queue_options: QueueOptions::unbounded(),
             senders: Arc::new(Map::new()),
             _phantom: PhantomData,
         }
     }
 
+    /// Sets options of per-chat queues of updates.
+    ///
+    /// By default, the queues are unbounded. Note that if a queue is full and
+    /// [`OverflowPolicy::Wait`] is used, updates from other chats are not
+    /// handed out either until the queue has free space.
+    ///
+    /// [`OverflowPolicy::Wait`]: crate::dispatching::queue::OverflowPolicy::Wait
+    #[must_use]
+    pub fn queue_options(mut self, options: QueueOptions) -> Self {
+        self.queue_options = options;
+        self
+    }
+
+    /// Spawns a worker for a new chat.
+    ///
+    /// `alive` is held by the worker until it finishes, see
+    /// [`DispatcherHandler::handle`].
     #[must_use]
-    fn new_tx(&self) -> mpsc::UnboundedSender<UpdateWithCx<Upd>> {
-        let (tx, rx) = mpsc::unbounded_channel();
+    fn new_tx(&self, alive: mpsc::UnboundedSender<Infallible>) -> queue::Sender<UpdateWithCx<Upd>> {
+        let (tx, rx) = queue::channel(self.queue_options);
 
         let storage = Arc::clone(&self.storage);
         let handler = Arc::clone(&self.handler);
         let senders = Arc::clone(&self.senders);
 
-        tokio::spawn(rx.for_each(move |cx: UpdateWithCx<Upd>| {
+        let worker = rx.for_each(move |cx: UpdateWithCx<Upd>| {
             let storage = Arc::clone(&storage);
             let handler = Arc::clone(&handler);
             let senders = Arc::clone(&senders);
 
             async move {
                 let chat_id = cx.update.chat_id();
-
-                let dialogue = Arc::clone(&storage)
-                    .remove_dialogue(chat_id)
-                    .await
-                    .map(Option::unwrap_or_default);
-
-                match handler.handle(DialogueWithCx { cx, dialogue }).await {
-                    DialogueStage::Next(new_dialogue) => {
-                        if let Ok(Some(_)) = storage.update_dialogue(chat_id, new_dialogue).await {
-                            panic!(
-                                "Oops, you have an bug in your Storage: update_dialogue returns \
-                                 Some after remove_dialogue"
-                            );
+                let (dependencies, completion) = (cx.dependencies.clone(), cx.completion.clone());
+
+                // If the handler panics, the dialogue is lost (it's already
+                // removed from `storage`), but the chat keeps being served.
+                catch_panic(&dependencies, &completion, async {
+                    let dialogue = Arc::clone(&storage)
+                        .remove_dialogue(chat_id)
+                        .await
+                        .map(Option::unwrap_or_default);
+
+                    match handler.handle(DialogueWithCx { cx, dialogue }).await {
+                        DialogueStage::Next(new_dialogue) => {
+                            if let Ok(Some(_)) =
+                                storage.update_dialogue(chat_id, new_dialogue).await
+                            {
+                                panic!(
+                                    "Oops, you have an bug in your Storage: update_dialogue \
+                                     returns Some after remove_dialogue"
+                                );
+                            }
+                        }
+                        DialogueStage::Exit => {
+                            // On the next .poll() call, the spawned future will
+                            // return Poll::Ready, because we are dropping the
+                            // sender right here:
+                            senders.remove(&chat_id);
+
+                            // We already removed a dialogue from `storage` (see
+                            // the beginning of this async block).
                         }
                     }
-                    DialogueStage::Exit => {
-                        // On the next .poll() call, the spawned future will
-                        // return Poll::Ready, because we are dropping the
-                        // sender right here:
-                        senders.remove(&chat_id);
-
-                        // We already removed a dialogue from `storage` (see
-                        // the beginning of this async block).
-                    }
-                }
+                })
+                .await;
             }
-        }));
+        });
+
+        tokio::spawn(async move {
+            worker.await;
+            drop(alive);
+        });
 
         tx
     }
 }
 
+impl<D, S, H, Upd> Clone for DialogueDispatcher<D, S, H, Upd> {
+    fn clone(&self) -> Self {
+        Self {
+            storage: Arc::clone(&self.storage),
+            handler: Arc::clone(&self.handler),
+            queue_options: self.queue_options,
+            senders: Arc::clone(&self.senders),
+            _phantom: PhantomData,
+        }
+    }
+}
+
 impl<D, S, H, Upd> DispatcherHandler<Upd> for DialogueDispatcher<D, S, H, Upd>
 where
     H: DialogueDispatcherHandler<Upd, D, S::Error> + Send + Sync + 'static,
@@ -131,34 +181,62 @@ where
     S: Storage<D> + Send + Sync + 'static,
     S::Error: Send + 'static,
 {
-    fn handle(self, updates: mpsc::UnboundedReceiver<UpdateWithCx<Upd>>) -> BoxFuture<'static, ()>
+    fn handle(self, updates: DispatcherHandlerRx<Upd>) -> BoxFuture<'static, ()>
     where
         UpdateWithCx<Upd>: 'static,
     {
         let this = Arc::new(self);
 
-        Box::pin(updates.for_each(move |cx| {
-            let this = Arc::clone(&this);
-            let chat_id = cx.update.chat_id();
-
-            match this.senders.get(&chat_id) {
-                // An old dialogue
-                Some(tx) => {
-                    if tx.1.send(cx).is_err() {
-                        panic!("We are not dropping a receiver or call .close() on it",);
-                    }
-                }
-                None => {
-                    let tx = this.new_tx();
-                    if tx.send(cx).is_err() {
-                        panic!("We are not dropping a receiver or call .close() on it",);
+        // Every worker holds a clone of `alive_tx`, so `alive_rx` is closed
+        // after all the workers have finished.
+        let (alive_tx, mut alive_rx) = mpsc::unbounded_channel::<Infallible>();
+
+        Box::pin(async move {
+            updates
+                .for_each({
+                    let this = Arc::clone(&this);
+
+                    move |cx| {
+                        let chat_id = cx.update.chat_id();
+
+                        let tx = match this.senders.get(&chat_id) {
+                            // An old dialogue
+                            Some(tx) => tx.1.clone(),
+                            None => {
+                                let tx = this.new_tx(alive_tx.clone());
+                                this.senders.insert(chat_id, tx.clone());
+                                tx
+                            }
+                        };
+
+                        let this = Arc::clone(&this);
+
+                        async move {
+                            let metrics = cx.dependencies.get::<Metrics>();
+
+                            if tx.send(cx).await.is_err() {
+                                log::error!("The worker of the chat {} has terminated", chat_id);
+                            }
+
+                            if let Some(Metrics(recorder)) = metrics {
+                                let depth = this.senders.iter().map(|entry| entry.1.len()).sum();
+                                recorder.record_queue_depth("dialogues", depth);
+                            }
+                        }
                     }
-                    this.senders.insert(chat_id, tx);
-                }
+                })
+                .await;
+
+            // The input stream is closed (e.g. the dispatcher is shutting
+            // down), so let the workers process the rest of updates and
+            // finish.
+            let chat_ids = this.senders.iter().map(|entry| entry.0).collect::<Vec<_>>();
+            for chat_id in chat_ids {
+                this.senders.remove(&chat_id);
             }
 
-            async {}
-        }))
+            while alive_rx.recv().await.is_some() {}
+        })
     }
 }
 
@@ -166,11 +244,11 @@ where
 mod tests {
     use super::*;
 
-    use crate::Bot;
+    use crate::{dispatching::Dependencies, Bot};
     use futures::{stream, StreamExt};
     use lazy_static::lazy_static;
     use tokio::{
-        sync::{mpsc, Mutex},
+        sync::Mutex,
         time::{delay_for, Duration},
     };
 
@@ -246,18 +324,23 @@ mod tests {
                 MyUpdate::new(3, 1611),
             ]
             .into_iter()
-            .map(|update| UpdateWithCx { update, bot: Bot::new("Doesn't matter here") })
+            .map(|update| UpdateWithCx {
+                update,
+                bot: Bot::new("Doesn't matter here"),
+                dependencies: Dependencies::new(),
+                completion: Default::default(),
+            })
             .collect::<Vec<UpdateWithCx<MyUpdate>>>(),
         );
 
-        let (tx, rx) = mpsc::unbounded_channel();
+        let (tx, rx) = queue::channel(QueueOptions::unbounded());
 
         updates
             .for_each(move |update| {
                 let tx = tx.clone();
 
                 async move {
-                    if tx.send(update).is_err() {
+                    if tx.send(update).await.is_err() {
                         panic!("tx.send(update) failed");
                     }
                 }
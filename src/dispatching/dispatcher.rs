@@ -1,42 +1,65 @@
 use crate::{
     dispatching::{
-        update_listeners, update_listeners::UpdateListener, DispatcherHandler, UpdateWithCx,
+        media_groups::MediaGroups,
+        panics::{catch_panic, report_panic, PanicHandler},
+        queue::{self, QueueOptions},
+        update_listeners,
+        update_listeners::{Acknowledger, UpdateListener},
+        AdminCache, Completion, Conversations, Dependencies, DispatcherHandler, HandlerPanic,
+        Middleware, StopToken, UpdateWithCx,
     },
     error_handlers::{ErrorHandler, LoggingErrorHandler},
+    metrics::{Metrics, Recorder},
     types::{
         CallbackQuery, ChosenInlineResult, InlineQuery, Message, Poll, PollAnswer,
-        PreCheckoutQuery, ShippingQuery, UpdateKind,
+        PreCheckoutQuery, ShippingQuery, Update, UpdateKind,
     },
     Bot,
 };
-use futures::StreamExt;
-use std::{fmt::Debug, sync::Arc};
-use tokio::sync::mpsc;
+use futures::{future, Stream, StreamExt};
+use serde_json::Value;
+use std::{
+    fmt::Debug,
+    mem,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+type Tx<Upd> = Option<queue::Sender<UpdateWithCx<Upd>>>;
+
+/// Spawns a handler and sets its queue when dispatching starts.
+type Starter = Box<dyn FnMut(&mut Dispatcher) + Send>;
 
-type Tx<Upd> = Option<mpsc::UnboundedSender<UpdateWithCx<Upd>>>;
+/// Subscribers of [`Dispatcher::handler_failures`].
+type FailureSubscribers = Arc<Mutex<Vec<mpsc::UnboundedSender<HandlerPanic>>>>;
 
 #[macro_use]
 mod macros {
     /// Pushes an update to a queue.
     macro_rules! send {
-        ($bot:expr, $tx:expr, $update:expr, $variant:expr) => {
-            send($bot, $tx, $update, stringify!($variant));
+        ($self:expr, $dependencies:expr, $completion:expr, $tx:expr, $update:expr, $variant:expr) => {
+            send($self.cx($update, $dependencies, $completion), $tx, stringify!($variant)).await;
         };
     }
 }
 
-fn send<'a, Upd>(bot: &'a Bot, tx: &'a Tx<Upd>, update: Upd, variant: &'static str)
+async fn send<Upd>(cx: UpdateWithCx<Upd>, tx: &Tx<Upd>, variant: &'static str)
 where
     Upd: Debug,
 {
     if let Some(tx) = tx {
-        if let Err(error) = tx.send(UpdateWithCx { bot: bot.clone(), update }) {
+        let completion = cx.completion.clone();
+
+        if let Err(error) = tx.send(cx).await {
             log::error!(
                 "The RX part of the {} channel is closed, but an update is received.\nError:{}\n",
                 variant,
                 error
             );
         }
+
+        completion.record_queue_depth(tx.len());
     }
 }
 
@@ -44,8 +67,30 @@ where
 ///
 /// See the [module-level documentation](crate::dispatching) for the design
 /// overview.
+///
+/// ## Graceful shutdown
+/// A dispatcher stops after [`StopToken::stop`] is called on a token returned
+/// from [`Dispatcher::shutdown_token`] (or after Ctrl-C, see
+/// [`Dispatcher::setup_ctrlc_handler`]). At first, the update listener is
+/// stopped and all the updates it has already received are dispatched. Then
+/// the handlers' queues are closed and the dispatcher waits until all the
+/// handlers have processed the rest of updates.
+///
+/// [`StopToken::stop`]: crate::dispatching::StopToken::stop
+/// [`Dispatcher::shutdown_token`]:
+/// crate::dispatching::Dispatcher::shutdown_token
+/// [`Dispatcher::setup_ctrlc_handler`]:
+/// crate::dispatching::Dispatcher::setup_ctrlc_handler
 pub struct Dispatcher {
     bot: Bot,
+    shutdown: StopToken,
+    starters: Mutex<Vec<Starter>>,
+    handlers: Vec<JoinHandle<()>>,
+    failure_subscribers: FailureSubscribers,
+    middlewares: Vec<Box<dyn Middleware>>,
+    dependencies: Dependencies,
+    queue_options: QueueOptions,
+    media_group_window: Duration,
 
     messages_queue: Tx<Message>,
     edited_messages_queue: Tx<Message>,
@@ -58,6 +103,8 @@ pub struct Dispatcher {
     pre_checkout_queries_queue: Tx<PreCheckoutQuery>,
     polls_queue: Tx<Poll>,
     poll_answers_queue: Tx<PollAnswer>,
+    unknown_updates_queue: Tx<Value>,
+    media_groups: Option<Arc<MediaGroups>>,
 }
 
 impl Dispatcher {
@@ -66,6 +113,14 @@ impl Dispatcher {
     pub fn new(bot: Bot) -> Self {
         Self {
             bot,
+            shutdown: StopToken::new(),
+            starters: Mutex::new(Vec::new()),
+            handlers: Vec::new(),
+            failure_subscribers: Arc::new(Mutex::new(Vec::new())),
+            middlewares: Vec::new(),
+            dependencies: Dependencies::new(),
+            queue_options: QueueOptions::unbounded(),
+            media_group_window: Duration::from_secs(1),
             messages_queue: None,
             edited_messages_queue: None,
             channel_posts_queue: None,
@@ -77,119 +132,687 @@ impl Dispatcher {
             pre_checkout_queries_queue: None,
             polls_queue: None,
             poll_answers_queue: None,
+            unknown_updates_queue: None,
+            media_groups: None,
         }
     }
 
+    /// Returns a token which gracefully shuts down this dispatcher.
+    ///
+    /// See [the type-level documentation](crate::dispatching::Dispatcher) for
+    /// the details.
+    #[must_use]
+    pub fn shutdown_token(&self) -> StopToken {
+        self.shutdown.clone()
+    }
+
+    /// Gracefully shuts down this dispatcher after Ctrl-C has been received.
+    ///
+    /// The second Ctrl-C terminates the process immediately.
     #[must_use]
-    fn new_tx<H, Upd>(&self, h: H) -> Tx<Upd>
+    pub fn setup_ctrlc_handler(self) -> Self {
+        let shutdown = self.shutdown_token();
+
+        tokio::spawn(async move {
+            if let Err(error) = tokio::signal::ctrl_c().await {
+                log::error!("Cannot listen for Ctrl-C: {}", error);
+                return;
+            }
+
+            log::info!("^C received, shutting down the dispatcher...");
+            shutdown.stop();
+
+            if tokio::signal::ctrl_c().await.is_ok() {
+                log::info!("^C received again, terminating...");
+                std::process::exit(130);
+            }
+        });
+
+        self
+    }
+
+    /// Adds a middleware, which is run on every update before it's pushed to a
+    /// handler.
+    ///
+    /// Middlewares are run in the order they were added. See [`Middleware`]
+    /// for the details.
+    ///
+    /// [`Middleware`]: crate::dispatching::Middleware
+    #[must_use]
+    pub fn middleware<M>(mut self, middleware: M) -> Self
+    where
+        M: Middleware,
+    {
+        self.middlewares.push(Box::new(middleware));
+        self
+    }
+
+    /// Registers a dependency, which can be extracted in handlers by its type
+    /// via [`UpdateWithCx::dependency`].
+    ///
+    /// A dependency replaces a previously registered one of the same type.
+    /// See [`Dependencies`] for the details.
+    ///
+    /// ## Example
+    /// ```
+    /// use std::sync::Arc;
+    /// use teloxide::prelude::*;
+    ///
+    /// struct Db;
+    ///
+    /// # async fn run() {
+    /// Dispatcher::new(Bot::from_env())
+    ///     .dependency(Arc::new(Db))
+    ///     .messages_handler(|rx: DispatcherHandlerRx<Message>| {
+    ///         rx.for_each_concurrent(None, |cx| async move {
+    ///             let db: Arc<Db> = cx.dependency();
+    ///             // ...
+    ///         })
+    ///     })
+    ///     .dispatch()
+    ///     .await;
+    /// # }
+    /// ```
+    ///
+    /// [`UpdateWithCx::dependency`]: crate::dispatching::UpdateWithCx::dependency
+    /// [`Dependencies`]: crate::dispatching::Dependencies
+    #[must_use]
+    pub fn dependency<T>(mut self, value: T) -> Self
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.dependencies = self.dependencies.with(value);
+        self
+    }
+
+    /// Registers all of `dependencies`.
+    ///
+    /// They are added to the already registered ones.
+    #[must_use]
+    pub fn dependencies(mut self, dependencies: Dependencies) -> Self {
+        self.dependencies = self.dependencies.extend(dependencies);
+        self
+    }
+
+    /// Sets options of the queues of all the handlers.
+    ///
+    /// The queues are created when dispatching starts, so it doesn't matter
+    /// whether this method is called before or after adding the handlers. By
+    /// default, the queues are unbounded. See [`queue`] for the details.
+    ///
+    /// ## Example
+    /// ```
+    /// use teloxide::{
+    ///     dispatching::queue::{OverflowPolicy, QueueOptions},
+    ///     prelude::*,
+    /// };
+    ///
+    /// # async fn run() {
+    /// Dispatcher::new(Bot::from_env())
+    ///     .messages_handler(|rx: DispatcherHandlerRx<Message>| {
+    ///         rx.for_each_concurrent(None, |cx| async move { /* ... */ })
+    ///     })
+    ///     // Under a burst of updates, stale ones aren't worth handling.
+    ///     .queue_options(QueueOptions::bounded(1000).overflow(OverflowPolicy::DropOldest))
+    ///     .dispatch()
+    ///     .await;
+    /// # }
+    /// ```
+    ///
+    /// [`queue`]: crate::dispatching::queue
+    #[must_use]
+    pub fn queue_options(mut self, options: QueueOptions) -> Self {
+        self.queue_options = options;
+        self
+    }
+
+    /// Sets a handler of panics of the handlers.
+    ///
+    /// [`Router`], [`Sequential`], [`DialogueDispatcher`] and the REPLs catch
+    /// a panic while handling an update, so that they go on handling the next
+    /// updates. Other handlers are terminated by a panic, so updates of their
+    /// kinds are dropped afterwards, unless they have been added via the
+    /// `*_handler_restartable` methods (see [`Dispatcher::handler_failures`]).
+    /// By default, panics are logged.
+    ///
+    /// [`Router`]: crate::dispatching::Router
+    /// [`Sequential`]: crate::dispatching::Sequential
+    /// [`DialogueDispatcher`]: crate::dispatching::dialogue::DialogueDispatcher
+    /// [`Dispatcher::handler_failures`]: crate::dispatching::Dispatcher::handler_failures
+    #[must_use]
+    pub fn panic_handler<Eh>(mut self, handler: Arc<Eh>) -> Self
+    where
+        Eh: ErrorHandler<HandlerPanic> + Send + Sync + 'static,
+    {
+        self.dependencies = self.dependencies.with(PanicHandler(handler));
+        self
+    }
+
+    /// Sets a recorder of metrics of updates.
+    ///
+    /// The number of received updates, the time of their handling, failures
+    /// of handlers and the depths of the handlers' queues are recorded. See
+    /// the [`metrics`] module for the details.
+    ///
+    /// [`metrics`]: crate::metrics
+    #[must_use]
+    pub fn metrics<R>(mut self, recorder: Arc<R>) -> Self
+    where
+        R: Recorder,
+    {
+        self.dependencies = self.dependencies.with(Metrics(recorder));
+        self
+    }
+
+    /// Enables [`UpdateWithCx::ask`].
+    ///
+    /// `conversations` are registered as a dependency and added as a
+    /// middleware, which passes replies to waiting handlers. Middlewares added
+    /// after this call don't see the replies. See [`Conversations`] for the
+    /// details.
+    ///
+    /// [`UpdateWithCx::ask`]: crate::dispatching::UpdateWithCx::ask
+    /// [`Conversations`]: crate::dispatching::Conversations
+    #[must_use]
+    pub fn conversations(self, conversations: Conversations) -> Self {
+        self.dependency(conversations.clone()).middleware(conversations)
+    }
+
+    /// Enables [`UpdateWithCx::sender_is_admin`] and the other permission
+    /// checks.
+    ///
+    /// `cache` is registered as a dependency and added as a middleware, which
+    /// invalidates cached administrators on relevant service messages. See
+    /// [`AdminCache`] for the details.
+    ///
+    /// [`UpdateWithCx::sender_is_admin`]: crate::dispatching::UpdateWithCx::sender_is_admin
+    /// [`AdminCache`]: crate::dispatching::AdminCache
+    #[must_use]
+    pub fn admin_cache(self, cache: AdminCache) -> Self {
+        self.dependency(cache.clone()).middleware(cache)
+    }
+
+    /// Returns a stream of panics, which have terminated handlers.
+    ///
+    /// A panic of a handler (as opposed to a panic while handling an update,
+    /// which [`Router`] and the other handlers catch) is passed to all the
+    /// streams returned from this method, e.g. to alert on it. A handler added
+    /// via one of the `*_handler_restartable` methods (e.g.
+    /// [`Dispatcher::messages_handler_restartable`]) is then started anew on
+    /// the same queue after a new update arrives.
+    /// Panics are also passed to a handler set via
+    /// [`Dispatcher::panic_handler`].
+    ///
+    /// [`Router`]: crate::dispatching::Router
+    /// [`Dispatcher::messages_handler_restartable`]:
+    /// crate::dispatching::Dispatcher::messages_handler_restartable
+    /// [`Dispatcher::panic_handler`]: crate::dispatching::Dispatcher::panic_handler
+    pub fn handler_failures(&self) -> impl Stream<Item = HandlerPanic> + Send + Unpin {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.failure_subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    fn cx<Upd>(
+        &self,
+        update: Upd,
+        dependencies: &Dependencies,
+        completion: &Completion,
+    ) -> UpdateWithCx<Upd> {
+        UpdateWithCx {
+            bot: self.bot.clone(),
+            update,
+            dependencies: dependencies.clone(),
+            completion: completion.clone(),
+        }
+    }
+
+    /// Adds `h`, which is spawned with a queue stored in the `queue` field
+    /// when dispatching starts.
+    fn add_handler<H, Upd>(&mut self, h: H, queue: fn(&mut Dispatcher) -> &mut Tx<Upd>)
     where
         H: DispatcherHandler<Upd> + Send + 'static,
         Upd: Send + 'static,
     {
-        let (tx, rx) = mpsc::unbounded_channel();
-        tokio::spawn(async move {
-            let fut = h.handle(rx);
-            fut.await;
-        });
-        Some(tx)
+        let mut h = Some(h);
+        self.starters.get_mut().unwrap().push(Box::new(move |dispatcher| match h.take() {
+            Some(h) => *queue(dispatcher) = Some(dispatcher.spawn_handler(h, || None)),
+            None => log::warn!("A handler cannot be started again, since it isn't restartable"),
+        }));
+    }
+
+    /// Like [`Dispatcher::add_handler`], but `h` is started anew after it has
+    /// panicked.
+    fn add_restartable_handler<H, Upd>(&mut self, h: H, queue: fn(&mut Dispatcher) -> &mut Tx<Upd>)
+    where
+        H: DispatcherHandler<Upd> + Clone + Send + Sync + 'static,
+        Upd: Send + 'static,
+    {
+        self.starters.get_mut().unwrap().push(Box::new(move |dispatcher| {
+            let restart = {
+                let h = h.clone();
+                move || Some(h.clone())
+            };
+            *queue(dispatcher) = Some(dispatcher.spawn_handler(h.clone(), restart));
+        }));
+    }
+
+    /// Spawns `h`, which is replaced by a handler returned from `restart`
+    /// after it has panicked.
+    #[must_use]
+    fn spawn_handler<H, R, Upd>(&mut self, h: H, restart: R) -> queue::Sender<UpdateWithCx<Upd>>
+    where
+        H: DispatcherHandler<Upd> + Send + 'static,
+        R: Fn() -> Option<H> + Send + 'static,
+        Upd: Send + 'static,
+    {
+        let (tx, mut rx) = queue::channel(self.queue_options);
+        let reopener = rx.reopener();
+        let dependencies = self.dependencies.clone();
+        let failure_subscribers = Arc::clone(&self.failure_subscribers);
+
+        self.handlers.push(tokio::spawn(async move {
+            // A handler isn't bound to a single update.
+            let completion = Completion::default();
+            let mut h = h;
+
+            loop {
+                let version = reopener.version();
+                let handled = report_panic(&dependencies, &completion, h.handle(rx));
+                let panic = match handled.await {
+                    Ok(()) => break,
+                    Err(panic) => panic,
+                };
+                failure_subscribers.lock().unwrap().retain(|tx| tx.send(panic.clone()).is_ok());
+
+                h = match restart() {
+                    Some(h) => h,
+                    None => break,
+                };
+
+                // A handler that panics before taking any update is restarted
+                // only after a new update arrives, so that it doesn't spin.
+                rx = match reopener.reopen(version).await {
+                    Some(rx) => rx,
+                    None => break,
+                };
+                log::info!("Restarting a handler after a panic");
+            }
+        }));
+        tx
+    }
+
+    /// Spawns all the added handlers.
+    fn start_handlers(&mut self) {
+        let mut starters = mem::take(self.starters.get_mut().unwrap());
+        for start in &mut starters {
+            start(self);
+        }
+        *self.starters.get_mut().unwrap() = starters;
     }
 
     #[must_use]
     pub fn messages_handler<H>(mut self, h: H) -> Self
     where
-        H: DispatcherHandler<Message> + 'static + Send,
+        H: DispatcherHandler<Message> + Send + 'static,
+    {
+        self.add_handler(h, |dispatcher| &mut dispatcher.messages_queue);
+        self
+    }
+
+    /// Like [`Dispatcher::messages_handler`], but `h` is started anew after it
+    /// has panicked, see [`Dispatcher::handler_failures`].
+    ///
+    /// [`Dispatcher::messages_handler`]: crate::dispatching::Dispatcher::messages_handler
+    /// [`Dispatcher::handler_failures`]: crate::dispatching::Dispatcher::handler_failures
+    #[must_use]
+    pub fn messages_handler_restartable<H>(mut self, h: H) -> Self
+    where
+        H: DispatcherHandler<Message> + Clone + Send + Sync + 'static,
     {
-        self.messages_queue = self.new_tx(h);
+        self.add_restartable_handler(h, |dispatcher| &mut dispatcher.messages_queue);
         self
     }
 
     #[must_use]
     pub fn edited_messages_handler<H>(mut self, h: H) -> Self
     where
-        H: DispatcherHandler<Message> + 'static + Send,
+        H: DispatcherHandler<Message> + Send + 'static,
+    {
+        self.add_handler(h, |dispatcher| &mut dispatcher.edited_messages_queue);
+        self
+    }
+
+    /// Like [`Dispatcher::edited_messages_handler`], but `h` is started anew after it
+    /// has panicked, see [`Dispatcher::handler_failures`].
+    ///
+    /// [`Dispatcher::edited_messages_handler`]: crate::dispatching::Dispatcher::edited_messages_handler
+    /// [`Dispatcher::handler_failures`]: crate::dispatching::Dispatcher::handler_failures
+    #[must_use]
+    pub fn edited_messages_handler_restartable<H>(mut self, h: H) -> Self
+    where
+        H: DispatcherHandler<Message> + Clone + Send + Sync + 'static,
     {
-        self.edited_messages_queue = self.new_tx(h);
+        self.add_restartable_handler(h, |dispatcher| &mut dispatcher.edited_messages_queue);
         self
     }
 
     #[must_use]
     pub fn channel_posts_handler<H>(mut self, h: H) -> Self
     where
-        H: DispatcherHandler<Message> + 'static + Send,
+        H: DispatcherHandler<Message> + Send + 'static,
     {
-        self.channel_posts_queue = self.new_tx(h);
+        self.add_handler(h, |dispatcher| &mut dispatcher.channel_posts_queue);
+        self
+    }
+
+    /// Like [`Dispatcher::channel_posts_handler`], but `h` is started anew after it
+    /// has panicked, see [`Dispatcher::handler_failures`].
+    ///
+    /// [`Dispatcher::channel_posts_handler`]: crate::dispatching::Dispatcher::channel_posts_handler
+    /// [`Dispatcher::handler_failures`]: crate::dispatching::Dispatcher::handler_failures
+    #[must_use]
+    pub fn channel_posts_handler_restartable<H>(mut self, h: H) -> Self
+    where
+        H: DispatcherHandler<Message> + Clone + Send + Sync + 'static,
+    {
+        self.add_restartable_handler(h, |dispatcher| &mut dispatcher.channel_posts_queue);
         self
     }
 
     #[must_use]
     pub fn edited_channel_posts_handler<H>(mut self, h: H) -> Self
     where
-        H: DispatcherHandler<Message> + 'static + Send,
+        H: DispatcherHandler<Message> + Send + 'static,
+    {
+        self.add_handler(h, |dispatcher| &mut dispatcher.edited_channel_posts_queue);
+        self
+    }
+
+    /// Like [`Dispatcher::edited_channel_posts_handler`], but `h` is started anew after it
+    /// has panicked, see [`Dispatcher::handler_failures`].
+    ///
+    /// [`Dispatcher::edited_channel_posts_handler`]: crate::dispatching::Dispatcher::edited_channel_posts_handler
+    /// [`Dispatcher::handler_failures`]: crate::dispatching::Dispatcher::handler_failures
+    #[must_use]
+    pub fn edited_channel_posts_handler_restartable<H>(mut self, h: H) -> Self
+    where
+        H: DispatcherHandler<Message> + Clone + Send + Sync + 'static,
     {
-        self.edited_channel_posts_queue = self.new_tx(h);
+        self.add_restartable_handler(h, |dispatcher| &mut dispatcher.edited_channel_posts_queue);
         self
     }
 
     #[must_use]
     pub fn inline_queries_handler<H>(mut self, h: H) -> Self
     where
-        H: DispatcherHandler<InlineQuery> + 'static + Send,
+        H: DispatcherHandler<InlineQuery> + Send + 'static,
+    {
+        self.add_handler(h, |dispatcher| &mut dispatcher.inline_queries_queue);
+        self
+    }
+
+    /// Like [`Dispatcher::inline_queries_handler`], but `h` is started anew after it
+    /// has panicked, see [`Dispatcher::handler_failures`].
+    ///
+    /// [`Dispatcher::inline_queries_handler`]: crate::dispatching::Dispatcher::inline_queries_handler
+    /// [`Dispatcher::handler_failures`]: crate::dispatching::Dispatcher::handler_failures
+    #[must_use]
+    pub fn inline_queries_handler_restartable<H>(mut self, h: H) -> Self
+    where
+        H: DispatcherHandler<InlineQuery> + Clone + Send + Sync + 'static,
     {
-        self.inline_queries_queue = self.new_tx(h);
+        self.add_restartable_handler(h, |dispatcher| &mut dispatcher.inline_queries_queue);
         self
     }
 
     #[must_use]
     pub fn chosen_inline_results_handler<H>(mut self, h: H) -> Self
     where
-        H: DispatcherHandler<ChosenInlineResult> + 'static + Send,
+        H: DispatcherHandler<ChosenInlineResult> + Send + 'static,
     {
-        self.chosen_inline_results_queue = self.new_tx(h);
+        self.add_handler(h, |dispatcher| &mut dispatcher.chosen_inline_results_queue);
+        self
+    }
+
+    /// Like [`Dispatcher::chosen_inline_results_handler`], but `h` is started anew after it
+    /// has panicked, see [`Dispatcher::handler_failures`].
+    ///
+    /// [`Dispatcher::chosen_inline_results_handler`]: crate::dispatching::Dispatcher::chosen_inline_results_handler
+    /// [`Dispatcher::handler_failures`]: crate::dispatching::Dispatcher::handler_failures
+    #[must_use]
+    pub fn chosen_inline_results_handler_restartable<H>(mut self, h: H) -> Self
+    where
+        H: DispatcherHandler<ChosenInlineResult> + Clone + Send + Sync + 'static,
+    {
+        self.add_restartable_handler(h, |dispatcher| &mut dispatcher.chosen_inline_results_queue);
         self
     }
 
     #[must_use]
     pub fn callback_queries_handler<H>(mut self, h: H) -> Self
     where
-        H: DispatcherHandler<CallbackQuery> + 'static + Send,
+        H: DispatcherHandler<CallbackQuery> + Send + 'static,
+    {
+        self.add_handler(h, |dispatcher| &mut dispatcher.callback_queries_queue);
+        self
+    }
+
+    /// Like [`Dispatcher::callback_queries_handler`], but `h` is started anew after it
+    /// has panicked, see [`Dispatcher::handler_failures`].
+    ///
+    /// [`Dispatcher::callback_queries_handler`]: crate::dispatching::Dispatcher::callback_queries_handler
+    /// [`Dispatcher::handler_failures`]: crate::dispatching::Dispatcher::handler_failures
+    #[must_use]
+    pub fn callback_queries_handler_restartable<H>(mut self, h: H) -> Self
+    where
+        H: DispatcherHandler<CallbackQuery> + Clone + Send + Sync + 'static,
     {
-        self.callback_queries_queue = self.new_tx(h);
+        self.add_restartable_handler(h, |dispatcher| &mut dispatcher.callback_queries_queue);
         self
     }
 
     #[must_use]
     pub fn shipping_queries_handler<H>(mut self, h: H) -> Self
     where
-        H: DispatcherHandler<ShippingQuery> + 'static + Send,
+        H: DispatcherHandler<ShippingQuery> + Send + 'static,
     {
-        self.shipping_queries_queue = self.new_tx(h);
+        self.add_handler(h, |dispatcher| &mut dispatcher.shipping_queries_queue);
+        self
+    }
+
+    /// Like [`Dispatcher::shipping_queries_handler`], but `h` is started anew after it
+    /// has panicked, see [`Dispatcher::handler_failures`].
+    ///
+    /// [`Dispatcher::shipping_queries_handler`]: crate::dispatching::Dispatcher::shipping_queries_handler
+    /// [`Dispatcher::handler_failures`]: crate::dispatching::Dispatcher::handler_failures
+    #[must_use]
+    pub fn shipping_queries_handler_restartable<H>(mut self, h: H) -> Self
+    where
+        H: DispatcherHandler<ShippingQuery> + Clone + Send + Sync + 'static,
+    {
+        self.add_restartable_handler(h, |dispatcher| &mut dispatcher.shipping_queries_queue);
         self
     }
 
     #[must_use]
     pub fn pre_checkout_queries_handler<H>(mut self, h: H) -> Self
     where
-        H: DispatcherHandler<PreCheckoutQuery> + 'static + Send,
+        H: DispatcherHandler<PreCheckoutQuery> + Send + 'static,
+    {
+        self.add_handler(h, |dispatcher| &mut dispatcher.pre_checkout_queries_queue);
+        self
+    }
+
+    /// Like [`Dispatcher::pre_checkout_queries_handler`], but `h` is started anew after it
+    /// has panicked, see [`Dispatcher::handler_failures`].
+    ///
+    /// [`Dispatcher::pre_checkout_queries_handler`]: crate::dispatching::Dispatcher::pre_checkout_queries_handler
+    /// [`Dispatcher::handler_failures`]: crate::dispatching::Dispatcher::handler_failures
+    #[must_use]
+    pub fn pre_checkout_queries_handler_restartable<H>(mut self, h: H) -> Self
+    where
+        H: DispatcherHandler<PreCheckoutQuery> + Clone + Send + Sync + 'static,
     {
-        self.pre_checkout_queries_queue = self.new_tx(h);
+        self.add_restartable_handler(h, |dispatcher| &mut dispatcher.pre_checkout_queries_queue);
         self
     }
 
     #[must_use]
     pub fn polls_handler<H>(mut self, h: H) -> Self
     where
-        H: DispatcherHandler<Poll> + 'static + Send,
+        H: DispatcherHandler<Poll> + Send + 'static,
+    {
+        self.add_handler(h, |dispatcher| &mut dispatcher.polls_queue);
+        self
+    }
+
+    /// Like [`Dispatcher::polls_handler`], but `h` is started anew after it
+    /// has panicked, see [`Dispatcher::handler_failures`].
+    ///
+    /// [`Dispatcher::polls_handler`]: crate::dispatching::Dispatcher::polls_handler
+    /// [`Dispatcher::handler_failures`]: crate::dispatching::Dispatcher::handler_failures
+    #[must_use]
+    pub fn polls_handler_restartable<H>(mut self, h: H) -> Self
+    where
+        H: DispatcherHandler<Poll> + Clone + Send + Sync + 'static,
     {
-        self.polls_queue = self.new_tx(h);
+        self.add_restartable_handler(h, |dispatcher| &mut dispatcher.polls_queue);
         self
     }
 
     #[must_use]
     pub fn poll_answers_handler<H>(mut self, h: H) -> Self
     where
-        H: DispatcherHandler<PollAnswer> + 'static + Send,
+        H: DispatcherHandler<PollAnswer> + Send + 'static,
+    {
+        self.add_handler(h, |dispatcher| &mut dispatcher.poll_answers_queue);
+        self
+    }
+
+    /// Like [`Dispatcher::poll_answers_handler`], but `h` is started anew after it
+    /// has panicked, see [`Dispatcher::handler_failures`].
+    ///
+    /// [`Dispatcher::poll_answers_handler`]: crate::dispatching::Dispatcher::poll_answers_handler
+    /// [`Dispatcher::handler_failures`]: crate::dispatching::Dispatcher::handler_failures
+    #[must_use]
+    pub fn poll_answers_handler_restartable<H>(mut self, h: H) -> Self
+    where
+        H: DispatcherHandler<PollAnswer> + Clone + Send + Sync + 'static,
     {
-        self.poll_answers_queue = self.new_tx(h);
+        self.add_restartable_handler(h, |dispatcher| &mut dispatcher.poll_answers_queue);
+        self
+    }
+
+    /// Sets a handler of updates that cannot be parsed.
+    ///
+    /// A handler receives the raw JSON of an update (see
+    /// [`UpdateKind::Unknown`]), e.g. to log, store or answer it somehow.
+    /// Without this handler, such updates are dropped after being logged.
+    ///
+    /// [`UpdateKind::Unknown`]: crate::types::UpdateKind::Unknown
+    #[must_use]
+    pub fn unknown_updates_handler<H>(mut self, h: H) -> Self
+    where
+        H: DispatcherHandler<Value> + Send + 'static,
+    {
+        self.add_handler(h, |dispatcher| &mut dispatcher.unknown_updates_queue);
+        self
+    }
+
+    /// Like [`Dispatcher::unknown_updates_handler`], but `h` is started anew after it
+    /// has panicked, see [`Dispatcher::handler_failures`].
+    ///
+    /// [`Dispatcher::unknown_updates_handler`]: crate::dispatching::Dispatcher::unknown_updates_handler
+    /// [`Dispatcher::handler_failures`]: crate::dispatching::Dispatcher::handler_failures
+    #[must_use]
+    pub fn unknown_updates_handler_restartable<H>(mut self, h: H) -> Self
+    where
+        H: DispatcherHandler<Value> + Clone + Send + Sync + 'static,
+    {
+        self.add_restartable_handler(h, |dispatcher| &mut dispatcher.unknown_updates_queue);
+        self
+    }
+
+    /// Sets a handler of media groups (albums).
+    ///
+    /// Messages of a media group are sent by Telegram one by one. After this
+    /// call, messages that belong to a media group aren't passed to the
+    /// messages handler. Instead, they are collected until no messages of the
+    /// group have been received for a window (see
+    /// [`Dispatcher::media_group_window`]) and then passed to this handler
+    /// together, sorted by their IDs.
+    ///
+    /// ## Example
+    /// ```
+    /// use teloxide::prelude::*;
+    ///
+    /// # async fn run() {
+    /// Dispatcher::new(Bot::from_env())
+    ///     .media_groups_handler(|rx: DispatcherHandlerRx<Vec<Message>>| {
+    ///         rx.for_each_concurrent(None, |cx| async move {
+    ///             let text = format!("Got an album of {} items", cx.update.len());
+    ///             cx.bot.send_message(cx.update[0].chat.id, text).send().await.log_on_error().await;
+    ///         })
+    ///     })
+    ///     .dispatch()
+    ///     .await;
+    /// # }
+    /// ```
+    ///
+    /// [`Dispatcher::media_group_window`]: crate::dispatching::Dispatcher::media_group_window
+    #[must_use]
+    pub fn media_groups_handler<H>(mut self, h: H) -> Self
+    where
+        H: DispatcherHandler<Vec<Message>> + Send + 'static,
+    {
+        let mut h = Some(h);
+        self.starters.get_mut().unwrap().push(Box::new(move |dispatcher| match h.take() {
+            Some(h) => {
+                let tx = dispatcher.spawn_handler(h, || None);
+                dispatcher.start_media_groups(tx);
+            }
+            None => log::warn!("A handler cannot be started again, since it isn't restartable"),
+        }));
+        self
+    }
+
+    /// Like [`Dispatcher::media_groups_handler`], but `h` is started anew
+    /// after it has panicked, see [`Dispatcher::handler_failures`].
+    ///
+    /// [`Dispatcher::media_groups_handler`]: crate::dispatching::Dispatcher::media_groups_handler
+    /// [`Dispatcher::handler_failures`]: crate::dispatching::Dispatcher::handler_failures
+    #[must_use]
+    pub fn media_groups_handler_restartable<H>(mut self, h: H) -> Self
+    where
+        H: DispatcherHandler<Vec<Message>> + Clone + Send + Sync + 'static,
+    {
+        self.starters.get_mut().unwrap().push(Box::new(move |dispatcher| {
+            let restart = {
+                let h = h.clone();
+                move || Some(h.clone())
+            };
+            let tx = dispatcher.spawn_handler(h.clone(), restart);
+            dispatcher.start_media_groups(tx);
+        }));
+        self
+    }
+
+    fn start_media_groups(&mut self, tx: queue::Sender<UpdateWithCx<Vec<Message>>>) {
+        self.media_groups = Some(Arc::new(MediaGroups::new(self.media_group_window, tx)));
+    }
+
+    /// Sets how long a media groups handler waits for the next message of a
+    /// media group.
+    ///
+    /// The window is read when dispatching starts, so this method can be
+    /// called before or after [`Dispatcher::media_groups_handler`]. By
+    /// default, it's 1 second.
+    ///
+    /// [`Dispatcher::media_groups_handler`]: crate::dispatching::Dispatcher::media_groups_handler
+    #[must_use]
+    pub fn media_group_window(mut self, window: Duration) -> Self {
+        self.media_group_window = window;
         self
     }
 
@@ -197,7 +820,11 @@ impl Dispatcher {
     ///
     /// The default parameters are a long polling update listener and log all
     /// errors produced by this listener).
-    pub async fn dispatch(&self) {
+    ///
+    /// Returns after the dispatcher has been [gracefully shut down].
+    ///
+    /// [gracefully shut down]: crate::dispatching::Dispatcher#graceful-shutdown
+    pub async fn dispatch(&mut self) {
         self.dispatch_with_listener(
             update_listeners::polling_default(self.bot.clone()),
             LoggingErrorHandler::with_custom_text("An error from the update listener"),
@@ -207,20 +834,118 @@ impl Dispatcher {
 
     /// Starts your bot with custom `update_listener` and
     /// `update_listener_error_handler`.
+    ///
+    /// Returns after the dispatcher has been [gracefully shut down] or after
+    /// `update_listener` has finished on its own.
+    ///
+    /// [gracefully shut down]: crate::dispatching::Dispatcher#graceful-shutdown
     pub async fn dispatch_with_listener<'a, UListener, ListenerE, Eh>(
-        &'a self,
-        update_listener: UListener,
+        &'a mut self,
+        mut update_listener: UListener,
         update_listener_error_handler: Arc<Eh>,
     ) where
         UListener: UpdateListener<ListenerE> + 'a,
         Eh: ErrorHandler<ListenerE> + 'a,
         ListenerE: Debug,
+    {
+        self.start_handlers();
+
+        let stop_listener = update_listener.stop_token();
+        let acknowledger = update_listener.acknowledger();
+        let shutdown = self.shutdown.stopped();
+
+        // This future never resolves, so `select` below completes only after
+        // the listener is exhausted.
+        let stop_on_shutdown = Box::pin(async move {
+            shutdown.await;
+            stop_listener.stop();
+            future::pending::<()>().await
+        });
+
+        future::select(
+            Box::pin(self.dispatch_updates(
+                update_listener,
+                update_listener_error_handler,
+                acknowledger.clone(),
+            )),
+            stop_on_shutdown,
+        )
+        .await;
+
+        self.drain().await;
+
+        if let Some(acknowledger) = acknowledger {
+            acknowledger.flush().await;
+        }
+    }
+
+    /// Closes all the queues and waits until the handlers have processed the
+    /// rest of updates.
+    async fn drain(&mut self) {
+        self.messages_queue.take();
+        self.edited_messages_queue.take();
+        self.channel_posts_queue.take();
+        self.edited_channel_posts_queue.take();
+        self.inline_queries_queue.take();
+        self.chosen_inline_results_queue.take();
+        self.callback_queries_queue.take();
+        self.shipping_queries_queue.take();
+        self.pre_checkout_queries_queue.take();
+        self.polls_queue.take();
+        self.poll_answers_queue.take();
+        self.unknown_updates_queue.take();
+        // Pending media groups are still pushed after their windows.
+        self.media_groups.take();
+
+        for result in future::join_all(self.handlers.drain(..)).await {
+            if let Err(error) = result {
+                log::error!("A dispatcher's handler has failed: {}", error);
+            }
+        }
+    }
+
+    /// Passes `update` through the middlewares, returning `None` if it has
+    /// been dropped by one of them (or one of them has panicked).
+    async fn apply_middlewares(
+        &self,
+        mut update: Update,
+        dependencies: &Dependencies,
+        completion: &Completion,
+    ) -> Option<Update> {
+        for middleware in &self.middlewares {
+            let id = update.id;
+
+            let cx = self.cx(update, dependencies, completion);
+            let handled = catch_panic(dependencies, completion, middleware.handle(cx));
+
+            update = match handled.await.flatten() {
+                Some(update) => update,
+                None => {
+                    log::trace!("The update {} is dropped by a middleware", id);
+                    return None;
+                }
+            };
+        }
+
+        Some(update)
+    }
+
+    async fn dispatch_updates<UListener, ListenerE, Eh>(
+        &self,
+        update_listener: UListener,
+        update_listener_error_handler: Arc<Eh>,
+        acknowledger: Option<Acknowledger>,
+    ) where
+        UListener: UpdateListener<ListenerE>,
+        Eh: ErrorHandler<ListenerE>,
+        ListenerE: Debug,
     {
         let update_listener = Box::pin(update_listener);
 
         update_listener
             .for_each(move |update| {
                 let update_listener_error_handler = Arc::clone(&update_listener_error_handler);
+                let acknowledger = acknowledger.clone();
 
                 async move {
                     log::trace!("Dispatcher received an update: {:?}", update);
@@ -233,13 +958,56 @@ impl Dispatcher {
                         }
                     };
 
+                    #[cfg(feature = "tracing-spans")]
+                    let dependencies =
+                        self.dependencies.clone().with(crate::spans::update(&update));
+                    #[cfg(not(feature = "tracing-spans"))]
+                    let dependencies = self.dependencies.clone();
+
+                    // The update is acknowledged and the time of its handling
+                    // is recorded after it has been handled (or dropped).
+                    let handling = dependencies.get::<Metrics>().map(|metrics| {
+                        let kind = update.kind.name();
+                        metrics.0.record_update(kind);
+                        metrics.handling(kind)
+                    });
+                    let ack = acknowledger.map(|acknowledger| acknowledger.guard(update.id));
+                    let completion = Completion::new(ack, handling);
+
+                    let update =
+                        match self.apply_middlewares(update, &dependencies, &completion).await {
+                            Some(update) => update,
+                            None => return,
+                        };
+
                     match update.kind {
                         UpdateKind::Message(message) => {
-                            send!(&self.bot, &self.messages_queue, message, UpdateKind::Message);
+                            let group_id = message.media_group_id().map(ToOwned::to_owned);
+
+                            match (&self.media_groups, group_id) {
+                                (Some(media_groups), Some(group_id)) => {
+                                    media_groups.push(
+                                        group_id,
+                                        self.cx(message, &dependencies, &completion),
+                                    );
+                                }
+                                _ => {
+                                    send!(
+                                        self,
+                                        &dependencies,
+                                        &completion,
+                                        &self.messages_queue,
+                                        message,
+                                        UpdateKind::Message
+                                    );
+                                }
+                            }
                         }
                         UpdateKind::EditedMessage(message) => {
                             send!(
-                                &self.bot,
+                                self,
+                                &dependencies,
+                                &completion,
                                 &self.edited_messages_queue,
                                 message,
                                 UpdateKind::EditedMessage
@@ -247,7 +1015,9 @@ impl Dispatcher {
                         }
                         UpdateKind::ChannelPost(post) => {
                             send!(
-                                &self.bot,
+                                self,
+                                &dependencies,
+                                &completion,
                                 &self.channel_posts_queue,
                                 post,
                                 UpdateKind::ChannelPost
@@ -255,7 +1025,9 @@ impl Dispatcher {
                         }
                         UpdateKind::EditedChannelPost(post) => {
                             send!(
-                                &self.bot,
+                                self,
+                                &dependencies,
+                                &completion,
                                 &self.edited_channel_posts_queue,
                                 post,
                                 UpdateKind::EditedChannelPost
@@ -263,7 +1035,9 @@ impl Dispatcher {
                         }
                         UpdateKind::InlineQuery(query) => {
                             send!(
-                                &self.bot,
+                                self,
+                                &dependencies,
+                                &completion,
                                 &self.inline_queries_queue,
                                 query,
                                 UpdateKind::InlineQuery
@@ -271,7 +1045,9 @@ impl Dispatcher {
                         }
                         UpdateKind::ChosenInlineResult(result) => {
                             send!(
-                                &self.bot,
+                                self,
+                                &dependencies,
+                                &completion,
                                 &self.chosen_inline_results_queue,
                                 result,
                                 UpdateKind::ChosenInlineResult
@@ -279,7 +1055,9 @@ impl Dispatcher {
                         }
                         UpdateKind::CallbackQuery(query) => {
                             send!(
-                                &self.bot,
+                                self,
+                                &dependencies,
+                                &completion,
                                 &self.callback_queries_queue,
                                 query,
                                 UpdateKind::CallbackQuer
@@ -287,7 +1065,9 @@ impl Dispatcher {
                         }
                         UpdateKind::ShippingQuery(query) => {
                             send!(
-                                &self.bot,
+                                self,
+                                &dependencies,
+                                &completion,
                                 &self.shipping_queries_queue,
                                 query,
                                 UpdateKind::ShippingQuery
@@ -295,26 +1075,443 @@ impl Dispatcher {
                         }
                         UpdateKind::PreCheckoutQuery(query) => {
                             send!(
-                                &self.bot,
+                                self,
+                                &dependencies,
+                                &completion,
                                 &self.pre_checkout_queries_queue,
                                 query,
                                 UpdateKind::PreCheckoutQuery
                             );
                         }
                         UpdateKind::Poll(poll) => {
-                            send!(&self.bot, &self.polls_queue, poll, UpdateKind::Poll);
+                            send!(
+                                self,
+                                &dependencies,
+                                &completion,
+                                &self.polls_queue,
+                                poll,
+                                UpdateKind::Poll
+                            );
                         }
                         UpdateKind::PollAnswer(answer) => {
                             send!(
-                                &self.bot,
+                                self,
+                                &dependencies,
+                                &completion,
                                 &self.poll_answers_queue,
                                 answer,
                                 UpdateKind::PollAnswer
                             );
                         }
+                        UpdateKind::Unknown(value) => {
+                            send!(
+                                self,
+                                &dependencies,
+                                &completion,
+                                &self.unknown_updates_queue,
+                                value,
+                                UpdateKind::Unknown
+                            );
+                        }
                     }
                 }
             })
             .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        dispatching::{DispatcherHandlerRx, Router},
+        error_handlers::IgnoringErrorHandlerSafe,
+        metrics::{Prometheus, RequestMetrics},
+        requests::Request,
+        testing::{MockMessage, MockServer},
+    };
+    use futures::{stream, FutureExt};
+    use std::{
+        collections::HashSet,
+        convert::Infallible,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex,
+        },
+    };
+    use tokio::time::{delay_for, Duration};
+
+    fn message_update(id: i32) -> Update {
+        Update::try_parse(&serde_json::json!({
+            "update_id": id,
+            "message": {
+                "message_id": id,
+                "chat": { "id": 1, "first_name": "Waffle", "type": "private" },
+                "date": 1_569_518_342,
+                "text": "hello there"
+            }
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    #[allow(deprecated)]
+    async fn shutdown_drains_handlers() {
+        let processed = Arc::new(AtomicUsize::new(0));
+
+        let mut dispatcher = Dispatcher::new(Bot::new("Doesn't matter here")).messages_handler({
+            let processed = Arc::clone(&processed);
+
+            move |rx: DispatcherHandlerRx<Message>| {
+                rx.for_each_concurrent(None, move |_| {
+                    let processed = Arc::clone(&processed);
+
+                    async move {
+                        delay_for(Duration::from_millis(200)).await;
+                        processed.fetch_add(1, Ordering::SeqCst);
+                    }
+                })
+            }
+        });
+
+        let shutdown = dispatcher.shutdown_token();
+        tokio::spawn(async move {
+            delay_for(Duration::from_millis(50)).await;
+            shutdown.stop();
+        });
+
+        // The listener never finishes on its own.
+        let updates = stream::iter((0..3).map(message_update).map(Ok::<_, Infallible>))
+            .chain(stream::pending());
+
+        dispatcher
+            .dispatch_with_listener(
+                update_listeners::from_stream(updates),
+                IgnoringErrorHandlerSafe::new(),
+            )
+            .await;
+
+        assert_eq!(processed.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn queue_options_apply_to_handlers_added_before() {
+        let server = MockServer::start();
+        let handled = Arc::new(AtomicUsize::new(0));
+
+        Dispatcher::new(server.bot())
+            .messages_handler({
+                let handled = Arc::clone(&handled);
+
+                move |rx: DispatcherHandlerRx<Message>| {
+                    rx.for_each(move |_| {
+                        let handled = Arc::clone(&handled);
+
+                        async move {
+                            delay_for(Duration::from_millis(50)).await;
+                            handled.fetch_add(1, Ordering::SeqCst);
+                        }
+                    })
+                }
+            })
+            .queue_options(QueueOptions::bounded(1).overflow(queue::OverflowPolicy::DropNewest))
+            .dispatch_with_listener(
+                update_listeners::from_stream(stream::iter(
+                    (0..3).map(message_update).map(Ok::<_, Infallible>),
+                )),
+                IgnoringErrorHandlerSafe::new(),
+            )
+            .await;
+
+        assert!(handled.load(Ordering::SeqCst) < 3);
+    }
+
+    #[tokio::test]
+    async fn middlewares_filter_updates() {
+        let server = MockServer::start();
+        let seen = Arc::new(Mutex::new(HashSet::new()));
+
+        Dispatcher::new(server.bot())
+            // Drops duplicated updates.
+            .middleware(move |cx: UpdateWithCx<Update>| {
+                let seen = Arc::clone(&seen);
+                async move {
+                    if seen.lock().unwrap().insert(cx.update.id) {
+                        Some(cx.update)
+                    } else {
+                        None
+                    }
+                }
+            })
+            // Replies to the 3rd update itself.
+            .middleware(|cx: UpdateWithCx<Update>| async move {
+                if cx.update.id != 3 {
+                    return Some(cx.update);
+                }
+
+                cx.bot.send_message(1, "Intercepted").send().await.unwrap();
+                None
+            })
+            .messages_handler(|rx: DispatcherHandlerRx<Message>| {
+                rx.for_each(|cx| async move {
+                    cx.answer_str(format!("Handled {}", cx.update.id)).await.unwrap();
+                })
+            })
+            .dispatch_with_listener(
+                update_listeners::from_stream(stream::iter(
+                    vec![1, 1, 2, 3, 2].into_iter().map(message_update).map(Ok::<_, Infallible>),
+                )),
+                IgnoringErrorHandlerSafe::new(),
+            )
+            .await;
+
+        let mut texts: Vec<_> =
+            server.requests().into_iter().map(|req| req.params["text"].clone()).collect();
+        texts.sort_by_key(ToString::to_string);
+        assert_eq!(texts, ["Handled 1", "Handled 2", "Intercepted"]);
+    }
+
+    #[tokio::test]
+    async fn dependencies_are_passed_to_handlers() {
+        #[derive(Clone)]
+        struct Greeting(&'static str);
+
+        let server = MockServer::start();
+
+        Dispatcher::new(server.bot())
+            .dependency(Greeting("Hello"))
+            .dependencies(Dependencies::new().with(Greeting("Hi")).with(Arc::new(0_u8)))
+            .middleware(|cx: UpdateWithCx<Update>| async move {
+                let _: Arc<u8> = cx.dependency();
+                Some(cx.update)
+            })
+            .messages_handler(|rx: DispatcherHandlerRx<Message>| {
+                rx.for_each(|cx| async move {
+                    let Greeting(greeting) = cx.dependency();
+                    cx.answer_str(greeting).await.unwrap();
+                })
+            })
+            .dispatch_with_listener(
+                update_listeners::from_stream(stream::iter(vec![Ok::<_, Infallible>(
+                    message_update(1),
+                )])),
+                IgnoringErrorHandlerSafe::new(),
+            )
+            .await;
+
+        assert_eq!(server.requests()[0].params["text"], "Hi");
+    }
+
+    #[tokio::test]
+    async fn panics_are_reported() {
+        let server = MockServer::start();
+        let panics = Arc::new(Mutex::new(Vec::new()));
+        let started = Arc::new(AtomicUsize::new(0));
+
+        let mut dispatcher = Dispatcher::new(server.bot())
+            .messages_handler(Router::new().endpoint(|cx: UpdateWithCx<Message>| async move {
+                if cx.update.id == 1 {
+                    panic!("Update 1");
+                }
+                cx.answer_str(format!("Handled {}", cx.update.id)).await.map(drop)
+            }))
+            // Isn't restartable, so the next channel posts are dropped.
+            .channel_posts_handler({
+                let not_clone = Mutex::new(());
+                move |rx: DispatcherHandlerRx<Message>| async move {
+                    drop((not_clone, rx));
+                    panic!("Channel posts");
+                }
+            })
+            // Panics on the first start, before taking any update.
+            .edited_messages_handler_restartable(move |rx: DispatcherHandlerRx<Message>| {
+                let started = started.fetch_add(1, Ordering::SeqCst);
+
+                async move {
+                    if started == 0 {
+                        panic!("Edited messages");
+                    }
+                    rx.for_each(|cx| async move {
+                        cx.answer_str(format!("Handled edited {}", cx.update.id)).await.unwrap();
+                    })
+                    .await
+                }
+            })
+            // The panic handler is resolved when dispatching starts.
+            .panic_handler({
+                let panics = Arc::clone(&panics);
+                Arc::new(move |panic: HandlerPanic| {
+                    panics.lock().unwrap().push(panic.message);
+                    async {}
+                })
+            });
+        let mut failures = dispatcher.handler_failures();
+
+        let edited = MockMessage::new("edited").id(3).build();
+        let channel_post = MockMessage::new("post").id(4).build();
+        dispatcher
+            .dispatch_with_listener(
+                update_listeners::from_stream(
+                    stream::iter(vec![message_update(1), message_update(2)])
+                        .chain(stream::once(async move {
+                            // Lets the edited messages handler panic first.
+                            delay_for(Duration::from_millis(50)).await;
+                            Update::new(3, UpdateKind::EditedMessage(edited))
+                        }))
+                        .chain(stream::once(async move {
+                            Update::new(4, UpdateKind::ChannelPost(channel_post))
+                        }))
+                        .map(Ok::<_, Infallible>),
+                ),
+                IgnoringErrorHandlerSafe::new(),
+            )
+            .await;
+
+        let mut texts: Vec<_> =
+            server.requests().into_iter().map(|req| req.params["text"].clone()).collect();
+        texts.sort_by_key(ToString::to_string);
+        assert_eq!(texts, ["Handled 2", "Handled edited 3"]);
+
+        let mut panics = panics.lock().unwrap().clone();
+        panics.sort();
+        assert_eq!(panics, ["Channel posts", "Edited messages", "Update 1"]);
+
+        let mut failed =
+            vec![failures.next().await.unwrap().message, failures.next().await.unwrap().message];
+        failed.sort();
+        assert_eq!(failed, ["Channel posts", "Edited messages"]);
+        assert!(failures.next().now_or_never().is_none());
+    }
+
+    #[tokio::test]
+    async fn metrics_are_recorded() {
+        let server = MockServer::start();
+        server.respond_error("sendMessage", 400, "Bad Request: chat not found");
+
+        let recorder = Arc::new(Prometheus::new());
+        let bot = server.bot().layer(RequestMetrics::new(Arc::clone(&recorder)));
+
+        Dispatcher::new(bot)
+            .metrics(Arc::clone(&recorder))
+            .messages_handler(Router::new().endpoint(|cx: UpdateWithCx<Message>| async move {
+                cx.answer_str("Hi").await.map(drop)
+            }))
+            .dispatch_with_listener(
+                update_listeners::from_stream(stream::iter(
+                    vec![1, 2].into_iter().map(message_update).map(Ok::<_, Infallible>),
+                )),
+                IgnoringErrorHandlerSafe::new(),
+            )
+            .await;
+
+        let rendered = recorder.render();
+        for line in &[
+            "teloxide_updates_total{kind=\"message\"} 2",
+            "teloxide_handler_failures_total{kind=\"message\"} 1",
+            "teloxide_update_handling_seconds_count{kind=\"message\"} 2",
+            "teloxide_requests_total{method=\"sendMessage\"} 2",
+            "teloxide_request_errors_total{method=\"sendMessage\",error=\"api_error\"} 1",
+        ] {
+            assert!(rendered.lines().any(|l| l == *line), "{} is not in:\n{}", line, rendered);
+        }
+        assert!(rendered.contains("teloxide_queue_depth{queue=\"message\"}"));
+    }
+
+    #[tokio::test]
+    async fn stashed_dependencies_dont_delay_handling() {
+        struct AckedListener<S> {
+            inner: S,
+            acknowledger: Acknowledger,
+        }
+
+        impl<S: Stream + Unpin> Stream for AckedListener<S> {
+            type Item = S::Item;
+
+            fn poll_next(
+                mut self: std::pin::Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+            ) -> std::task::Poll<Option<S::Item>> {
+                std::pin::Pin::new(&mut self.inner).poll_next(cx)
+            }
+        }
+
+        impl<S, E> UpdateListener<E> for AckedListener<S>
+        where
+            S: UpdateListener<E> + Unpin,
+        {
+            fn stop_token(&mut self) -> StopToken {
+                self.inner.stop_token()
+            }
+
+            fn acknowledger(&mut self) -> Option<Acknowledger> {
+                Some(self.acknowledger.clone())
+            }
+        }
+
+        let acknowledger = Acknowledger::new(1, |_| Box::pin(futures::future::ready(())));
+        assert!(acknowledger.receive(1));
+        assert!(acknowledger.receive(2));
+
+        let recorder = Arc::new(Prometheus::new());
+        let stashed = Arc::new(Mutex::new(Vec::new()));
+
+        Dispatcher::new(MockServer::start().bot())
+            .metrics(Arc::clone(&recorder))
+            .messages_handler(Router::new().endpoint({
+                let stashed = Arc::clone(&stashed);
+                move |cx: UpdateWithCx<Message>| {
+                    stashed.lock().unwrap().push(cx.dependencies.clone());
+                    async { Ok::<_, Infallible>(()) }
+                }
+            }))
+            .dispatch_with_listener(
+                AckedListener {
+                    inner: update_listeners::from_stream(stream::iter(
+                        vec![1, 2].into_iter().map(message_update).map(Ok::<_, Infallible>),
+                    )),
+                    acknowledger: acknowledger.clone(),
+                },
+                IgnoringErrorHandlerSafe::new(),
+            )
+            .await;
+
+        assert_eq!(stashed.lock().unwrap().len(), 2);
+        assert_eq!(acknowledger.offset(), 3);
+        assert!(recorder
+            .render()
+            .lines()
+            .any(|l| l == "teloxide_update_handling_seconds_count{kind=\"message\"} 2"));
+    }
+
+    #[tokio::test]
+    async fn unknown_updates_are_handled() {
+        let server = MockServer::start();
+        let unknown = serde_json::json!({
+            "update_id": 2,
+            "my_chat_member": { "chat": { "id": 10 } }
+        });
+
+        Dispatcher::new(server.bot())
+            .unknown_updates_handler(|rx: DispatcherHandlerRx<Value>| {
+                rx.for_each(|cx| async move {
+                    let chat_id = cx.update["my_chat_member"]["chat"]["id"].as_i64().unwrap();
+                    cx.bot.send_message(chat_id, "Unknown").send().await.unwrap();
+                })
+            })
+            .dispatch_with_listener(
+                update_listeners::from_stream(stream::iter(
+                    vec![message_update(1), Update::parse_or_unknown(unknown).unwrap()]
+                        .into_iter()
+                        .map(Ok::<_, Infallible>),
+                )),
+                IgnoringErrorHandlerSafe::new(),
+            )
+            .await;
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].params["chat_id"], 10);
+        assert_eq!(requests[0].params["text"], "Unknown");
+    }
+}
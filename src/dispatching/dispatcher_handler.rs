@@ -8,7 +8,11 @@ use futures::future::BoxFuture;
 /// See the [module-level documentation](crate::dispatching) for the design
 /// overview.
 ///
+/// [`Dispatcher`] can start a [`Clone`] handler anew after it has panicked,
+/// see [`Dispatcher::handler_failures`].
+///
 /// [`Dispatcher`]: crate::dispatching::Dispatcher
+/// [`Dispatcher::handler_failures`]: crate::dispatching::Dispatcher::handler_failures
 pub trait DispatcherHandler<Upd> {
     #[must_use]
     fn handle(self, updates: DispatcherHandlerRx<Upd>) -> BoxFuture<'static, ()>
@@ -1,5 +1,13 @@
-use crate::{prelude::UpdateWithCx, types::Message, utils::command::BotCommand};
+use crate::{
+    prelude::UpdateWithCx,
+    types::{CallbackQuery, Message},
+    utils::{
+        callback_data::{CallbackData, CallbackDataCodec, CallbackDataStore},
+        command::BotCommand,
+    },
+};
 use futures::{stream::BoxStream, Stream, StreamExt};
+use std::fmt::Debug;
 
 /// An extension trait to be used with [`DispatcherHandlerRx`].
 ///
@@ -20,6 +28,20 @@ pub trait DispatcherHandlerRxExt {
         Self: Stream<Item = UpdateWithCx<Message>>,
         C: BotCommand,
         N: Into<String> + Send;
+
+    /// Extracts only callback queries with their data parsed by `codec` from
+    /// this stream of arbitrary callback queries.
+    ///
+    /// Queries without data and with data that cannot be parsed are skipped.
+    fn callback_data<D, S>(
+        self,
+        codec: CallbackDataCodec<S>,
+    ) -> BoxStream<'static, (UpdateWithCx<CallbackQuery>, D)>
+    where
+        Self: Stream<Item = UpdateWithCx<CallbackQuery>>,
+        D: CallbackData + Send + 'static,
+        S: CallbackDataStore + Send + Sync + 'static,
+        S::Error: Debug;
 }
 
 impl<T> DispatcherHandlerRxExt for T
@@ -47,4 +69,30 @@ where
             async move { C::parse(&text, &bot_name).map(|command| (cx, command)).ok() }
         }))
     }
+
+    fn callback_data<D, S>(
+        self,
+        codec: CallbackDataCodec<S>,
+    ) -> BoxStream<'static, (UpdateWithCx<CallbackQuery>, D)>
+    where
+        Self: Stream<Item = UpdateWithCx<CallbackQuery>>,
+        D: CallbackData + Send + 'static,
+        S: CallbackDataStore + Send + Sync + 'static,
+        S::Error: Debug,
+    {
+        Box::pin(self.filter_map(move |cx| {
+            let codec = codec.clone();
+
+            async move {
+                let data = cx.update.data.as_deref()?;
+                match codec.decode(data).await {
+                    Ok(data) => Some((cx, data)),
+                    Err(error) => {
+                        log::trace!("Cannot parse callback data {:?}: {}", data, error);
+                        None
+                    }
+                }
+            }
+        }))
+    }
 }
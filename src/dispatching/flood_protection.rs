@@ -0,0 +1,561 @@
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use futures::future::BoxFuture;
+
+use crate::{
+    dispatching::{Middleware, UpdateWithCx},
+    error_handlers::OnError,
+    requests::Request,
+    types::{ChatPermissions, Update, UpdateKind},
+};
+
+/// How often full (i.e. idle) buckets are removed, in checked updates.
+const CLEANUP_INTERVAL: u64 = 1024;
+
+/// What updates are counted together.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum FloodKey {
+    /// Updates from the same user in all chats.
+    User,
+
+    /// Updates from the same chat.
+    Chat,
+
+    /// Updates from the same user in the same chat.
+    UserInChat,
+}
+
+/// A limit of the rate of incoming updates.
+///
+/// It's a token bucket: up to `count` updates are allowed at once, and then
+/// `count` updates per `period` are allowed, so that the allowed updates are
+/// evenly distributed over time.
+#[derive(Debug, Clone)]
+pub struct FloodLimit {
+    key: FloodKey,
+    count: u32,
+    period: Duration,
+    kinds: Vec<&'static str>,
+}
+
+impl FloodLimit {
+    /// Allows `count` updates with the same `key` per `period`.
+    ///
+    /// # Panics
+    /// If `count` or `period` is zero.
+    #[must_use]
+    pub fn new(key: FloodKey, count: u32, period: Duration) -> Self {
+        assert!(count > 0, "The count of a flood limit must be positive");
+        assert!(period > Duration::from_secs(0), "The period of a flood limit must be positive");
+        Self { key, count, period, kinds: Vec::new() }
+    }
+
+    /// Allows `count` updates from the same user per `period`.
+    #[must_use]
+    pub fn per_user(count: u32, period: Duration) -> Self {
+        Self::new(FloodKey::User, count, period)
+    }
+
+    /// Allows `count` updates from the same chat per `period`.
+    #[must_use]
+    pub fn per_chat(count: u32, period: Duration) -> Self {
+        Self::new(FloodKey::Chat, count, period)
+    }
+
+    /// Applies the limit only to updates of `kind`.
+    ///
+    /// Kinds are named as in the Bot API, e.g. `message` or `callback_query`
+    /// (see [`UpdateKind::name`]). Can be called several times to add more
+    /// kinds. By default, the limit applies to all the updates.
+    ///
+    /// [`UpdateKind::name`]: crate::types::UpdateKind::name
+    #[must_use]
+    pub fn kind(mut self, kind: &'static str) -> Self {
+        self.kinds.push(kind);
+        self
+    }
+
+    fn applies_to(&self, kind: &str) -> bool {
+        self.kinds.is_empty() || self.kinds.contains(&kind)
+    }
+
+    fn tokens_per_second(&self) -> f64 {
+        f64::from(self.count) / self.period.as_secs_f64()
+    }
+}
+
+/// What to do when a user or a chat floods.
+///
+/// An action is taken once a limit is exceeded. The next updates are dropped
+/// silently until the limit allows an update again.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum FloodAction {
+    /// Just drop updates.
+    Drop,
+
+    /// Send a warning to the chat, as a reply to the message that has
+    /// exceeded the limit (if the update is a message).
+    Warn(String),
+
+    /// Restrict the user in a supergroup for the specified duration, so that
+    /// they cannot send messages.
+    ///
+    /// A duration, which ends after 2038 (i.e. doesn't fit into the Bot API),
+    /// makes the restriction permanent.
+    ///
+    /// In other chats updates are just dropped. The bot must be an
+    /// administrator of a supergroup with the `can_restrict_members` right.
+    Restrict(Duration),
+}
+
+/// A flood detected by [`FloodProtection`].
+///
+/// [`FloodProtection`]: crate::dispatching::FloodProtection
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Flood {
+    /// The key of the exceeded limit.
+    pub key: FloodKey,
+
+    /// An ID of the user, who has sent the update, if any.
+    pub user_id: Option<i32>,
+
+    /// An ID of the chat of the update, if any.
+    pub chat_id: Option<i64>,
+
+    /// `true` if the limit has just been exceeded, i.e. [`FloodAction`] has
+    /// been taken for this update.
+    ///
+    /// [`FloodAction`]: crate::dispatching::FloodAction
+    pub first: bool,
+}
+
+type Hook = Box<dyn Fn(UpdateWithCx<Update>, Flood) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// A [`Middleware`], which drops updates of users and chats exceeding rate
+/// limits.
+///
+/// Every update is checked against all the [`FloodLimit`]s, which apply to
+/// it. Limits by users aren't applied to updates without a user (e.g. channel
+/// posts), and limits by chats aren't applied to updates without a chat (e.g.
+/// inline queries). If any of the limits is exceeded, the update is dropped
+/// and [`FloodAction`] is taken.
+///
+/// ## Example
+/// ```
+/// use std::time::Duration;
+/// use teloxide::{
+///     dispatching::{FloodAction, FloodLimit, FloodProtection},
+///     prelude::*,
+/// };
+///
+/// # async fn run() {
+/// let protection = FloodProtection::new()
+///     .limit(FloodLimit::per_user(5, Duration::from_secs(10)))
+///     .limit(FloodLimit::per_chat(20, Duration::from_secs(60)).kind("message"))
+///     .action(FloodAction::Warn("Slow down, please.".to_owned()))
+///     .on_flood(|cx: UpdateWithCx<Update>, flood| async move {
+///         log::warn!("A flood in the update {}: {:?}", cx.update.id, flood);
+///     });
+///
+/// Dispatcher::new(Bot::from_env())
+///     .middleware(protection)
+///     .messages_handler(|rx: DispatcherHandlerRx<Message>| {
+///         rx.for_each_concurrent(None, |cx| async move { /* ... */ })
+///     })
+///     .dispatch()
+///     .await;
+/// # }
+/// ```
+///
+/// [`Middleware`]: crate::dispatching::Middleware
+/// [`FloodLimit`]: crate::dispatching::FloodLimit
+/// [`FloodAction`]: crate::dispatching::FloodAction
+pub struct FloodProtection {
+    limits: Vec<FloodLimit>,
+    action: FloodAction,
+    hook: Option<Hook>,
+    buckets: Mutex<Buckets>,
+}
+
+impl FloodProtection {
+    /// Creates a protection without limits, which drops updates exceeding
+    /// them.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            limits: Vec::new(),
+            action: FloodAction::Drop,
+            hook: None,
+            buckets: Mutex::new(Buckets::default()),
+        }
+    }
+
+    /// Adds a limit.
+    #[must_use]
+    pub fn limit(mut self, limit: FloodLimit) -> Self {
+        self.limits.push(limit);
+        self
+    }
+
+    /// Sets what to do when a limit is exceeded.
+    ///
+    /// By default, it's [`FloodAction::Drop`].
+    ///
+    /// [`FloodAction::Drop`]: crate::dispatching::FloodAction::Drop
+    #[must_use]
+    pub fn action(mut self, action: FloodAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Sets a function, which is called with every dropped update.
+    #[must_use]
+    pub fn on_flood<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(UpdateWithCx<Update>, Flood) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.hook = Some(Box::new(move |cx, flood| Box::pin(hook(cx, flood))));
+        self
+    }
+
+    /// Takes a token from every bucket of `update`, returning a flood if
+    /// there's no token in one of them.
+    fn check(&self, update: &Update) -> Option<Flood> {
+        let kind = update.kind.name();
+        let user_id = update.user().map(|user| user.id);
+        let chat_id = update.chat().map(|chat| chat.id);
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets.checks += 1;
+        if buckets.checks >= CLEANUP_INTERVAL {
+            buckets.checks = 0;
+            let limits = &self.limits;
+            buckets.map.retain(|key, bucket| !bucket.refill(&limits[key.0], now));
+        }
+
+        let keys: Vec<_> = self
+            .limits
+            .iter()
+            .enumerate()
+            .filter(|(_, limit)| limit.applies_to(kind))
+            .filter_map(|(i, limit)| match (limit.key, user_id, chat_id) {
+                (FloodKey::User, Some(user_id), _) => Some((i, Some(user_id), None)),
+                (FloodKey::Chat, _, Some(chat_id)) => Some((i, None, Some(chat_id))),
+                (FloodKey::UserInChat, Some(user_id), Some(chat_id)) => {
+                    Some((i, Some(user_id), Some(chat_id)))
+                }
+                _ => None,
+            })
+            .collect();
+
+        // Tokens are taken only if no limit is exceeded, so that a dropped
+        // update doesn't use up the other limits.
+        for key in &keys {
+            let limit = &self.limits[key.0];
+            let bucket = buckets.map.entry(*key).or_insert_with(|| Bucket::full(limit, now));
+            if bucket.is_empty(limit, now) {
+                let first = bucket.flood();
+                return Some(Flood { key: limit.key, user_id, chat_id, first });
+            }
+        }
+
+        for key in &keys {
+            if let Some(bucket) = buckets.map.get_mut(key) {
+                bucket.take();
+            }
+        }
+
+        None
+    }
+
+    async fn act(&self, cx: &UpdateWithCx<Update>, flood: &Flood) {
+        match &self.action {
+            FloodAction::Drop => {}
+            FloodAction::Warn(text) => {
+                if let Some(chat_id) = flood.chat_id {
+                    let mut request = cx.bot.send_message(chat_id, text.clone());
+                    if let Some(message_id) = message_id(&cx.update) {
+                        request = request.reply_to_message_id(message_id);
+                    }
+                    request.send().await.log_on_error().await;
+                }
+            }
+            FloodAction::Restrict(duration) => match (cx.update.chat(), flood.user_id) {
+                (Some(chat), Some(user_id)) if chat.is_supergroup() => {
+                    let mut permissions = ChatPermissions::new();
+                    permissions.can_send_messages = Some(false);
+
+                    let mut request = cx.bot.restrict_chat_member(chat.id, user_id, permissions);
+                    if let Some(until) = restricted_until(*duration) {
+                        request = request.until_date(until);
+                    }
+                    request.send().await.log_on_error().await;
+                }
+                _ => {}
+            },
+        }
+    }
+}
+
+impl Default for FloodProtection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for FloodProtection {
+    async fn handle(&self, cx: UpdateWithCx<Update>) -> Option<Update> {
+        let flood = match self.check(&cx.update) {
+            Some(flood) => flood,
+            None => return Some(cx.update),
+        };
+        log::debug!("The update {} is dropped because of a flood: {:?}", cx.update.id, flood);
+
+        if flood.first {
+            self.act(&cx, &flood).await;
+        }
+        if let Some(hook) = &self.hook {
+            hook(cx, flood).await;
+        }
+
+        None
+    }
+}
+
+/// Buckets by indices of limits, user IDs and chat IDs.
+/// Returns an ID of the message of `update`, if it's a message.
+fn message_id(update: &Update) -> Option<i32> {
+    match &update.kind {
+        UpdateKind::Message(message)
+        | UpdateKind::EditedMessage(message)
+        | UpdateKind::ChannelPost(message)
+        | UpdateKind::EditedChannelPost(message) => Some(message.id),
+        _ => None,
+    }
+}
+
+/// Returns the Unix time, when a restriction for `duration` ends, or `None`
+/// if it doesn't fit into `i32`.
+fn restricted_until(duration: Duration) -> Option<i32> {
+    let until = SystemTime::now().checked_add(duration)?;
+    let until = until.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    i32::try_from(until).ok()
+}
+
+#[derive(Default)]
+struct Buckets {
+    map: HashMap<(usize, Option<i32>, Option<i64>), Bucket>,
+    checks: u64,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+
+    /// Whether the last update has been dropped.
+    flooding: bool,
+}
+
+impl Bucket {
+    fn full(limit: &FloodLimit, now: Instant) -> Self {
+        Self { tokens: f64::from(limit.count), updated: now, flooding: false }
+    }
+
+    /// Adds tokens for the time passed, returning `true` if the bucket is
+    /// full.
+    fn refill(&mut self, limit: &FloodLimit, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        let capacity = f64::from(limit.count);
+
+        self.tokens = (self.tokens + elapsed * limit.tokens_per_second()).min(capacity);
+        self.updated = now;
+        self.tokens >= capacity
+    }
+
+    /// Adds tokens for the time passed, returning `true` if there are no
+    /// tokens to take.
+    fn is_empty(&mut self, limit: &FloodLimit, now: Instant) -> bool {
+        self.refill(limit, now);
+        self.tokens < 1.0
+    }
+
+    /// Marks the last update as dropped, returning `true` if the limit has
+    /// just been exceeded.
+    fn flood(&mut self) -> bool {
+        let first = !self.flooding;
+        self.flooding = true;
+        first
+    }
+
+    /// Takes a token from a non-empty bucket.
+    fn take(&mut self) {
+        self.tokens -= 1.0;
+        self.flooding = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dispatching::{Dependencies, Dispatcher, DispatcherHandlerRx},
+        error_handlers::IgnoringErrorHandlerSafe,
+        testing::{self, MockMessage, MockServer},
+        types::{Message, UpdateKind, User},
+    };
+    use futures::StreamExt;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn buckets_are_refilled() {
+        let limit = FloodLimit::per_user(2, Duration::from_secs(10));
+        let now = Instant::now();
+        let mut bucket = Bucket::full(&limit, now);
+
+        let mut take = |at| {
+            if bucket.is_empty(&limit, now + Duration::from_secs(at)) {
+                Err(bucket.flood())
+            } else {
+                bucket.take();
+                Ok(())
+            }
+        };
+        assert_eq!(take(0), Ok(()));
+        assert_eq!(take(0), Ok(()));
+        assert_eq!(take(0), Err(true));
+        assert_eq!(take(1), Err(false));
+        // A token per 5 seconds.
+        assert_eq!(take(5), Ok(()));
+        assert_eq!(take(6), Err(true));
+        assert!(bucket.refill(&limit, now + Duration::from_secs(16)));
+    }
+
+    #[test]
+    fn dropped_updates_dont_use_up_other_limits() {
+        let protection = FloodProtection::new()
+            .limit(FloodLimit::per_user(2, Duration::from_secs(3600)))
+            .limit(FloodLimit::per_chat(1, Duration::from_secs(3600)));
+
+        let update = |id, user_id| {
+            let message =
+                MockMessage::new("hi").from(User::new(user_id, false, "User")).chat_id(-1).build();
+            Update::new(id, UpdateKind::Message(message))
+        };
+
+        assert!(protection.check(&update(1, 1)).is_none());
+
+        // The chat limit is exceeded, so the per-user limit isn't touched.
+        let flood = protection.check(&update(2, 1)).unwrap();
+        assert_eq!((flood.key, flood.first), (FloodKey::Chat, true));
+        let flood = protection.check(&update(3, 1)).unwrap();
+        assert_eq!((flood.key, flood.first), (FloodKey::Chat, false));
+
+        let buckets = protection.buckets.lock().unwrap();
+        assert!((buckets.map[&(0, Some(1), None)].tokens - 1.0).abs() < 0.01);
+        assert!(buckets.map[&(1, None, Some(-1))].tokens < 0.01);
+    }
+
+    #[tokio::test]
+    async fn floods_are_dropped() {
+        let server = MockServer::start();
+        let handled = Arc::new(Mutex::new(Vec::new()));
+        let floods = Arc::new(Mutex::new(Vec::new()));
+
+        let protection = FloodProtection::new()
+            .limit(FloodLimit::per_user(2, Duration::from_secs(3600)))
+            .limit(FloodLimit::per_chat(1, Duration::from_secs(3600)).kind("edited_message"))
+            .action(FloodAction::Warn("Slow down".to_owned()))
+            .on_flood({
+                let floods = Arc::clone(&floods);
+                move |cx, flood| {
+                    floods.lock().unwrap().push((cx.update.id, flood.first));
+                    async {}
+                }
+            });
+
+        let message = |id| MockMessage::new("/start").id(id).build();
+        Dispatcher::new(server.bot())
+            .middleware(protection)
+            .messages_handler({
+                let handled = Arc::clone(&handled);
+                move |rx: DispatcherHandlerRx<Message>| {
+                    rx.for_each(move |cx| {
+                        handled.lock().unwrap().push(cx.update.id);
+                        async {}
+                    })
+                }
+            })
+            .dispatch_with_listener(
+                testing::updates(vec![
+                    UpdateKind::Message(message(1)),
+                    UpdateKind::Message(
+                        MockMessage::new("hi").id(2).from(User::new(2, false, "Other")).build(),
+                    ),
+                    UpdateKind::Message(message(3)),
+                    UpdateKind::Message(message(4)),
+                    UpdateKind::Message(message(5)),
+                ]),
+                IgnoringErrorHandlerSafe::new(),
+            )
+            .await;
+
+        assert_eq!(*handled.lock().unwrap(), [1, 2, 3]);
+        assert_eq!(*floods.lock().unwrap(), [(4, true), (5, false)]);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "sendMessage");
+        assert_eq!(requests[0].params["text"], "Slow down");
+        assert_eq!(requests[0].params["reply_to_message_id"], 4);
+    }
+
+    #[tokio::test]
+    async fn users_are_restricted_in_supergroups() {
+        let server = MockServer::start();
+        let protection = FloodProtection::new()
+            .limit(FloodLimit::new(FloodKey::UserInChat, 1, Duration::from_secs(3600)))
+            .action(FloodAction::Restrict(Duration::from_secs(60)));
+
+        let message: Message = serde_json::from_str(
+            r#"{
+                "message_id": 1,
+                "date": 0,
+                "chat": { "id": -100, "type": "supergroup", "title": "Group" },
+                "from": { "id": 5, "is_bot": false, "first_name": "User" },
+                "text": "spam"
+            }"#,
+        )
+        .unwrap();
+
+        for id in 0..3 {
+            let update = Update::new(id, UpdateKind::Message(message.clone()));
+            let cx = UpdateWithCx {
+                bot: server.bot(),
+                update,
+                dependencies: Dependencies::new(),
+                completion: Default::default(),
+            };
+            assert_eq!(protection.handle(cx).await.is_some(), id == 0);
+        }
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "restrictChatMember");
+        assert_eq!(requests[0].params["chat_id"], -100);
+        assert_eq!(requests[0].params["user_id"], 5);
+        assert_eq!(requests[0].params["permissions"]["can_send_messages"], false);
+        assert!(requests[0].params["until_date"].as_i64().unwrap() > 0);
+
+        assert!(restricted_until(Duration::from_secs(100 * 365 * 24 * 60 * 60)).is_none());
+        assert!(restricted_until(Duration::from_secs(u64::MAX)).is_none());
+    }
+}
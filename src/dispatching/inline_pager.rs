@@ -0,0 +1,481 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use futures::{future::BoxFuture, StreamExt};
+
+use crate::{
+    dispatching::{
+        panics::catch_panic, router::report, DispatcherHandler, DispatcherHandlerRx, UpdateWithCx,
+    },
+    requests::Request,
+    types::{ChosenInlineResult, InlineQuery, InlineQueryResult},
+};
+
+/// The maximum number of results in an answer to an inline query.
+const MAX_PAGE_SIZE: usize = 50;
+
+/// How many sent results are remembered to be passed to a handler of chosen
+/// results.
+const REMEMBERED_RESULTS: usize = 10_000;
+
+type Provider = Arc<
+    dyn Fn(
+            UpdateWithCx<InlineQuery>,
+            usize,
+            usize,
+        ) -> BoxFuture<'static, Option<Vec<InlineQueryResult>>>
+        + Send
+        + Sync,
+>;
+
+/// A [`DispatcherHandler`], which answers inline queries with pages of
+/// results.
+///
+/// Results are provided by an asynchronous function, which accepts an inline
+/// query and an offset, i.e. the number of results the user has already
+/// received, and returns results starting from this offset. The pager takes
+/// no more than a page of them (50 results by default), answers the query and
+/// sets [`next_offset`], so that Telegram asks for the next page when the user
+/// scrolls the results down. A provider may return more results than a page,
+/// even all of them, since the rest are never taken from an iterator.
+///
+/// Queries are answered concurrently. Errors returned from the provider and
+/// errors of answering are logged.
+///
+/// If you want to know which results users choose, pass a handler to
+/// [`InlinePager::chosen_results`] and the returned [`ChosenInlineResults`]
+/// to [`Dispatcher::chosen_inline_results_handler`]. Note that chosen results
+/// are sent by Telegram only if [inline feedback] is enabled via @BotFather.
+///
+/// ## Example
+/// ```
+/// use std::convert::Infallible;
+///
+/// use teloxide::{
+///     dispatching::InlinePager,
+///     prelude::*,
+///     types::{
+///         ChosenInlineResult, InlineQuery, InlineQueryResult, InlineQueryResultArticle,
+///         InputMessageContent, InputMessageContentText,
+///     },
+/// };
+///
+/// async fn search(
+///     cx: UpdateWithCx<InlineQuery>,
+///     offset: usize,
+/// ) -> Result<impl Iterator<Item = InlineQueryResult>, Infallible> {
+///     let query = cx.update.query;
+///     Ok((offset..1000).map(move |i| {
+///         let text = format!("{} #{}", query, i);
+///         let content = InputMessageContent::Text(InputMessageContentText::new(text.clone()));
+///         InlineQueryResultArticle::new(i.to_string(), text, content).into()
+///     }))
+/// }
+///
+/// async fn chosen(
+///     _cx: UpdateWithCx<ChosenInlineResult>,
+///     result: Option<InlineQueryResult>,
+/// ) -> Result<(), Infallible> {
+///     log::info!("Chosen: {:?}", result);
+///     Ok(())
+/// }
+///
+/// # async fn run() {
+/// let pager = InlinePager::new(search).cache_time(60);
+/// let chosen = pager.chosen_results(chosen);
+///
+/// Dispatcher::new(Bot::from_env())
+///     .inline_queries_handler(pager)
+///     .chosen_inline_results_handler(chosen)
+///     .dispatch()
+///     .await;
+/// # }
+/// ```
+///
+/// [`DispatcherHandler`]: crate::dispatching::DispatcherHandler
+/// [`next_offset`]: crate::requests::AnswerInlineQuery::next_offset
+/// [`InlinePager::chosen_results`]: crate::dispatching::InlinePager::chosen_results
+/// [`ChosenInlineResults`]: crate::dispatching::ChosenInlineResults
+/// [`Dispatcher::chosen_inline_results_handler`]:
+/// crate::dispatching::Dispatcher::chosen_inline_results_handler
+/// [inline feedback]: https://core.telegram.org/bots/inline#collecting-feedback
+#[derive(Clone)]
+pub struct InlinePager {
+    provider: Provider,
+    page_size: usize,
+    cache_time: i32,
+    is_personal: bool,
+    sent: Arc<Mutex<Option<SentResults>>>,
+}
+
+impl InlinePager {
+    /// Answers inline queries with results of `provider`.
+    #[must_use]
+    pub fn new<P, Fut, I, E>(provider: P) -> Self
+    where
+        P: Fn(UpdateWithCx<InlineQuery>, usize) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<I, E>> + Send + 'static,
+        I: IntoIterator<Item = InlineQueryResult>,
+        E: Debug + Send,
+    {
+        Self {
+            provider: Arc::new(move |cx, offset, limit| {
+                let completion = cx.completion.clone();
+                let fut = provider(cx, offset);
+
+                Box::pin(async move {
+                    // Results aren't required to be `Send`, so they're collected
+                    // before reporting an error.
+                    let results =
+                        fut.await.map(|results| results.into_iter().take(limit).collect());
+                    match results {
+                        Ok(results) => Some(results),
+                        Err(error) => {
+                            report(&completion, Err(error)).await;
+                            None
+                        }
+                    }
+                })
+            }),
+            page_size: MAX_PAGE_SIZE,
+            cache_time: 300,
+            is_personal: false,
+            sent: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Sets the maximum number of results in a page.
+    ///
+    /// By default, it's 50, the maximum allowed by Telegram.
+    ///
+    /// # Panics
+    /// If `size` is 0 or greater than 50.
+    #[must_use]
+    pub fn page_size(mut self, size: usize) -> Self {
+        assert!(
+            size > 0 && size <= MAX_PAGE_SIZE,
+            "The size of a page must be from 1 to {}",
+            MAX_PAGE_SIZE
+        );
+        self.page_size = size;
+        self
+    }
+
+    /// Sets the maximum amount of time in seconds that results of a query may
+    /// be cached on the server.
+    ///
+    /// By default, it's 300.
+    #[must_use]
+    pub fn cache_time(mut self, seconds: i32) -> Self {
+        self.cache_time = seconds;
+        self
+    }
+
+    /// Sets whether results may be cached on the server only for the user
+    /// that sent a query.
+    ///
+    /// By default, it's `false`, so results of a query may be returned to any
+    /// user who sends the same query.
+    #[allow(clippy::wrong_self_convention)]
+    #[must_use]
+    pub fn is_personal(mut self, val: bool) -> Self {
+        self.is_personal = val;
+        self
+    }
+
+    /// Creates a handler of results chosen by users.
+    ///
+    /// `handler` receives a chosen result, as it was sent by this pager, if
+    /// it's still remembered (the last 10000 sent results are), or `None`
+    /// otherwise.
+    #[must_use]
+    pub fn chosen_results<H, Fut, E>(&self, handler: H) -> ChosenInlineResults
+    where
+        H: Fn(UpdateWithCx<ChosenInlineResult>, Option<InlineQueryResult>) -> Fut
+            + Send
+            + Sync
+            + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Debug + Send,
+    {
+        self.sent.lock().unwrap().get_or_insert_with(SentResults::default);
+
+        ChosenInlineResults {
+            sent: Arc::clone(&self.sent),
+            handler: Arc::new(move |cx, result| {
+                let completion = cx.completion.clone();
+                let fut = handler(cx, result);
+                Box::pin(async move { report(&completion, fut.await).await })
+            }),
+        }
+    }
+
+    async fn answer(&self, cx: UpdateWithCx<InlineQuery>) {
+        let bot = cx.bot.clone();
+        let completion = cx.completion.clone();
+        let query = cx.update.clone();
+        // Offsets are set by the pager, so anything else is the first page.
+        let offset = query.offset.parse().unwrap_or(0);
+
+        let mut results = match (self.provider)(cx, offset, self.page_size + 1).await {
+            Some(results) => results,
+            None => return,
+        };
+
+        let next_offset = if results.len() > self.page_size {
+            results.truncate(self.page_size);
+            (offset + self.page_size).to_string()
+        } else {
+            String::new()
+        };
+
+        if let Some(sent) = self.sent.lock().unwrap().as_mut() {
+            for result in &results {
+                sent.insert(&query.from.id, &query.query, result.clone());
+            }
+        }
+
+        let answer = bot
+            .answer_inline_query(query.id, results)
+            .cache_time(self.cache_time)
+            .is_personal(self.is_personal)
+            .next_offset(next_offset)
+            .send()
+            .await;
+        report(&completion, answer.map(drop)).await;
+    }
+}
+
+impl DispatcherHandler<InlineQuery> for InlinePager {
+    fn handle(self, updates: DispatcherHandlerRx<InlineQuery>) -> BoxFuture<'static, ()>
+    where
+        UpdateWithCx<InlineQuery>: Send + 'static,
+    {
+        if let Some(sent) = self.sent.lock().unwrap().as_mut() {
+            sent.personal = self.is_personal;
+        }
+        let pager = Arc::new(self);
+
+        Box::pin(updates.for_each_concurrent(None, move |cx| {
+            let pager = Arc::clone(&pager);
+
+            async move {
+                let (dependencies, completion) = (cx.dependencies.clone(), cx.completion.clone());
+                catch_panic(&dependencies, &completion, async move { pager.answer(cx).await })
+                    .await;
+            }
+        }))
+    }
+}
+
+type Handler = Arc<
+    dyn Fn(UpdateWithCx<ChosenInlineResult>, Option<InlineQueryResult>) -> BoxFuture<'static, ()>
+        + Send
+        + Sync,
+>;
+
+/// A [`DispatcherHandler`] of inline results chosen by users, created by
+/// [`InlinePager::chosen_results`].
+///
+/// Chosen results are handled concurrently. Errors returned from the handler
+/// are logged.
+///
+/// [`DispatcherHandler`]: crate::dispatching::DispatcherHandler
+/// [`InlinePager::chosen_results`]: crate::dispatching::InlinePager::chosen_results
+#[derive(Clone)]
+pub struct ChosenInlineResults {
+    sent: Arc<Mutex<Option<SentResults>>>,
+    handler: Handler,
+}
+
+impl DispatcherHandler<ChosenInlineResult> for ChosenInlineResults {
+    fn handle(self, updates: DispatcherHandlerRx<ChosenInlineResult>) -> BoxFuture<'static, ()>
+    where
+        UpdateWithCx<ChosenInlineResult>: Send + 'static,
+    {
+        let this = Arc::new(self);
+
+        Box::pin(updates.for_each_concurrent(None, move |cx| {
+            let this = Arc::clone(&this);
+
+            async move {
+                let (dependencies, completion) = (cx.dependencies.clone(), cx.completion.clone());
+                catch_panic(&dependencies, &completion, async move {
+                    let chosen = &cx.update;
+                    let result = this.sent.lock().unwrap().as_ref().and_then(|sent| {
+                        sent.get(&chosen.from.id, &chosen.query, &chosen.result_id)
+                    });
+                    (this.handler)(cx, result).await
+                })
+                .await;
+            }
+        }))
+    }
+}
+
+/// A user ID (only for personal results), a query and a result ID.
+type SentKey = (Option<i32>, String, String);
+
+/// Results sent recently.
+#[derive(Default)]
+struct SentResults {
+    /// Whether results are personal, i.e. the same query of different users
+    /// can give different results.
+    personal: bool,
+    order: VecDeque<SentKey>,
+    results: HashMap<SentKey, InlineQueryResult>,
+}
+
+impl SentResults {
+    fn key(&self, user_id: &i32, query: &str, result_id: &str) -> SentKey {
+        let user_id = if self.personal { Some(*user_id) } else { None };
+        (user_id, query.to_owned(), result_id.to_owned())
+    }
+
+    fn insert(&mut self, user_id: &i32, query: &str, result: InlineQueryResult) {
+        let key = self.key(user_id, query, result.id());
+        if self.results.insert(key.clone(), result).is_none() {
+            self.order.push_back(key);
+        }
+
+        if self.order.len() > REMEMBERED_RESULTS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.results.remove(&oldest);
+            }
+        }
+    }
+
+    fn get(&self, user_id: &i32, query: &str, result_id: &str) -> Option<InlineQueryResult> {
+        self.results.get(&self.key(user_id, query, result_id)).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dispatching::{
+            queue::{self, QueueOptions},
+            Dependencies, Dispatcher,
+        },
+        error_handlers::IgnoringErrorHandlerSafe,
+        testing::{self, mock_user, MockServer},
+        types::{
+            InlineQueryResultArticle, InputMessageContent, InputMessageContentText, UpdateKind,
+        },
+    };
+    use serde_json::json;
+    use std::{convert::Infallible, iter};
+    use tokio::sync::mpsc;
+
+    fn article(id: usize) -> InlineQueryResult {
+        let content = InputMessageContent::Text(InputMessageContentText::new("text"));
+        InlineQueryResultArticle::new(id.to_string(), "title", content).into()
+    }
+
+    async fn numbers(
+        cx: UpdateWithCx<InlineQuery>,
+        offset: usize,
+    ) -> Result<impl Iterator<Item = InlineQueryResult>, Infallible> {
+        let count: usize = cx.update.query.parse().unwrap();
+        Ok((offset..count).map(article))
+    }
+
+    #[tokio::test]
+    async fn answers_with_pages() {
+        let server = MockServer::start();
+
+        Dispatcher::new(server.bot())
+            .inline_queries_handler(InlinePager::new(numbers).page_size(2).cache_time(10))
+            .dispatch_with_listener(
+                testing::updates(vec![
+                    UpdateKind::InlineQuery(InlineQuery::new("1", mock_user(), "5", "")),
+                    UpdateKind::InlineQuery(InlineQuery::new("2", mock_user(), "5", "2")),
+                    UpdateKind::InlineQuery(InlineQuery::new("3", mock_user(), "5", "4")),
+                    UpdateKind::InlineQuery(InlineQuery::new("4", mock_user(), "4", "2")),
+                ]),
+                IgnoringErrorHandlerSafe::new(),
+            )
+            .await;
+
+        let mut answers: Vec<_> = server
+            .requests()
+            .into_iter()
+            .map(|req| {
+                let ids: Vec<_> = req.params["results"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|r| r["id"].clone())
+                    .collect();
+                assert_eq!(req.params["cache_time"], 10);
+                assert_eq!(req.params["is_personal"], false);
+                (
+                    req.params["inline_query_id"].clone(),
+                    json!(ids),
+                    req.params["next_offset"].clone(),
+                )
+            })
+            .collect();
+        answers.sort_by_key(|answer| answer.0.to_string());
+
+        assert_eq!(
+            answers,
+            [
+                (json!("1"), json!(["0", "1"]), json!("2")),
+                (json!("2"), json!(["2", "3"]), json!("4")),
+                (json!("3"), json!(["4"]), json!("")),
+                (json!("4"), json!(["2", "3"]), json!("")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn correlates_chosen_results() {
+        let server = MockServer::start();
+        let pager = InlinePager::new(|_, offset| async move {
+            Ok::<_, Infallible>(iter::once(article(offset)))
+        });
+        let (chosen_tx, mut chosen_rx) = mpsc::unbounded_channel();
+        let chosen = pager.chosen_results(move |cx, result| {
+            chosen_tx.send((cx.update.query, result)).unwrap();
+            async { Ok::<_, Infallible>(()) }
+        });
+
+        let (tx, rx) = queue::channel(QueueOptions::unbounded());
+        let query = InlineQuery::new("1", mock_user(), "cats", "");
+        tx.send(UpdateWithCx {
+            bot: server.bot(),
+            update: query,
+            dependencies: Dependencies::new(),
+            completion: Default::default(),
+        })
+        .await
+        .unwrap();
+        drop(tx);
+        pager.handle(rx).await;
+
+        let (tx, rx) = queue::channel(QueueOptions::unbounded());
+        for query in &["cats", "dogs"] {
+            let result = ChosenInlineResult::new("0", mock_user(), *query);
+            tx.send(UpdateWithCx {
+                bot: server.bot(),
+                update: result,
+                dependencies: Dependencies::new(),
+                completion: Default::default(),
+            })
+            .await
+            .unwrap();
+        }
+        drop(tx);
+        chosen.handle(rx).await;
+
+        let mut chosen = vec![chosen_rx.recv().await.unwrap(), chosen_rx.recv().await.unwrap()];
+        chosen.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(chosen, [("cats".to_owned(), Some(article(0))), ("dogs".to_owned(), None)]);
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::time::delay_for;
+
+use crate::{
+    dispatching::{queue, Completion, UpdateWithCx},
+    types::Message,
+};
+
+#[derive(Default)]
+struct Pending {
+    messages: Vec<UpdateWithCx<Message>>,
+
+    /// Incremented on every message, so that only the timer started by the
+    /// last message pushes the group.
+    generation: u64,
+}
+
+/// Groups messages of media groups (albums) and pushes every group into a
+/// queue after no messages of it have been received during a window.
+pub(crate) struct MediaGroups {
+    window: Duration,
+    tx: queue::Sender<UpdateWithCx<Vec<Message>>>,
+    pending: Mutex<HashMap<String, Pending>>,
+}
+
+impl MediaGroups {
+    pub(crate) fn new(window: Duration, tx: queue::Sender<UpdateWithCx<Vec<Message>>>) -> Self {
+        Self { window, tx, pending: Mutex::new(HashMap::new()) }
+    }
+
+    /// Adds a message of the media group `group_id`.
+    ///
+    /// A spawned timer holds `self`, so the queue is closed only after all
+    /// the pending groups have been pushed.
+    pub(crate) fn push(self: &Arc<Self>, group_id: String, cx: UpdateWithCx<Message>) {
+        let generation = {
+            let mut pending = self.pending.lock().unwrap();
+            let group = pending.entry(group_id.clone()).or_default();
+            group.messages.push(cx);
+            group.generation += 1;
+            group.generation
+        };
+
+        let this = Arc::clone(self);
+        tokio::spawn(async move {
+            delay_for(this.window).await;
+
+            let messages = {
+                let mut pending = this.pending.lock().unwrap();
+                match pending.get(&group_id) {
+                    Some(group) if group.generation == generation => {
+                        pending.remove(&group_id).unwrap().messages
+                    }
+                    _ => return,
+                }
+            };
+
+            this.send(messages).await;
+        });
+    }
+
+    async fn send(&self, mut messages: Vec<UpdateWithCx<Message>>) {
+        messages.sort_by_key(|cx| cx.update.id);
+
+        let bot = messages[0].bot.clone();
+        let dependencies = messages[0].dependencies.clone();
+        let mut parts = Vec::with_capacity(messages.len());
+        let mut update = Vec::with_capacity(messages.len());
+        for cx in messages {
+            parts.push(cx.completion);
+            update.push(cx.update);
+        }
+        let completion = Completion::group(parts);
+
+        let cx = UpdateWithCx { bot, update, dependencies, completion: completion.clone() };
+        if let Err(error) = self.tx.send(cx).await {
+            log::error!(
+                "The RX part of the media groups channel is closed, but an update is \
+                 received.\nError:{}\n",
+                error
+            );
+        }
+
+        completion.record_queue_depth(self.tx.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dispatching::{Dispatcher, DispatcherHandlerRx},
+        error_handlers::IgnoringErrorHandlerSafe,
+        testing::{self, mock_user, MockMessage, MockServer},
+        types::{Message, UpdateKind},
+    };
+    use futures::StreamExt;
+    use serde_json::json;
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+    use tokio::sync::Mutex;
+
+    fn photo(id: i32, group_id: &str) -> Message {
+        let message = json!({
+            "message_id": id,
+            "date": 0,
+            "chat": { "id": 1, "type": "private", "first_name": "User" },
+            "from": mock_user(),
+            "photo": [{ "file_id": "id", "file_unique_id": "id", "width": 1, "height": 1 }],
+            "media_group_id": group_id,
+        });
+        serde_json::from_str(&message.to_string()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn groups_are_handled_together() {
+        let server = MockServer::start();
+        let groups = Arc::new(Mutex::new(Vec::new()));
+        let messages = Arc::new(Mutex::new(Vec::new()));
+
+        let started = Instant::now();
+
+        Dispatcher::new(server.bot())
+            .media_groups_handler({
+                let groups = Arc::clone(&groups);
+                move |rx: DispatcherHandlerRx<Vec<Message>>| {
+                    rx.for_each(move |cx| {
+                        let groups = Arc::clone(&groups);
+                        async move {
+                            let ids: Vec<_> = cx.update.iter().map(|message| message.id).collect();
+                            groups.lock().await.push(ids);
+                        }
+                    })
+                }
+            })
+            .messages_handler({
+                let messages = Arc::clone(&messages);
+                move |rx: DispatcherHandlerRx<Message>| {
+                    rx.for_each(move |cx| {
+                        let messages = Arc::clone(&messages);
+                        async move { messages.lock().await.push(cx.update.id) }
+                    })
+                }
+            })
+            // The window is applied to the handler added above.
+            .media_group_window(Duration::from_millis(50))
+            .dispatch_with_listener(
+                testing::updates(vec![
+                    UpdateKind::Message(photo(2, "a")),
+                    UpdateKind::Message(photo(3, "b")),
+                    UpdateKind::Message(MockMessage::new("text").id(4).build()),
+                    UpdateKind::Message(photo(1, "a")),
+                ]),
+                IgnoringErrorHandlerSafe::new(),
+            )
+            .await;
+
+        let mut groups = groups.lock().await.clone();
+        groups.sort();
+        assert_eq!(groups, [vec![1, 2], vec![3]]);
+        assert_eq!(*messages.lock().await, [4]);
+        assert!(started.elapsed() < Duration::from_millis(500));
+    }
+}
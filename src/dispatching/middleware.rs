@@ -0,0 +1,69 @@
+use std::{fmt, future::Future};
+
+use crate::{dispatching::UpdateWithCx, types::Update};
+
+/// A middleware of [`Dispatcher`], which is run on every update before it's
+/// pushed to a handler.
+///
+/// Middlewares are run sequentially in the order they were added via
+/// [`Dispatcher::middleware`]. Each of them receives an update returned from
+/// the previous one, so it can inspect or modify it, or drop it by returning
+/// `None`. In the latter case, the rest of the middlewares and the handlers
+/// don't see the update, so a middleware is also able to short-circuit
+/// handling, e.g. by replying to a user itself.
+///
+/// Updates are passed through the middlewares one by one, so a slow
+/// middleware delays all the subsequent updates.
+///
+/// Functions that accept [`UpdateWithCx<Update>`] and return
+/// `Future<Output = Option<Update>>` are middlewares too.
+///
+/// ## Example
+/// ```
+/// use teloxide::{dispatching::UpdateWithCx, prelude::*};
+///
+/// const BANNED_USERS: &[i32] = &[218_485_655];
+///
+/// fn dispatcher(bot: Bot) -> Dispatcher {
+///     Dispatcher::new(bot).middleware(|cx: UpdateWithCx<Update>| async move {
+///         match cx.update.user() {
+///             Some(user) if BANNED_USERS.contains(&user.id) => {
+///                 if let Some(chat) = cx.update.chat() {
+///                     let reply = cx.bot.send_message(chat.id, "You are banned.");
+///                     reply.send().await.log_on_error().await;
+///                 }
+///                 None
+///             }
+///             _ => Some(cx.update),
+///         }
+///     })
+/// }
+/// ```
+///
+/// [`Dispatcher`]: crate::dispatching::Dispatcher
+/// [`Dispatcher::middleware`]: crate::dispatching::Dispatcher::middleware
+/// [`UpdateWithCx<Update>`]: crate::dispatching::UpdateWithCx
+#[async_trait::async_trait]
+pub trait Middleware: Send + Sync + 'static {
+    /// Handles an update.
+    ///
+    /// Returns the update to pass further or `None` to drop it.
+    async fn handle(&self, cx: UpdateWithCx<Update>) -> Option<Update>;
+}
+
+#[async_trait::async_trait]
+impl<F, Fut> Middleware for F
+where
+    F: Fn(UpdateWithCx<Update>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Option<Update>> + Send,
+{
+    async fn handle(&self, cx: UpdateWithCx<Update>) -> Option<Update> {
+        self(cx).await
+    }
+}
+
+impl fmt::Debug for dyn Middleware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Middleware")
+    }
+}
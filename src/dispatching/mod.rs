@@ -3,8 +3,8 @@
 //! The key type here is [`Dispatcher`]. It encapsulates [`Bot`] and handlers
 //! for [all the update kinds].
 //!
-//! Every handler accept [`tokio::sync::mpsc::UnboundedReceiver`] (the RX halve
-//! of an asynchronous channel). Inside a body of your handler, you typically
+//! Every handler accept [`DispatcherHandlerRx`] (the RX halve of an
+//! asynchronous [queue]). Inside a body of your handler, you typically
 //! asynchronously concurrently iterate through updates like this:
 //!
 //! ```
@@ -24,12 +24,26 @@
 //! **Note** that handlers must implement [`DispatcherHandler`], which means
 //! that:
 //!  - You are able to supply [`DialogueDispatcher`] as a handler.
-//!  - You are able to supply functions that accept
-//!    [`tokio::sync::mpsc::UnboundedReceiver`] and return `Future<Output = ()`
-//!    as a handler.
+//!  - You are able to supply functions that accept [`DispatcherHandlerRx`]
+//!    and return `Future<Output = ()` as a handler.
 //!
 //! Since they implement [`DispatcherHandler`] too.
 //!
+//! For non-trivial bots, [`Router`] composes handlers into a tree of
+//! branches with filters, so that an update is handled by the first matching
+//! endpoint. [`Sequential`] handles updates from the same chat (or with the
+//! same custom key) in order, but concurrently with other chats.
+//! [`InlinePager`] answers inline queries with pages of results.
+//!
+//! Before an update is pushed into a handler, it's passed through
+//! [`Middleware`]s added via [`Dispatcher::middleware`], which can inspect,
+//! modify or drop it. It's useful for checks common to all the handlers,
+//! e.g. filtering out banned users.
+//!
+//! Simple multi-step flows can be written without [`DialogueDispatcher`]:
+//! after [`Dispatcher::conversations`], a handler can ask a question via
+//! [`UpdateWithCx::ask`] and get a reply of the same user right there.
+//!
 //! [See the examples](https://github.com/teloxide/teloxide/tree/master/examples).
 //!
 //! [`Dispatcher`]: crate::dispatching::Dispatcher
@@ -37,27 +51,60 @@
 //! [`Update`]: crate::types::Update
 //! [`ErrorHandler`]: crate::dispatching::ErrorHandler
 //! [`DispatcherHandler`]: crate::dispatching::DispatcherHandler
+//! [`Middleware`]: crate::dispatching::Middleware
+//! [`Router`]: crate::dispatching::Router
+//! [`Sequential`]: crate::dispatching::Sequential
+//! [`InlinePager`]: crate::dispatching::InlinePager
+//! [`Dispatcher::middleware`]: crate::dispatching::Dispatcher::middleware
+//! [`Dispatcher::conversations`]: crate::dispatching::Dispatcher::conversations
+//! [`UpdateWithCx::ask`]: crate::dispatching::UpdateWithCx::ask
 //! [`DialogueDispatcher`]: crate::dispatching::dialogue::DialogueDispatcher
 //! [`DispatcherHandlerResult`]: crate::dispatching::DispatcherHandlerResult
 //! [`Bot`]: crate::Bot
-//! [`tokio::sync::mpsc::UnboundedReceiver`]: https://docs.rs/tokio/0.2.11/tokio/sync/mpsc/struct.UnboundedReceiver.html
+//! [`DispatcherHandlerRx`]: crate::dispatching::DispatcherHandlerRx
+//! [queue]: crate::dispatching::queue
 //! [examples/dialogue_bot]: https://github.com/teloxide/teloxide/tree/master/examples/dialogue_bot
 
+mod admin_cache;
+mod completion;
+mod conversations;
+mod dependencies;
 pub mod dialogue;
 mod dispatcher;
 mod dispatcher_handler;
 mod dispatcher_handler_rx_ext;
+mod flood_protection;
+mod inline_pager;
+mod media_groups;
+mod middleware;
+mod multi_dispatcher;
+mod panics;
+pub mod queue;
 pub(crate) mod repls;
+mod router;
+mod sequential;
+mod stop_token;
 pub mod update_listeners;
 mod update_with_cx;
 
+pub use admin_cache::{AdminCache, AdminCheckError};
+pub use completion::Completion;
+pub use conversations::{AskError, Conversations};
+pub use dependencies::{Dependencies, FromDependencies, MissingDependency};
 pub use dispatcher::Dispatcher;
 pub use dispatcher_handler::DispatcherHandler;
 pub use dispatcher_handler_rx_ext::DispatcherHandlerRxExt;
-use tokio::sync::mpsc::UnboundedReceiver;
+pub use flood_protection::{Flood, FloodAction, FloodKey, FloodLimit, FloodProtection};
+pub use inline_pager::{ChosenInlineResults, InlinePager};
+pub use middleware::Middleware;
+pub use multi_dispatcher::{BotKey, MultiDispatcher};
+pub use panics::HandlerPanic;
+pub use router::{Route, Router};
+pub use sequential::Sequential;
+pub use stop_token::StopToken;
 pub use update_with_cx::UpdateWithCx;
 
 /// A type of a stream, consumed by [`Dispatcher`]'s handlers.
 ///
 /// [`Dispatcher`]: crate::dispatching::Dispatcher
-pub type DispatcherHandlerRx<Upd> = UnboundedReceiver<UpdateWithCx<Upd>>;
+pub type DispatcherHandlerRx<Upd> = queue::Receiver<UpdateWithCx<Upd>>;
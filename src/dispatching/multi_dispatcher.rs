@@ -0,0 +1,332 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug},
+    hash::Hash,
+    sync::{Arc, Mutex},
+};
+
+use futures::future;
+use tokio::task::JoinHandle;
+
+use crate::{
+    dispatching::{
+        update_listeners::{self, UpdateListener},
+        Dispatcher, StopToken,
+    },
+    error_handlers::{ErrorHandler, LoggingErrorHandler},
+    Bot,
+};
+
+/// A key of a bot, which an update came from.
+///
+/// [`MultiDispatcher`] registers it as a dependency of every bot, so it can be
+/// extracted in handlers via [`UpdateWithCx::dependency`].
+///
+/// [`MultiDispatcher`]: crate::dispatching::MultiDispatcher
+/// [`UpdateWithCx::dependency`]: crate::dispatching::UpdateWithCx::dependency
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct BotKey<K>(pub K);
+
+/// A dispatcher of many bots.
+///
+/// Every bot has its own [`Dispatcher`], built by a shared factory, and its
+/// own update listener. Bots can be added and removed at any time, without
+/// affecting the other bots.
+///
+/// ## Example
+/// ```no_run
+/// use teloxide::{
+///     dispatching::{BotKey, MultiDispatcher},
+///     prelude::*,
+///     BotBuilder,
+/// };
+///
+/// # async fn run(tokens: Vec<(u32, String)>) {
+/// let dispatcher = MultiDispatcher::new(|bot| {
+///     Dispatcher::new(bot).messages_handler(|rx: DispatcherHandlerRx<Message>| {
+///         rx.for_each(|message| async move {
+///             let BotKey(customer_id): BotKey<u32> = message.dependency();
+///             let text = format!("Hello from the bot of the customer {}!", customer_id);
+///             message.answer_str(text).await.log_on_error().await;
+///         })
+///     })
+/// });
+///
+/// for (customer_id, token) in tokens {
+///     dispatcher.add_bot(customer_id, BotBuilder::new().token(token).build()).await;
+/// }
+///
+/// tokio::signal::ctrl_c().await.unwrap();
+/// dispatcher.shutdown().await;
+/// # }
+/// ```
+///
+/// [`Dispatcher`]: crate::dispatching::Dispatcher
+pub struct MultiDispatcher<K> {
+    factory: Box<dyn Fn(Bot) -> Dispatcher + Send + Sync>,
+    bots: Mutex<HashMap<K, RunningBot>>,
+}
+
+struct RunningBot {
+    shutdown: StopToken,
+    handle: JoinHandle<()>,
+}
+
+impl<K> MultiDispatcher<K>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+{
+    /// Creates a dispatcher, which builds a [`Dispatcher`] of every bot via
+    /// `factory`.
+    ///
+    /// [`Dispatcher`]: crate::dispatching::Dispatcher
+    #[must_use]
+    pub fn new<F>(factory: F) -> Self
+    where
+        F: Fn(Bot) -> Dispatcher + Send + Sync + 'static,
+    {
+        Self { factory: Box::new(factory), bots: Mutex::new(HashMap::new()) }
+    }
+
+    /// Starts dispatching updates of `bot`, received via
+    /// [`polling_default`].
+    ///
+    /// If there's already a bot with `key`, it's gracefully shut down at
+    /// first, see [`MultiDispatcher::add_bot_with_listener`].
+    ///
+    /// # Panics
+    /// If it's called outside of the Tokio runtime.
+    ///
+    /// [`polling_default`]: crate::dispatching::update_listeners::polling_default
+    /// [`MultiDispatcher::add_bot_with_listener`]:
+    /// crate::dispatching::MultiDispatcher::add_bot_with_listener
+    pub async fn add_bot(&self, key: K, bot: Bot) {
+        let listener = update_listeners::polling_default(bot.clone());
+        self.add_bot_with_listener(
+            key,
+            bot,
+            listener,
+            LoggingErrorHandler::with_custom_text("An error from the update listener"),
+        )
+        .await;
+    }
+
+    /// Starts dispatching updates of `bot`, received via `update_listener`.
+    ///
+    /// If there's already a bot with `key`, it's gracefully shut down and the
+    /// new one is started only after the old one has stopped, so that they
+    /// don't receive updates of the same token at once (which Telegram
+    /// rejects with `409 Conflict` for polling).
+    ///
+    /// # Panics
+    /// If it's called outside of the Tokio runtime.
+    pub async fn add_bot_with_listener<UListener, ListenerE, Eh>(
+        &self,
+        key: K,
+        bot: Bot,
+        update_listener: UListener,
+        update_listener_error_handler: Arc<Eh>,
+    ) where
+        UListener: UpdateListener<ListenerE> + Send + 'static,
+        ListenerE: Debug + Send + 'static,
+        Eh: ErrorHandler<ListenerE> + Send + Sync + 'static,
+    {
+        let mut dispatcher = (self.factory)(bot).dependency(BotKey(key.clone()));
+        let shutdown = dispatcher.shutdown_token();
+
+        // Another call with the same key may add a bot while the old one is
+        // stopping, so the key is checked again under the lock.
+        let mut bots = loop {
+            let old = {
+                let mut bots = self.bots.lock().unwrap();
+                match bots.remove(&key) {
+                    Some(old) => old,
+                    None => break bots,
+                }
+            };
+            old.stop().await;
+        };
+
+        let handle = tokio::spawn(async move {
+            dispatcher.dispatch_with_listener(update_listener, update_listener_error_handler).await;
+        });
+        bots.insert(key, RunningBot { shutdown, handle });
+    }
+
+    /// Gracefully shuts down the bot with `key` and waits until it has
+    /// stopped.
+    ///
+    /// Returns `false` if there's no such bot.
+    pub async fn remove_bot(&self, key: &K) -> bool {
+        let bot = self.bots.lock().unwrap().remove(key);
+
+        match bot {
+            Some(bot) => {
+                bot.stop().await;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the keys of the running bots.
+    #[must_use]
+    pub fn keys(&self) -> Vec<K> {
+        self.bots.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Gracefully shuts down all the bots and waits until they have stopped.
+    pub async fn shutdown(&self) {
+        let bots: Vec<_> = self.bots.lock().unwrap().drain().map(|(_, bot)| bot).collect();
+        future::join_all(bots.into_iter().map(RunningBot::stop)).await;
+    }
+}
+
+impl RunningBot {
+    async fn stop(self) {
+        self.shutdown.stop();
+
+        if let Err(error) = self.handle.await {
+            log::error!("A dispatcher of a bot has failed: {}", error);
+        }
+    }
+}
+
+impl<K> Debug for MultiDispatcher<K>
+where
+    K: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bots = self.bots.lock().unwrap();
+        f.debug_struct("MultiDispatcher").field("bots", &bots.keys().collect::<Vec<_>>()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dispatching::{update_listeners, DispatcherHandlerRx},
+        error_handlers::IgnoringErrorHandlerSafe,
+        requests::Request,
+        testing::{MockMessage, MockServer},
+        types::{Message, Update, UpdateKind},
+    };
+    use futures::{stream, StreamExt};
+    use std::{
+        convert::Infallible,
+        sync::atomic::{AtomicBool, Ordering},
+    };
+    use tokio::time::{delay_for, Duration};
+
+    #[tokio::test]
+    async fn bots_are_added_and_removed() {
+        let first = MockServer::start();
+        let second = MockServer::start();
+
+        let dispatcher = MultiDispatcher::new(|bot| {
+            Dispatcher::new(bot).messages_handler(|rx: DispatcherHandlerRx<Message>| {
+                rx.for_each(|cx| async move {
+                    let BotKey(key): BotKey<&'static str> = cx.dependency();
+                    cx.bot.send_message(cx.update.chat.id, key).send().await.unwrap();
+                })
+            })
+        });
+
+        for &(key, server) in &[("first", &first), ("second", &second)] {
+            let update = Update::new(1, UpdateKind::Message(MockMessage::new("hi").build()));
+            // The listener never finishes on its own.
+            let updates = stream::iter(vec![Ok::<_, Infallible>(update)]).chain(stream::pending());
+
+            dispatcher
+                .add_bot_with_listener(
+                    key,
+                    server.bot(),
+                    update_listeners::from_stream(updates),
+                    IgnoringErrorHandlerSafe::new(),
+                )
+                .await;
+        }
+
+        // Wait until both the bots have handled their updates.
+        while first.requests().is_empty() || second.requests().is_empty() {
+            delay_for(Duration::from_millis(10)).await;
+        }
+
+        let mut keys = dispatcher.keys();
+        keys.sort();
+        assert_eq!(keys, ["first", "second"]);
+
+        assert!(dispatcher.remove_bot(&"first").await);
+        assert!(!dispatcher.remove_bot(&"first").await);
+        assert_eq!(dispatcher.keys(), ["second"]);
+        assert_eq!(first.requests()[0].params["text"], "first");
+
+        dispatcher.shutdown().await;
+        assert!(dispatcher.keys().is_empty());
+        assert_eq!(second.requests()[0].params["text"], "second");
+    }
+
+    #[tokio::test]
+    async fn readded_bots_are_stopped_first() {
+        struct SetOnDrop(Arc<AtomicBool>);
+
+        impl Drop for SetOnDrop {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let server = MockServer::start();
+        let dispatcher = Arc::new(MultiDispatcher::new(Dispatcher::new));
+        let old_stopped = Arc::new(AtomicBool::new(false));
+        let stopped_before_start = Arc::new(AtomicBool::new(false));
+
+        let guard = SetOnDrop(Arc::clone(&old_stopped));
+        let old = stream::pending::<Result<Update, Infallible>>().inspect(move |_| {
+            let _ = &guard;
+        });
+        dispatcher
+            .add_bot_with_listener(
+                "bot",
+                server.bot(),
+                update_listeners::from_stream(old),
+                IgnoringErrorHandlerSafe::new(),
+            )
+            .await;
+        assert!(!old_stopped.load(Ordering::SeqCst));
+
+        let new = stream::once({
+            let old_stopped = Arc::clone(&old_stopped);
+            let stopped_before_start = Arc::clone(&stopped_before_start);
+            async move {
+                stopped_before_start.store(old_stopped.load(Ordering::SeqCst), Ordering::SeqCst)
+            }
+        })
+        .filter_map(|()| async { None::<Result<Update, Infallible>> })
+        .chain(stream::pending());
+
+        // The future is `Send`, so a bot can be re-added from a spawned task.
+        tokio::spawn({
+            let dispatcher = Arc::clone(&dispatcher);
+            let bot = server.bot();
+            async move {
+                dispatcher
+                    .add_bot_with_listener(
+                        "bot",
+                        bot,
+                        update_listeners::from_stream(new),
+                        IgnoringErrorHandlerSafe::new(),
+                    )
+                    .await
+            }
+        })
+        .await
+        .unwrap();
+
+        delay_for(Duration::from_millis(50)).await;
+        assert!(stopped_before_start.load(Ordering::SeqCst));
+        assert_eq!(dispatcher.keys(), ["bot"]);
+        dispatcher.shutdown().await;
+    }
+}
@@ -0,0 +1,89 @@
+use std::{any::Any, future::Future, panic::AssertUnwindSafe, sync::Arc};
+
+use futures::FutureExt;
+use thiserror::Error;
+
+use crate::{
+    dispatching::{Completion, Dependencies},
+    error_handlers::{ErrorHandler, LoggingErrorHandler},
+};
+
+/// A panic of a handler.
+///
+/// Panics are caught and passed to a handler set via
+/// [`Dispatcher::panic_handler`].
+///
+/// [`Dispatcher::panic_handler`]: crate::dispatching::Dispatcher::panic_handler
+#[derive(Debug, Clone, Error)]
+#[error("A handler has panicked: {message}")]
+pub struct HandlerPanic {
+    /// A message of the panic.
+    pub message: String,
+}
+
+impl HandlerPanic {
+    fn from_payload(payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast_ref::<&str>() {
+                Some(message) => (*message).to_owned(),
+                None => "Box<Any>".to_owned(),
+            },
+        };
+
+        Self { message }
+    }
+}
+
+/// A handler of panics, stored in [`Dependencies`] of [`Dispatcher`].
+///
+/// [`Dependencies`]: crate::dispatching::Dependencies
+/// [`Dispatcher`]: crate::dispatching::Dispatcher
+#[derive(Clone)]
+pub(crate) struct PanicHandler(pub(crate) Arc<dyn ErrorHandler<HandlerPanic> + Send + Sync>);
+
+impl Default for PanicHandler {
+    fn default() -> Self {
+        Self(LoggingErrorHandler::with_custom_text("A handler has panicked"))
+    }
+}
+
+/// Runs `fut`, passing its panic (if any) to a [`PanicHandler`] from
+/// `dependencies` and marking an update with `completion` as failed.
+///
+/// Returns `None` if `fut` has panicked.
+pub(crate) async fn catch_panic<F>(
+    dependencies: &Dependencies,
+    completion: &Completion,
+    fut: F,
+) -> Option<F::Output>
+where
+    F: Future,
+{
+    report_panic(dependencies, completion, fut).await.ok()
+}
+
+/// The same as [`catch_panic`], but also returns the panic.
+pub(crate) async fn report_panic<F>(
+    dependencies: &Dependencies,
+    completion: &Completion,
+    fut: F,
+) -> Result<F::Output, HandlerPanic>
+where
+    F: Future,
+{
+    #[cfg(feature = "tracing-spans")]
+    let fut = crate::spans::instrument(dependencies, fut);
+
+    match AssertUnwindSafe(fut).catch_unwind().await {
+        Ok(output) => Ok(output),
+        Err(payload) => {
+            completion.fail();
+
+            let panic = HandlerPanic::from_payload(payload);
+            let PanicHandler(handler) = dependencies.get().unwrap_or_default();
+            handler.handle_error(panic.clone()).await;
+            Err(panic)
+        }
+    }
+}
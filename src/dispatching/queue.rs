@@ -0,0 +1,436 @@
+//! Queues of updates between [`Dispatcher`] and its handlers.
+//!
+//! By default, queues are unbounded, so a slow handler makes a queue grow
+//! without limit under a burst of updates. A bounded queue (see
+//! [`QueueOptions::bounded`]) either slows down the update listener or drops
+//! updates when it's full, depending on [`OverflowPolicy`].
+//!
+//! [`Dispatcher`]: crate::dispatching::Dispatcher
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use futures::{future, Stream};
+pub use tokio::sync::mpsc::error::SendError;
+
+/// What to do when a bounded queue is full.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum OverflowPolicy {
+    /// Wait until a handler takes an update from the queue.
+    ///
+    /// Since [`Dispatcher`] pushes updates sequentially, it stops receiving
+    /// updates from the update listener until then.
+    ///
+    /// [`Dispatcher`]: crate::dispatching::Dispatcher
+    Wait,
+
+    /// Drop the oldest update in the queue and push the new one.
+    DropOldest,
+
+    /// Drop the new update.
+    DropNewest,
+}
+
+/// Options of a queue.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct QueueOptions {
+    capacity: Option<usize>,
+    overflow: OverflowPolicy,
+}
+
+impl QueueOptions {
+    /// Options of an unbounded queue.
+    #[must_use]
+    pub fn unbounded() -> Self {
+        Self { capacity: None, overflow: OverflowPolicy::Wait }
+    }
+
+    /// Options of a queue that holds at most `capacity` updates.
+    ///
+    /// By default, [`OverflowPolicy::Wait`] is used.
+    ///
+    /// # Panics
+    /// If `capacity` is 0.
+    ///
+    /// [`OverflowPolicy::Wait`]: crate::dispatching::queue::OverflowPolicy::Wait
+    #[must_use]
+    pub fn bounded(capacity: usize) -> Self {
+        assert!(capacity > 0, "The capacity of a queue must be positive");
+        Self { capacity: Some(capacity), overflow: OverflowPolicy::Wait }
+    }
+
+    /// Sets what to do when the queue is full.
+    ///
+    /// It makes no difference for unbounded queues.
+    #[must_use]
+    pub fn overflow(mut self, overflow: OverflowPolicy) -> Self {
+        self.overflow = overflow;
+        self
+    }
+}
+
+impl Default for QueueOptions {
+    fn default() -> Self {
+        Self::unbounded()
+    }
+}
+
+/// Creates a queue with the given options.
+///
+/// The returned [`Receiver`] is a stream, which finishes after all the
+/// [`Sender`]s have been dropped and all the queued values have been taken.
+///
+/// [`Receiver`]: crate::dispatching::queue::Receiver
+/// [`Sender`]: crate::dispatching::queue::Sender
+#[must_use]
+pub fn channel<T>(options: QueueOptions) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        options,
+        state: Mutex::new(State {
+            values: VecDeque::new(),
+            senders: 1,
+            receivers: 1,
+            version: 0,
+            receiver_waker: None,
+            sender_wakers: Vec::new(),
+        }),
+    });
+
+    (Sender { shared: Arc::clone(&shared) }, Receiver { shared })
+}
+
+struct Shared<T> {
+    options: QueueOptions,
+    state: Mutex<State<T>>,
+}
+
+struct State<T> {
+    values: VecDeque<T>,
+    senders: usize,
+
+    /// The number of [`Receiver`]s and [`Reopener`]s. The queue is closed
+    /// after all of them have been dropped.
+    receivers: usize,
+
+    /// The number of values pushed and taken so far.
+    version: u64,
+    receiver_waker: Option<Waker>,
+    sender_wakers: Vec<Waker>,
+}
+
+impl<T> State<T> {
+    fn wake_receiver(&mut self) {
+        if let Some(waker) = self.receiver_waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn wake_senders(&mut self) {
+        self.sender_wakers.drain(..).for_each(Waker::wake);
+    }
+}
+
+/// The TX part of a queue.
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+    /// Pushes `value` to the queue.
+    ///
+    /// If the queue is full, it acts according to [`OverflowPolicy`]. Fails
+    /// if the [`Receiver`] has been dropped.
+    ///
+    /// [`OverflowPolicy`]: crate::dispatching::queue::OverflowPolicy
+    /// [`Receiver`]: crate::dispatching::queue::Receiver
+    pub async fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let mut value = Some(value);
+        future::poll_fn(|cx| self.poll_send(cx, &mut value)).await
+    }
+
+    /// Returns the number of values in the queue.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.shared.state.lock().unwrap().values.len()
+    }
+
+    /// Returns `true` if the queue is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn poll_send(
+        &self,
+        cx: &mut Context<'_>,
+        value: &mut Option<T>,
+    ) -> Poll<Result<(), SendError<T>>> {
+        let mut state = self.shared.state.lock().unwrap();
+        let new_value = value.take().expect("A value is already sent");
+
+        if state.receivers == 0 {
+            return Poll::Ready(Err(SendError(new_value)));
+        }
+
+        if let Some(capacity) = self.shared.options.capacity {
+            if state.values.len() >= capacity {
+                match self.shared.options.overflow {
+                    OverflowPolicy::Wait => {
+                        *value = Some(new_value);
+                        state.sender_wakers.push(cx.waker().clone());
+                        return Poll::Pending;
+                    }
+                    OverflowPolicy::DropOldest => {
+                        log::warn!("A queue is full, dropping the oldest update");
+                        state.values.pop_front();
+                    }
+                    OverflowPolicy::DropNewest => {
+                        log::warn!("A queue is full, dropping a new update");
+                        return Poll::Ready(Ok(()));
+                    }
+                }
+            }
+        }
+
+        state.values.push_back(new_value);
+        state.version += 1;
+        state.wake_receiver();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().unwrap().senders += 1;
+        Self { shared: Arc::clone(&self.shared) }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.senders -= 1;
+        if state.senders == 0 {
+            state.wake_receiver();
+        }
+    }
+}
+
+/// The RX part of a queue.
+///
+/// It's a [`Stream`] of values.
+///
+/// [`Stream`]: futures::Stream
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Receiver<T> {
+    /// Takes the next value from the queue.
+    ///
+    /// Returns `None` after all the [`Sender`]s have been dropped and all the
+    /// values have been taken.
+    ///
+    /// [`Sender`]: crate::dispatching::queue::Sender
+    pub async fn recv(&mut self) -> Option<T> {
+        future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+
+    /// Returns a [`Reopener`], which keeps the queue open after this receiver
+    /// has been dropped.
+    #[must_use]
+    pub(crate) fn reopener(&self) -> Reopener<T> {
+        self.shared.state.lock().unwrap().receivers += 1;
+        Reopener { shared: Arc::clone(&self.shared) }
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut state = self.shared.state.lock().unwrap();
+
+        match state.values.pop_front() {
+            Some(value) => {
+                state.version += 1;
+                state.wake_senders();
+                Poll::Ready(Some(value))
+            }
+            None if state.senders == 0 => Poll::Ready(None),
+            None => {
+                state.receiver_waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        release(&self.shared);
+    }
+}
+
+/// Creates new [`Receiver`]s of a queue after the previous one has been
+/// dropped, e.g. because its handler has panicked.
+///
+/// While a reopener is alive, the queue stays open without a receiver, so
+/// values pushed in the meantime are kept for the next receiver.
+pub(crate) struct Reopener<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Reopener<T> {
+    /// Returns the number of values pushed to and taken from the queue so
+    /// far.
+    #[must_use]
+    pub(crate) fn version(&self) -> u64 {
+        self.shared.state.lock().unwrap().version
+    }
+
+    /// Waits until a value has been pushed or taken since `version` and
+    /// returns a new receiver.
+    ///
+    /// Returns `None` if all the [`Sender`]s have been dropped before that.
+    pub(crate) async fn reopen(&self, version: u64) -> Option<Receiver<T>> {
+        future::poll_fn(|cx| {
+            let mut state = self.shared.state.lock().unwrap();
+
+            if state.version != version {
+                state.receivers += 1;
+                Poll::Ready(Some(Receiver { shared: Arc::clone(&self.shared) }))
+            } else if state.senders == 0 {
+                Poll::Ready(None)
+            } else {
+                state.receiver_waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+impl<T> Drop for Reopener<T> {
+    fn drop(&mut self) {
+        release(&self.shared);
+    }
+}
+
+/// Closes the queue after the last [`Receiver`] or [`Reopener`] has been
+/// dropped.
+fn release<T>(shared: &Shared<T>) {
+    let values = {
+        let mut state = shared.state.lock().unwrap();
+        state.receivers -= 1;
+        if state.receivers > 0 {
+            return;
+        }
+
+        state.wake_senders();
+        std::mem::take(&mut state.values)
+    };
+
+    drop(values);
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender").field("options", &self.shared.options).finish()
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver").field("options", &self.shared.options).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use tokio::time::{delay_for, timeout, Duration};
+
+    #[tokio::test]
+    async fn waits_for_free_space() {
+        let (tx, rx) = channel(QueueOptions::bounded(2));
+
+        tx.send(1).await.unwrap();
+        tx.send(2).await.unwrap();
+        assert_eq!(tx.len(), 2);
+        assert!(timeout(Duration::from_millis(50), tx.send(3)).await.is_err());
+
+        let sender = tokio::spawn(async move {
+            tx.send(3).await.unwrap();
+            tx.send(4).await.unwrap();
+        });
+
+        let received: Vec<_> = rx
+            .then(|value| async move {
+                delay_for(Duration::from_millis(10)).await;
+                value
+            })
+            .collect()
+            .await;
+        sender.await.unwrap();
+
+        assert_eq!(received, [1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn drops_updates_on_overflow() {
+        let (tx, mut rx) = channel(QueueOptions::bounded(2).overflow(OverflowPolicy::DropOldest));
+        for value in 1..=4 {
+            tx.send(value).await.unwrap();
+        }
+        drop(tx);
+        assert_eq!(rx.by_ref().collect::<Vec<_>>().await, [3, 4]);
+        assert_eq!(rx.recv().await, None);
+
+        let (tx, rx) = channel(QueueOptions::bounded(2).overflow(OverflowPolicy::DropNewest));
+        for value in 1..=4 {
+            tx.send(value).await.unwrap();
+        }
+        drop(tx);
+        assert_eq!(rx.collect::<Vec<_>>().await, [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn fails_after_receiver_is_dropped() {
+        let (tx, rx) = channel(QueueOptions::bounded(1));
+        tx.send(1).await.unwrap();
+
+        let blocked = tokio::spawn({
+            let tx = tx.clone();
+            async move { tx.send(2).await }
+        });
+        delay_for(Duration::from_millis(10)).await;
+        drop(rx);
+
+        assert_eq!(blocked.await.unwrap().unwrap_err().0, 2);
+        assert_eq!(tx.send(3).await.unwrap_err().0, 3);
+    }
+
+    #[tokio::test]
+    async fn reopener_keeps_values_for_the_next_receiver() {
+        let (tx, mut rx) = channel(QueueOptions::unbounded());
+        let reopener = rx.reopener();
+        tx.send(1).await.unwrap();
+        assert_eq!(rx.recv().await, Some(1));
+
+        let version = reopener.version();
+        drop(rx);
+        tx.send(2).await.unwrap();
+        drop(tx);
+
+        let rx = reopener.reopen(version).await.unwrap();
+        assert_eq!(rx.collect::<Vec<_>>().await, [2]);
+        assert!(reopener.reopen(reopener.version()).await.is_none());
+    }
+}
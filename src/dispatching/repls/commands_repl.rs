@@ -1,7 +1,7 @@
 use crate::{
     dispatching::{
-        update_listeners, update_listeners::UpdateListener, Dispatcher, DispatcherHandlerRx,
-        DispatcherHandlerRxExt, UpdateWithCx,
+        panics::catch_panic, update_listeners, update_listeners::UpdateListener, Dependencies,
+        Dispatcher, DispatcherHandlerRx, DispatcherHandlerRxExt, UpdateWithCx,
     },
     error_handlers::{LoggingErrorHandler, OnError},
     types::Message,
@@ -13,7 +13,8 @@ use std::{fmt::Debug, future::Future, sync::Arc};
 
 /// A [REPL] for commands.
 ///
-/// All errors from an update listener and handler will be logged.
+/// All errors from an update listener and handler will be logged. Ctrl-C
+/// [gracefully shuts down] the bot.
 ///
 /// # Caution
 /// **DO NOT** use this function together with [`Dispatcher`] and other REPLs,
@@ -22,6 +23,7 @@ use std::{fmt::Debug, future::Future, sync::Arc};
 ///
 /// [REPL]: https://en.wikipedia.org/wiki/Read-eval-print_loop
 /// [`Dispatcher`]: crate::dispatching::Dispatcher
+/// [gracefully shuts down]: crate::dispatching::Dispatcher#graceful-shutdown
 pub async fn commands_repl<Cmd, H, Fut, HandlerE>(bot: Bot, bot_name: &'static str, handler: H)
 where
     Cmd: BotCommand + Send + 'static,
@@ -41,9 +43,40 @@ where
     .await;
 }
 
+/// Like [`commands_repl`], but with [`Dependencies`] available in `handler`.
+///
+/// All errors from an update listener and handler will be logged. Ctrl-C
+/// [gracefully shuts down] the bot.
+///
+/// # Caution
+/// **DO NOT** use this function together with [`Dispatcher`] and other REPLs,
+/// because Telegram disallow multiple requests at the same time from the same
+/// bot.
+///
+/// [`Dispatcher`]: crate::dispatching::Dispatcher
+/// [gracefully shuts down]: crate::dispatching::Dispatcher#graceful-shutdown
+/// [`commands_repl`]: crate::dispatching::repls::commands_repl()
+/// [`Dependencies`]: crate::dispatching::Dependencies
+pub async fn commands_repl_with_dependencies<Cmd, H, Fut, HandlerE>(
+    bot: Bot,
+    bot_name: &'static str,
+    dependencies: Dependencies,
+    handler: H,
+) where
+    Cmd: BotCommand + Send + 'static,
+    H: Fn(UpdateWithCx<Message>, Cmd) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), HandlerE>> + Send + 'static,
+    Result<(), HandlerE>: OnError<HandlerE>,
+    HandlerE: Debug + Send,
+{
+    let listener = update_listeners::polling_default(bot.clone());
+    run(bot, bot_name, dependencies, handler, listener).await;
+}
+
 /// Like [`commands_repl`], but with a custom [`UpdateListener`].
 ///
-/// All errors from an update listener and handler will be logged.
+/// All errors from an update listener and handler will be logged. Ctrl-C
+/// [gracefully shuts down] the bot.
 ///
 /// # Caution
 /// **DO NOT** use this function together with [`Dispatcher`] and other REPLs,
@@ -51,6 +84,7 @@ where
 /// bot.
 ///
 /// [`Dispatcher`]: crate::dispatching::Dispatcher
+/// [gracefully shuts down]: crate::dispatching::Dispatcher#graceful-shutdown
 /// [`commands_repl`]: crate::dispatching::repls::commands_repl()
 /// [`UpdateListener`]: crate::dispatching::update_listeners::UpdateListener
 pub async fn commands_repl_with_listener<'a, Cmd, H, Fut, L, ListenerE, HandlerE>(
@@ -66,10 +100,29 @@ pub async fn commands_repl_with_listener<'a, Cmd, H, Fut, L, ListenerE, HandlerE
     ListenerE: Debug + Send + 'a,
     Result<(), HandlerE>: OnError<HandlerE>,
     HandlerE: Debug + Send,
+{
+    run(bot, bot_name, Dependencies::new(), handler, listener).await;
+}
+
+async fn run<'a, Cmd, H, Fut, L, ListenerE, HandlerE>(
+    bot: Bot,
+    bot_name: &'static str,
+    dependencies: Dependencies,
+    handler: H,
+    listener: L,
+) where
+    Cmd: BotCommand + Send + 'static,
+    H: Fn(UpdateWithCx<Message>, Cmd) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), HandlerE>> + Send + 'static,
+    L: UpdateListener<ListenerE> + Send + 'a,
+    ListenerE: Debug + Send + 'a,
+    Result<(), HandlerE>: OnError<HandlerE>,
+    HandlerE: Debug + Send,
 {
     let handler = Arc::new(handler);
 
     Dispatcher::new(bot)
+        .dependencies(dependencies)
         .messages_handler(move |rx: DispatcherHandlerRx<Message>| {
             rx.commands::<Cmd, &'static str>(bot_name).for_each_concurrent(
                 None,
@@ -77,11 +130,24 @@ pub async fn commands_repl_with_listener<'a, Cmd, H, Fut, L, ListenerE, HandlerE
                     let handler = Arc::clone(&handler);
 
                     async move {
-                        handler(cx, cmd).await.log_on_error().await;
+                        let (dependencies, completion) =
+                            (cx.dependencies.clone(), cx.completion.clone());
+                        let failed = catch_panic(&dependencies, &completion, async move {
+                            let result = handler(cx, cmd).await;
+                            let failed = result.is_err();
+                            result.log_on_error().await;
+                            failed
+                        })
+                        .await;
+
+                        if failed == Some(true) {
+                            completion.fail();
+                        }
                     }
                 },
             )
         })
+        .setup_ctrlc_handler()
         .dispatch_with_listener(
             listener,
             LoggingErrorHandler::with_custom_text("An error from the update listener"),
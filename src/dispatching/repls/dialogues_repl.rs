@@ -3,7 +3,7 @@ use crate::{
         dialogue::{DialogueDispatcher, DialogueStage, DialogueWithCx},
         update_listeners,
         update_listeners::UpdateListener,
-        Dispatcher, UpdateWithCx,
+        Dependencies, Dispatcher, UpdateWithCx,
     },
     error_handlers::LoggingErrorHandler,
     types::Message,
@@ -13,8 +13,8 @@ use std::{convert::Infallible, fmt::Debug, future::Future, sync::Arc};
 
 /// A [REPL] for dialogues.
 ///
-/// All errors from an update listener and handler will be logged. This function
-/// uses [`InMemStorage`].
+/// All errors from an update listener and handler will be logged. Ctrl-C
+/// [gracefully shuts down] the bot. This function uses [`InMemStorage`].
 ///
 /// # Caution
 /// **DO NOT** use this function together with [`Dispatcher`] and other REPLs,
@@ -23,6 +23,7 @@ use std::{convert::Infallible, fmt::Debug, future::Future, sync::Arc};
 ///
 /// [REPL]: https://en.wikipedia.org/wiki/Read-eval-print_loop
 /// [`Dispatcher`]: crate::dispatching::Dispatcher
+/// [gracefully shuts down]: crate::dispatching::Dispatcher#graceful-shutdown
 /// [`InMemStorage`]: crate::dispatching::dialogue::InMemStorage
 pub async fn dialogues_repl<'a, H, D, Fut>(bot: Bot, handler: H)
 where
@@ -35,10 +36,38 @@ where
     dialogues_repl_with_listener(bot, handler, update_listeners::polling_default(cloned_bot)).await;
 }
 
+/// Like [`dialogues_repl`], but with [`Dependencies`] available in `handler`.
+///
+/// All errors from an update listener and handler will be logged. Ctrl-C
+/// [gracefully shuts down] the bot. This function uses [`InMemStorage`].
+///
+/// # Caution
+/// **DO NOT** use this function together with [`Dispatcher`] and other REPLs,
+/// because Telegram disallow multiple requests at the same time from the same
+/// bot.
+///
+/// [`Dispatcher`]: crate::dispatching::Dispatcher
+/// [gracefully shuts down]: crate::dispatching::Dispatcher#graceful-shutdown
+/// [`dialogues_repl`]: crate::dispatching::repls::dialogues_repl()
+/// [`Dependencies`]: crate::dispatching::Dependencies
+/// [`InMemStorage`]: crate::dispatching::dialogue::InMemStorage
+pub async fn dialogues_repl_with_dependencies<H, D, Fut>(
+    bot: Bot,
+    dependencies: Dependencies,
+    handler: H,
+) where
+    H: Fn(UpdateWithCx<Message>, D) -> Fut + Send + Sync + 'static,
+    D: Default + Send + 'static,
+    Fut: Future<Output = DialogueStage<D>> + Send + 'static,
+{
+    let listener = update_listeners::polling_default(bot.clone());
+    run(bot, dependencies, handler, listener).await;
+}
+
 /// Like [`dialogues_repl`], but with a custom [`UpdateListener`].
 ///
-/// All errors from an update listener and handler will be logged. This function
-/// uses [`InMemStorage`].
+/// All errors from an update listener and handler will be logged. Ctrl-C
+/// [gracefully shuts down] the bot. This function uses [`InMemStorage`].
 ///
 /// # Caution
 /// **DO NOT** use this function together with [`Dispatcher`] and other REPLs,
@@ -46,6 +75,7 @@ where
 /// bot.
 ///
 /// [`Dispatcher`]: crate::dispatching::Dispatcher
+/// [gracefully shuts down]: crate::dispatching::Dispatcher#graceful-shutdown
 /// [`dialogues_repl`]: crate::dispatching::repls::dialogues_repl()
 /// [`UpdateListener`]: crate::dispatching::update_listeners::UpdateListener
 /// [`InMemStorage`]: crate::dispatching::dialogue::InMemStorage
@@ -59,10 +89,26 @@ pub async fn dialogues_repl_with_listener<'a, H, D, Fut, L, ListenerE>(
     Fut: Future<Output = DialogueStage<D>> + Send + 'static,
     L: UpdateListener<ListenerE> + Send + 'a,
     ListenerE: Debug + Send + 'a,
+{
+    run(bot, Dependencies::new(), handler, listener).await;
+}
+
+async fn run<'a, H, D, Fut, L, ListenerE>(
+    bot: Bot,
+    dependencies: Dependencies,
+    handler: H,
+    listener: L,
+) where
+    H: Fn(UpdateWithCx<Message>, D) -> Fut + Send + Sync + 'static,
+    D: Default + Send + 'static,
+    Fut: Future<Output = DialogueStage<D>> + Send + 'static,
+    L: UpdateListener<ListenerE> + Send + 'a,
+    ListenerE: Debug + Send + 'a,
 {
     let handler = Arc::new(handler);
 
     Dispatcher::new(bot)
+        .dependencies(dependencies)
         .messages_handler(DialogueDispatcher::new(
             move |DialogueWithCx { cx, dialogue }: DialogueWithCx<Message, D, Infallible>| {
                 let handler = Arc::clone(&handler);
@@ -73,6 +119,7 @@ pub async fn dialogues_repl_with_listener<'a, H, D, Fut, L, ListenerE>(
                 }
             },
         ))
+        .setup_ctrlc_handler()
         .dispatch_with_listener(
             listener,
             LoggingErrorHandler::with_custom_text("An error from the update listener"),
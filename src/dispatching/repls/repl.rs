@@ -1,7 +1,7 @@
 use crate::{
     dispatching::{
-        update_listeners, update_listeners::UpdateListener, Dispatcher, DispatcherHandlerRx,
-        UpdateWithCx,
+        panics::catch_panic, update_listeners, update_listeners::UpdateListener, Dependencies,
+        Dispatcher, DispatcherHandlerRx, UpdateWithCx,
     },
     error_handlers::{LoggingErrorHandler, OnError},
     types::Message,
@@ -12,7 +12,8 @@ use std::{fmt::Debug, future::Future, sync::Arc};
 
 /// A [REPL] for messages.
 ///
-/// All errors from an update listener and a handler will be logged.
+/// All errors from an update listener and a handler will be logged. Ctrl-C
+/// [gracefully shuts down] the bot.
 ///
 /// # Caution
 /// **DO NOT** use this function together with [`Dispatcher`] and other REPLs,
@@ -21,6 +22,7 @@ use std::{fmt::Debug, future::Future, sync::Arc};
 ///
 /// [REPL]: https://en.wikipedia.org/wiki/Read-eval-print_loop
 /// [`Dispatcher`]: crate::dispatching::Dispatcher
+/// [gracefully shuts down]: crate::dispatching::Dispatcher#graceful-shutdown
 pub async fn repl<H, Fut, E>(bot: Bot, handler: H)
 where
     H: Fn(UpdateWithCx<Message>) -> Fut + Send + Sync + 'static,
@@ -32,9 +34,35 @@ where
     repl_with_listener(bot, handler, update_listeners::polling_default(cloned_bot)).await;
 }
 
+/// Like [`repl`], but with [`Dependencies`] available in `handler`.
+///
+/// All errors from an update listener and a handler will be logged. Ctrl-C
+/// [gracefully shuts down] the bot.
+///
+/// # Caution
+/// **DO NOT** use this function together with [`Dispatcher`] and other REPLs,
+/// because Telegram disallow multiple requests at the same time from the same
+/// bot.
+///
+/// [`Dispatcher`]: crate::dispatching::Dispatcher
+/// [gracefully shuts down]: crate::dispatching::Dispatcher#graceful-shutdown
+/// [`repl`]: crate::dispatching::repls::repl()
+/// [`Dependencies`]: crate::dispatching::Dependencies
+pub async fn repl_with_dependencies<H, Fut, E>(bot: Bot, dependencies: Dependencies, handler: H)
+where
+    H: Fn(UpdateWithCx<Message>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+    Result<(), E>: OnError<E>,
+    E: Debug + Send,
+{
+    let listener = update_listeners::polling_default(bot.clone());
+    run(bot, dependencies, handler, listener).await;
+}
+
 /// Like [`repl`], but with a custom [`UpdateListener`].
 ///
-/// All errors from an update listener and handler will be logged.
+/// All errors from an update listener and handler will be logged. Ctrl-C
+/// [gracefully shuts down] the bot.
 ///
 /// # Caution
 /// **DO NOT** use this function together with [`Dispatcher`] and other REPLs,
@@ -42,6 +70,7 @@ where
 /// bot.
 ///
 /// [`Dispatcher`]: crate::dispatching::Dispatcher
+/// [gracefully shuts down]: crate::dispatching::Dispatcher#graceful-shutdown
 /// [`repl`]: crate::dispatching::repls::repl()
 /// [`UpdateListener`]: crate::dispatching::update_listeners::UpdateListener
 pub async fn repl_with_listener<'a, H, Fut, E, L, ListenerE>(bot: Bot, handler: H, listener: L)
@@ -52,19 +81,49 @@ where
     ListenerE: Debug,
     Result<(), E>: OnError<E>,
     E: Debug + Send,
+{
+    run(bot, Dependencies::new(), handler, listener).await;
+}
+
+async fn run<'a, H, Fut, E, L, ListenerE>(
+    bot: Bot,
+    dependencies: Dependencies,
+    handler: H,
+    listener: L,
+) where
+    H: Fn(UpdateWithCx<Message>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+    L: UpdateListener<ListenerE> + Send + 'a,
+    ListenerE: Debug,
+    Result<(), E>: OnError<E>,
+    E: Debug + Send,
 {
     let handler = Arc::new(handler);
 
     Dispatcher::new(bot)
+        .dependencies(dependencies)
         .messages_handler(|rx: DispatcherHandlerRx<Message>| {
             rx.for_each_concurrent(None, move |message| {
                 let handler = Arc::clone(&handler);
 
                 async move {
-                    handler(message).await.log_on_error().await;
+                    let (dependencies, completion) =
+                        (message.dependencies.clone(), message.completion.clone());
+                    let failed = catch_panic(&dependencies, &completion, async move {
+                        let result = handler(message).await;
+                        let failed = result.is_err();
+                        result.log_on_error().await;
+                        failed
+                    })
+                    .await;
+
+                    if failed == Some(true) {
+                        completion.fail();
+                    }
                 }
             })
         })
+        .setup_ctrlc_handler()
         .dispatch_with_listener(
             listener,
             LoggingErrorHandler::with_custom_text("An error from the update listener"),
@@ -0,0 +1,460 @@
+use std::{fmt::Debug, future::Future, sync::Arc};
+
+use futures::{future::BoxFuture, StreamExt};
+
+use crate::{
+    dispatching::{
+        panics::catch_panic, Completion, DispatcherHandler, DispatcherHandlerRx, FromDependencies,
+        UpdateWithCx,
+    },
+    error_handlers::OnError,
+    types::Message,
+    utils::command::BotCommand,
+};
+
+/// A node of a [`Router`] tree.
+///
+/// [`Router`]: crate::dispatching::Router
+pub trait Route<Upd>: Send + Sync + 'static {
+    /// Tries to handle `cx`.
+    ///
+    /// Returns a future, which handles `cx`, if this route matches `cx`, or
+    /// gives `cx` back otherwise.
+    fn route(&self, cx: UpdateWithCx<Upd>) -> Result<BoxFuture<'static, ()>, UpdateWithCx<Upd>>;
+}
+
+type Filter<Upd> = Arc<dyn Fn(&Upd) -> bool + Send + Sync>;
+
+/// A declarative tree of handlers.
+///
+/// A router matches an update if all its filters (see [`Router::filter`])
+/// return `true` and one of its branches matches the update. Branches are
+/// tried in the order they were added and the first matching one handles the
+/// update. A branch is either a nested router or an endpoint (see
+/// [`Router::endpoint`], [`Router::command`]), so an endpoint added last acts
+/// as a fallback.
+///
+/// A router is a [`DispatcherHandler`], which handles updates concurrently.
+/// Updates that don't match any branch are ignored. Errors returned from
+/// endpoints are logged.
+///
+/// ## Example
+/// ```
+/// use teloxide::{dispatching::Router, prelude::*, utils::command::BotCommand};
+///
+/// #[derive(BotCommand)]
+/// #[command(rename = "lowercase")]
+/// enum Command {
+///     Help,
+///     Start,
+/// }
+///
+/// async fn command(cx: UpdateWithCx<Message>, command: Command) -> ResponseResult<()> {
+///     match command {
+///         Command::Help => cx.answer_str(Command::descriptions()).await?,
+///         Command::Start => cx.answer_str("Hello!").await?,
+///     };
+///     Ok(())
+/// }
+///
+/// async fn echo(cx: UpdateWithCx<Message>) -> ResponseResult<()> {
+///     cx.answer_str(cx.update.text().unwrap()).await?;
+///     Ok(())
+/// }
+///
+/// async fn fallback(cx: UpdateWithCx<Message>) -> ResponseResult<()> {
+///     cx.answer_str("Send me a text in a private chat.").await?;
+///     Ok(())
+/// }
+///
+/// async fn run(bot: Bot) {
+///     Dispatcher::new(bot)
+///         .messages_handler(
+///             Router::new()
+///                 .command("my_bot", command)
+///                 .branch(
+///                     Router::new()
+///                         .filter(|message: &Message| message.chat.is_private())
+///                         .filter(|message: &Message| message.text().is_some())
+///                         .endpoint(echo),
+///                 )
+///                 .endpoint(fallback),
+///         )
+///         .dispatch()
+///         .await;
+/// }
+/// ```
+///
+/// [`DispatcherHandler`]: crate::dispatching::DispatcherHandler
+pub struct Router<Upd> {
+    filters: Vec<Filter<Upd>>,
+    routes: Vec<Arc<dyn Route<Upd>>>,
+}
+
+impl<Upd> Router<Upd>
+where
+    Upd: Send + 'static,
+{
+    #[must_use]
+    pub fn new() -> Self {
+        Self { filters: Vec::new(), routes: Vec::new() }
+    }
+
+    /// Adds a predicate, which an update must satisfy to be matched by this
+    /// router.
+    #[must_use]
+    pub fn filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&Upd) -> bool + Send + Sync + 'static,
+    {
+        self.filters.push(Arc::new(filter));
+        self
+    }
+
+    /// Adds a branch, e.g. a nested router.
+    #[must_use]
+    pub fn branch<R>(mut self, route: R) -> Self
+    where
+        R: Route<Upd>,
+    {
+        self.routes.push(Arc::new(route));
+        self
+    }
+
+    /// Adds a branch, which handles all the updates reaching it.
+    #[must_use]
+    pub fn endpoint<H, Fut, E>(self, handler: H) -> Self
+    where
+        H: Fn(UpdateWithCx<Upd>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Debug + Send,
+    {
+        self.branch(Endpoint(Box::new(move |cx| {
+            let completion = cx.completion.clone();
+            let fut = handler(cx);
+            Box::pin(async move { report(&completion, fut.await).await })
+        })))
+    }
+
+    /// Adds a branch, which handles all the updates reaching it, passing
+    /// dependencies of types `D` to `handler`.
+    ///
+    /// `D` is a tuple, e.g. `(Arc<Db>, Config)`, see [`FromDependencies`]. If
+    /// one of the dependencies hasn't been registered, `handler` isn't called
+    /// and [`MissingDependency`] is reported as its error.
+    ///
+    /// ## Example
+    /// ```
+    /// use std::sync::{
+    ///     atomic::{AtomicU64, Ordering},
+    ///     Arc,
+    /// };
+    /// use teloxide::{dispatching::Router, prelude::*};
+    ///
+    /// async fn count(
+    ///     cx: UpdateWithCx<Message>,
+    ///     (total,): (Arc<AtomicU64>,),
+    /// ) -> ResponseResult<()> {
+    ///     let total = total.fetch_add(1, Ordering::Relaxed) + 1;
+    ///     cx.answer_str(format!("I've received {} messages", total)).await?;
+    ///     Ok(())
+    /// }
+    ///
+    /// # async fn run() {
+    /// Dispatcher::new(Bot::from_env())
+    ///     .dependency(Arc::new(AtomicU64::new(0)))
+    ///     .messages_handler(Router::new().endpoint_with(count))
+    ///     .dispatch()
+    ///     .await;
+    /// # }
+    /// ```
+    ///
+    /// [`FromDependencies`]: crate::dispatching::FromDependencies
+    /// [`MissingDependency`]: crate::dispatching::MissingDependency
+    #[must_use]
+    pub fn endpoint_with<D, H, Fut, E>(self, handler: H) -> Self
+    where
+        D: FromDependencies,
+        H: Fn(UpdateWithCx<Upd>, D) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Debug + Send,
+    {
+        self.branch(Endpoint(Box::new(move |cx| {
+            let completion = cx.completion.clone();
+            match D::from_dependencies(&cx.dependencies) {
+                Ok(extracted) => {
+                    let fut = handler(cx, extracted);
+                    Box::pin(async move { report(&completion, fut.await).await })
+                }
+                Err(error) => Box::pin(async move { report(&completion, Err(error)).await }),
+            }
+        })))
+    }
+}
+
+impl Router<Message> {
+    /// Adds a branch, which handles text messages with commands of type `C`.
+    ///
+    /// Messages that cannot be parsed into `C` are passed to the next
+    /// branches.
+    #[must_use]
+    pub fn command<C, N, H, Fut, E>(self, bot_name: N, handler: H) -> Self
+    where
+        C: BotCommand + 'static,
+        N: Into<String>,
+        H: Fn(UpdateWithCx<Message>, C) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Debug + Send,
+    {
+        self.branch(Command {
+            bot_name: bot_name.into(),
+            handler: Box::new(move |cx, command| {
+                let completion = cx.completion.clone();
+                let fut = handler(cx, command);
+                Box::pin(async move { report(&completion, fut.await).await })
+            }),
+        })
+    }
+}
+
+impl<Upd> Clone for Router<Upd> {
+    fn clone(&self) -> Self {
+        Self { filters: self.filters.clone(), routes: self.routes.clone() }
+    }
+}
+
+impl<Upd> Default for Router<Upd>
+where
+    Upd: Send + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Upd> Route<Upd> for Router<Upd>
+where
+    Upd: Send + 'static,
+{
+    fn route(
+        &self,
+        mut cx: UpdateWithCx<Upd>,
+    ) -> Result<BoxFuture<'static, ()>, UpdateWithCx<Upd>> {
+        if !self.filters.iter().all(|filter| filter(&cx.update)) {
+            return Err(cx);
+        }
+
+        for route in &self.routes {
+            cx = match route.route(cx) {
+                Ok(fut) => return Ok(fut),
+                Err(cx) => cx,
+            };
+        }
+
+        Err(cx)
+    }
+}
+
+impl<Upd> DispatcherHandler<Upd> for Router<Upd>
+where
+    Upd: Send + 'static,
+{
+    fn handle(self, updates: DispatcherHandlerRx<Upd>) -> BoxFuture<'static, ()>
+    where
+        UpdateWithCx<Upd>: Send + 'static,
+    {
+        let router = Arc::new(self);
+
+        Box::pin(updates.for_each_concurrent(None, move |cx| {
+            let router = Arc::clone(&router);
+
+            async move {
+                let (dependencies, completion) = (cx.dependencies.clone(), cx.completion.clone());
+                catch_panic(&dependencies, &completion, async move {
+                    match router.route(cx) {
+                        Ok(fut) => fut.await,
+                        Err(_) => log::trace!("An update is not matched by any route"),
+                    }
+                })
+                .await;
+            }
+        }))
+    }
+}
+
+/// Logs an error of a handler and marks the update as failed.
+///
+/// Holding `completion` until then keeps the update from being recorded as
+/// handled before it's marked as failed.
+pub(super) async fn report<E>(completion: &Completion, result: Result<(), E>)
+where
+    E: Debug + Send,
+{
+    if result.is_err() {
+        completion.fail();
+    }
+    result.log_on_error().await;
+}
+
+type Handler<Args> = Box<dyn Fn(Args) -> BoxFuture<'static, ()> + Send + Sync>;
+
+struct Endpoint<Upd>(Handler<UpdateWithCx<Upd>>);
+
+impl<Upd> Route<Upd> for Endpoint<Upd>
+where
+    Upd: Send + 'static,
+{
+    fn route(&self, cx: UpdateWithCx<Upd>) -> Result<BoxFuture<'static, ()>, UpdateWithCx<Upd>> {
+        Ok((self.0)(cx))
+    }
+}
+
+struct Command<C> {
+    bot_name: String,
+    handler: Box<dyn Fn(UpdateWithCx<Message>, C) -> BoxFuture<'static, ()> + Send + Sync>,
+}
+
+impl<C> Route<Message> for Command<C>
+where
+    C: BotCommand + 'static,
+{
+    fn route(
+        &self,
+        cx: UpdateWithCx<Message>,
+    ) -> Result<BoxFuture<'static, ()>, UpdateWithCx<Message>> {
+        let command = cx.update.text().and_then(|text| C::parse(text, &self.bot_name).ok());
+
+        match command {
+            Some(command) => Ok((self.handler)(cx, command)),
+            None => Err(cx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dispatching::Dispatcher,
+        error_handlers::IgnoringErrorHandlerSafe,
+        requests::Request,
+        testing::{self, MockCallbackQuery, MockMessage, MockServer},
+        types::{CallbackQuery, UpdateKind},
+        utils::command::ParseError,
+        RequestError,
+    };
+
+    struct Start;
+
+    impl BotCommand for Start {
+        fn descriptions() -> String {
+            String::new()
+        }
+
+        fn parse<N>(s: &str, _: N) -> Result<Self, ParseError>
+        where
+            N: Into<String>,
+        {
+            match s {
+                "/start" => Ok(Start),
+                _ => Err(ParseError::UnknownCommand(s.to_owned())),
+            }
+        }
+    }
+
+    async fn reply(cx: UpdateWithCx<Message>, text: &str) -> Result<(), RequestError> {
+        cx.answer_str(text).await.map(drop)
+    }
+
+    #[tokio::test]
+    async fn first_matching_branch_handles_update() {
+        let server = MockServer::start();
+
+        Dispatcher::new(server.bot())
+            .messages_handler(
+                Router::new()
+                    .command("bot", |cx, Start| reply(cx, "start"))
+                    .branch(
+                        Router::new()
+                            .filter(|message: &Message| message.chat.is_private())
+                            .filter(|message: &Message| message.text() == Some("private"))
+                            .endpoint(|cx| reply(cx, "private")),
+                    )
+                    .endpoint(|cx| reply(cx, "fallback")),
+            )
+            .callback_queries_handler(
+                Router::new()
+                    .filter(|query: &CallbackQuery| {
+                        query.data.as_deref().unwrap_or_default().starts_with("like:")
+                    })
+                    .endpoint(|cx: UpdateWithCx<CallbackQuery>| async move {
+                        cx.bot.answer_callback_query(cx.update.id).send().await.map(drop)
+                    }),
+            )
+            .dispatch_with_listener(
+                testing::updates(vec![
+                    UpdateKind::Message(MockMessage::new("/start").chat_id(10).build()),
+                    UpdateKind::Message(MockMessage::new("private").chat_id(20).build()),
+                    UpdateKind::Message(MockMessage::new("private").chat_id(-30).build()),
+                    UpdateKind::CallbackQuery(MockCallbackQuery::new("like:1").id("40").build()),
+                    UpdateKind::CallbackQuery(MockCallbackQuery::new("share:1").id("50").build()),
+                ]),
+                IgnoringErrorHandlerSafe::new(),
+            )
+            .await;
+
+        let mut requests: Vec<_> = server
+            .requests()
+            .into_iter()
+            .map(|req| match req.method.as_str() {
+                "sendMessage" => format!("{} {}", req.params["chat_id"], req.params["text"]),
+                _ => format!("{} {}", req.method, req.params["callback_query_id"]),
+            })
+            .collect();
+        requests.sort();
+
+        assert_eq!(
+            requests,
+            [
+                r#"-30 "fallback""#,
+                r#"10 "start""#,
+                r#"20 "private""#,
+                r#"answerCallbackQuery "40""#
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn endpoints_extract_dependencies() {
+        let server = MockServer::start();
+
+        async fn greet(cx: UpdateWithCx<Message>, (name,): (String,)) -> Result<(), RequestError> {
+            reply(cx, &name).await
+        }
+
+        Dispatcher::new(server.bot())
+            .dependency("bot".to_owned())
+            .messages_handler(
+                Router::new()
+                    .branch(
+                        Router::new()
+                            .filter(|message: &Message| message.chat.id == 1)
+                            .endpoint_with(|cx, (_, _): (String, i32)| reply(cx, "unreachable")),
+                    )
+                    .endpoint_with(greet),
+            )
+            .dispatch_with_listener(
+                testing::updates(vec![
+                    UpdateKind::Message(MockMessage::new("hi").chat_id(1).build()),
+                    UpdateKind::Message(MockMessage::new("hi").chat_id(2).build()),
+                ]),
+                IgnoringErrorHandlerSafe::new(),
+            )
+            .await;
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].params["chat_id"], 2);
+        assert_eq!(requests[0].params["text"], "bot");
+    }
+}
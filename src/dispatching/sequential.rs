@@ -0,0 +1,278 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::Infallible,
+    hash::Hash,
+    sync::{Arc, Mutex},
+};
+
+use futures::{future, future::BoxFuture, StreamExt};
+use tokio::sync::{mpsc, Semaphore};
+
+use crate::dispatching::{
+    dialogue::GetChatId, panics::catch_panic, DispatcherHandler, DispatcherHandlerRx, Route,
+    UpdateWithCx,
+};
+
+/// A [`DispatcherHandler`], which handles updates with the same key
+/// sequentially, but concurrently with updates with different keys.
+///
+/// A key is a chat ID (see [`Sequential::by_chat`]) or an arbitrary value
+/// computed from an update (see [`Sequential::by_key`]), e.g. an ID of a user.
+/// Updates are handled by a [`Route`], e.g. a [`Router`]. Updates that aren't
+/// matched by the route are ignored.
+///
+/// ## Example
+/// ```
+/// use teloxide::{
+///     dispatching::{Router, Sequential},
+///     prelude::*,
+///     types::CallbackQuery,
+/// };
+///
+/// async fn handle_message(cx: UpdateWithCx<Message>) -> ResponseResult<()> {
+///     cx.answer_str("Got it!").await?;
+///     Ok(())
+/// }
+///
+/// async fn handle_query(cx: UpdateWithCx<CallbackQuery>) -> ResponseResult<()> {
+///     cx.bot.answer_callback_query(cx.update.id).send().await?;
+///     Ok(())
+/// }
+///
+/// # async fn run() {
+/// Dispatcher::new(Bot::from_env())
+///     .messages_handler(
+///         Sequential::by_chat(Router::new().endpoint(handle_message)).concurrency_limit(100),
+///     )
+///     .callback_queries_handler(Sequential::by_key(
+///         |query: &CallbackQuery| query.from.id,
+///         Router::new().endpoint(handle_query),
+///     ))
+///     .dispatch()
+///     .await;
+/// # }
+/// ```
+///
+/// [`DispatcherHandler`]: crate::dispatching::DispatcherHandler
+/// [`Route`]: crate::dispatching::Route
+/// [`Router`]: crate::dispatching::Router
+pub struct Sequential<Upd, K> {
+    key: Arc<dyn Fn(&Upd) -> K + Send + Sync>,
+    route: Arc<dyn Route<Upd>>,
+    concurrency_limit: Option<usize>,
+}
+
+impl<Upd> Sequential<Upd, i64>
+where
+    Upd: GetChatId + Send + 'static,
+{
+    /// Handles updates from the same chat sequentially.
+    #[must_use]
+    pub fn by_chat<R>(route: R) -> Self
+    where
+        R: Route<Upd>,
+    {
+        Self::by_key(GetChatId::chat_id, route)
+    }
+}
+
+impl<Upd, K> Sequential<Upd, K>
+where
+    Upd: Send + 'static,
+    K: Hash + Eq + Clone + Send + 'static,
+{
+    /// Handles updates with the same `key` sequentially.
+    #[must_use]
+    pub fn by_key<F, R>(key: F, route: R) -> Self
+    where
+        F: Fn(&Upd) -> K + Send + Sync + 'static,
+        R: Route<Upd>,
+    {
+        Self { key: Arc::new(key), route: Arc::new(route), concurrency_limit: None }
+    }
+
+    /// Sets the maximum number of updates handled at the same time.
+    ///
+    /// By default, there's no limit.
+    ///
+    /// # Panics
+    /// If `limit` is 0.
+    #[must_use]
+    pub fn concurrency_limit(mut self, limit: usize) -> Self {
+        assert!(limit > 0, "The concurrency limit must be positive");
+        self.concurrency_limit = Some(limit);
+        self
+    }
+}
+
+impl<Upd, K> Clone for Sequential<Upd, K> {
+    fn clone(&self) -> Self {
+        Self {
+            key: Arc::clone(&self.key),
+            route: Arc::clone(&self.route),
+            concurrency_limit: self.concurrency_limit,
+        }
+    }
+}
+
+struct Shared<Upd, K> {
+    route: Arc<dyn Route<Upd>>,
+    semaphore: Option<Semaphore>,
+
+    /// Updates waiting to be handled by keys. A key is present if and only if
+    /// there's a worker handling updates with this key.
+    pending: Mutex<HashMap<K, VecDeque<UpdateWithCx<Upd>>>>,
+}
+
+impl<Upd, K> DispatcherHandler<Upd> for Sequential<Upd, K>
+where
+    Upd: Send + 'static,
+    K: Hash + Eq + Clone + Send + 'static,
+{
+    fn handle(self, updates: DispatcherHandlerRx<Upd>) -> BoxFuture<'static, ()>
+    where
+        UpdateWithCx<Upd>: Send + 'static,
+    {
+        let Self { key, route, concurrency_limit } = self;
+        let shared = Arc::new(Shared {
+            route,
+            semaphore: concurrency_limit.map(Semaphore::new),
+            pending: Mutex::new(HashMap::new()),
+        });
+
+        // Every worker holds a clone of `alive_tx`, so `alive_rx` is closed
+        // after all the workers have finished.
+        let (alive_tx, mut alive_rx) = mpsc::unbounded_channel::<Infallible>();
+
+        Box::pin(async move {
+            updates
+                .for_each(move |cx| {
+                    let key = key(&cx.update);
+                    let mut pending = shared.pending.lock().unwrap();
+
+                    match pending.get_mut(&key) {
+                        Some(updates) => updates.push_back(cx),
+                        None => {
+                            pending.insert(key.clone(), vec![cx].into());
+                            tokio::spawn(work(Arc::clone(&shared), key, alive_tx.clone()));
+                        }
+                    }
+
+                    future::ready(())
+                })
+                .await;
+
+            while alive_rx.recv().await.is_some() {}
+        })
+    }
+}
+
+/// Handles updates with `key` until there are no more such updates.
+async fn work<Upd, K>(shared: Arc<Shared<Upd, K>>, key: K, alive: mpsc::UnboundedSender<Infallible>)
+where
+    Upd: Send + 'static,
+    K: Hash + Eq,
+{
+    loop {
+        let cx = {
+            let mut pending = shared.pending.lock().unwrap();
+            let updates = pending.get_mut(&key).expect("A worker owns its key");
+
+            match updates.pop_front() {
+                Some(cx) => cx,
+                None => {
+                    pending.remove(&key);
+                    break;
+                }
+            }
+        };
+
+        let _permit = match &shared.semaphore {
+            Some(semaphore) => Some(semaphore.acquire().await),
+            None => None,
+        };
+
+        let (dependencies, completion) = (cx.dependencies.clone(), cx.completion.clone());
+        catch_panic(&dependencies, &completion, async {
+            match shared.route.route(cx) {
+                Ok(fut) => fut.await,
+                Err(_) => log::trace!("An update is not matched by any route"),
+            }
+        })
+        .await;
+    }
+
+    drop(alive);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dispatching::{
+            queue::{self, QueueOptions},
+            Dependencies, Router,
+        },
+        Bot,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::time::{delay_for, Duration};
+
+    #[tokio::test]
+    #[allow(deprecated)]
+    async fn sequential_within_key_and_limited() {
+        let handled = Arc::new(Mutex::new(Vec::new()));
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+
+        let handler = Sequential::by_key(
+            |&(key, _): &(u8, u8)| key,
+            Router::new().endpoint({
+                let handled = Arc::clone(&handled);
+                let running = Arc::clone(&running);
+                let max_running = Arc::clone(&max_running);
+
+                move |cx: UpdateWithCx<(u8, u8)>| {
+                    let handled = Arc::clone(&handled);
+                    let running = Arc::clone(&running);
+                    let max_running = Arc::clone(&max_running);
+
+                    async move {
+                        let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_running.fetch_max(now_running, Ordering::SeqCst);
+
+                        // Later updates are handled faster.
+                        delay_for(Duration::from_millis(50 - u64::from(cx.update.1) * 10)).await;
+
+                        handled.lock().unwrap().push(cx.update);
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        Ok::<_, Infallible>(())
+                    }
+                }
+            }),
+        )
+        .concurrency_limit(2);
+
+        let (tx, rx) = queue::channel(QueueOptions::unbounded());
+        for &update in &[(1, 0), (2, 0), (1, 1), (3, 0), (1, 2), (2, 1)] {
+            let cx = UpdateWithCx {
+                bot: Bot::new("Doesn't matter here"),
+                update,
+                dependencies: Dependencies::new(),
+                completion: Default::default(),
+            };
+            tx.send(cx).await.unwrap();
+        }
+        drop(tx);
+
+        handler.handle(rx).await;
+
+        let handled = handled.lock().unwrap();
+        for key in 1..=3 {
+            let updates: Vec<_> = handled.iter().filter(|(k, _)| *k == key).collect();
+            assert!(updates.windows(2).all(|pair| pair[0].1 < pair[1].1), "{:?}", handled);
+        }
+        assert_eq!(handled.len(), 6);
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,81 @@
+use futures::future::BoxFuture;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// A token which is used to stop an [`UpdateListener`] or to shutdown a
+/// [`Dispatcher`].
+///
+/// All the clones of a token share the same state, so stopping one of them
+/// stops all of them. Stopping is idempotent.
+///
+/// [`UpdateListener`]: crate::dispatching::update_listeners::UpdateListener
+/// [`Dispatcher`]: crate::dispatching::Dispatcher
+#[derive(Debug, Clone)]
+pub struct StopToken {
+    tx: Arc<watch::Sender<bool>>,
+    rx: watch::Receiver<bool>,
+}
+
+impl StopToken {
+    /// Creates a token which is not stopped yet.
+    #[must_use]
+    pub fn new() -> Self {
+        let (tx, rx) = watch::channel(false);
+        Self { tx: Arc::new(tx), rx }
+    }
+
+    /// Asks everything that watches this token to stop.
+    pub fn stop(&self) {
+        // `rx` is alive, so this cannot fail.
+        let _ = self.tx.broadcast(true);
+    }
+
+    /// Returns `true` if [`StopToken::stop`] has been called.
+    ///
+    /// [`StopToken::stop`]: crate::dispatching::StopToken::stop
+    #[must_use]
+    pub fn is_stopped(&self) -> bool {
+        *self.rx.borrow()
+    }
+
+    /// Returns a future which resolves as soon as this token is stopped.
+    #[must_use]
+    pub fn stopped(&self) -> BoxFuture<'static, ()> {
+        let mut rx = self.rx.clone();
+
+        Box::pin(async move {
+            while !*rx.borrow() {
+                if rx.recv().await.is_none() {
+                    return;
+                }
+            }
+        })
+    }
+}
+
+impl Default for StopToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::{timeout, Duration};
+
+    #[tokio::test]
+    async fn stopped_resolves_after_stop() {
+        let token = StopToken::new();
+        let stopped = token.stopped();
+
+        assert!(!token.is_stopped());
+        assert!(timeout(Duration::from_millis(50), token.stopped()).await.is_err());
+
+        token.clone().stop();
+
+        assert!(token.is_stopped());
+        timeout(Duration::from_millis(50), stopped).await.unwrap();
+        timeout(Duration::from_millis(50), token.stopped()).await.unwrap();
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    collections::BTreeSet,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use futures::future::BoxFuture;
+use tokio::sync::Notify;
+
+/// A tracker of updates, which have been received by an update listener, but
+/// haven't been handled yet.
+///
+/// An update listener returns it from [`UpdateListener::acknowledger`] if it
+/// needs to know when updates are handled (see [`CommitPolicy::OnHandled`]).
+/// An update is acknowledged after it has been handled, see [`Completion`].
+///
+/// [`UpdateListener::acknowledger`]:
+/// crate::dispatching::update_listeners::UpdateListener::acknowledger
+/// [`CommitPolicy::OnHandled`]:
+/// crate::dispatching::update_listeners::CommitPolicy::OnHandled
+/// [`Completion`]: crate::dispatching::Completion
+#[derive(Clone)]
+pub struct Acknowledger {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    state: Mutex<State>,
+    acked: Notify,
+    flush: Box<dyn Fn(i32) -> BoxFuture<'static, ()> + Send + Sync>,
+}
+
+struct State {
+    /// IDs of received, but not handled updates.
+    pending: BTreeSet<i32>,
+
+    /// The ID of an update after the last received one.
+    next: i32,
+}
+
+impl Acknowledger {
+    /// Creates a tracker, which starts receiving updates from `offset`.
+    ///
+    /// `flush` saves an offset, from which updates should be received after
+    /// a restart.
+    pub(crate) fn new<F>(offset: i32, flush: F) -> Self
+    where
+        F: Fn(i32) -> BoxFuture<'static, ()> + Send + Sync + 'static,
+    {
+        Self {
+            inner: Arc::new(Inner {
+                state: Mutex::new(State { pending: BTreeSet::new(), next: offset }),
+                acked: Notify::new(),
+                flush: Box::new(flush),
+            }),
+        }
+    }
+
+    /// Registers a received update.
+    ///
+    /// Returns `false` if the update has already been received.
+    pub(crate) fn receive(&self, id: i32) -> bool {
+        let mut state = self.inner.state.lock().unwrap();
+
+        if id < state.next {
+            return false;
+        }
+
+        state.pending.insert(id);
+        state.next = id + 1;
+        true
+    }
+
+    /// Returns a guard, which acknowledges the update with `id` on drop.
+    pub(crate) fn guard(&self, id: i32) -> AckGuard {
+        AckGuard { acknowledger: self.clone(), id }
+    }
+
+    /// Returns the ID of the first update that hasn't been handled yet (or of
+    /// the next update, if all are handled).
+    pub(crate) fn offset(&self) -> i32 {
+        let state = self.inner.state.lock().unwrap();
+        state.pending.iter().next().copied().unwrap_or(state.next)
+    }
+
+    /// Waits until an update is acknowledged.
+    pub(crate) async fn acked(&self) {
+        self.inner.acked.notified().await;
+    }
+
+    /// Saves the current offset.
+    pub(crate) async fn flush(&self) {
+        (self.inner.flush)(self.offset()).await;
+    }
+}
+
+impl fmt::Debug for Acknowledger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.inner.state.lock().unwrap();
+        f.debug_struct("Acknowledger")
+            .field("pending", &state.pending)
+            .field("next", &state.next)
+            .finish()
+    }
+}
+
+/// Acknowledges an update on drop.
+pub(crate) struct AckGuard {
+    acknowledger: Acknowledger,
+    id: i32,
+}
+
+impl Drop for AckGuard {
+    fn drop(&mut self) {
+        self.acknowledger.inner.state.lock().unwrap().pending.remove(&self.id);
+        self.acknowledger.inner.acked.notify();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future;
+
+    #[test]
+    fn offset_is_the_first_pending_update() {
+        let acknowledger = Acknowledger::new(10, |_| Box::pin(future::ready(())));
+        assert_eq!(acknowledger.offset(), 10);
+
+        assert!(!acknowledger.receive(9));
+        assert!(acknowledger.receive(10));
+        assert!(acknowledger.receive(12));
+        assert!(!acknowledger.receive(10));
+
+        let first = acknowledger.guard(10);
+        let second = acknowledger.guard(12);
+        assert_eq!(acknowledger.offset(), 10);
+
+        drop(second);
+        assert_eq!(acknowledger.offset(), 10);
+        drop(first);
+        assert_eq!(acknowledger.offset(), 13);
+    }
+}
@@ -5,6 +5,12 @@
 //!  - [`polling_default`], which returns a default long polling listener.
 //!  - [`polling`], which returns a long/short polling listener with your
 //!    configuration.
+//!  - [`polling_with_store`], which returns a polling listener, which keeps
+//!    its offset in an [`OffsetStore`] (e.g. a file or Redis) across restarts.
+//!  - [`webhook`], which sets up a webhook and returns a listener backed by
+//!    an embedded HTTP server.
+//!  - [`from_stream`], which turns an arbitrary stream of updates into a
+//!    listener.
 //!
 //! And then you can extract updates from it and pass them directly to a
 //! dispatcher.
@@ -92,95 +98,118 @@
 //!   updates `0..=N`.
 //!
 //! # Webhooks
-//! See the [README FAQ about webhooks](https://github.com/teloxide/teloxide/blob/master/README.md#faq).
+//! In webhook mode, Telegram sends HTTPS POST requests with updates to a URL
+//! you've specified via [`Bot::set_webhook`]. [`webhook`] binds an HTTP server
+//! to the specified address, registers the webhook and turns incoming requests
+//! into a stream of updates. Note that the server itself speaks plain HTTP, so
+//! you need a reverse proxy (nginx, ngrok, Heroku's router, etc.) to
+//! terminate TLS.
+//!
+//! See also: [Marvin's Marvellous Guide to All Things Webhook].
 //!
 //! [`UpdateListener`]: UpdateListener
 //! [`polling_default`]: polling_default
 //! [`polling`]: polling
+//! [`polling_with_store`]: polling_with_store
+//! [`OffsetStore`]: OffsetStore
+//! [`webhook`]: webhook
+//! [`from_stream`]: from_stream
+//! [`Bot::set_webhook`]: crate::Bot::set_webhook
+//! [Marvin's Marvellous Guide to All Things Webhook]: https://core.telegram.org/bots/webhooks
 //! [`Box::get_updates`]: crate::Bot::get_updates
 //! [getting updates]: https://core.telegram.org/bots/api#getting-updates
 //! [long]: https://en.wikipedia.org/wiki/Push_technology#Long_polling
 //! [short]: https://en.wikipedia.org/wiki/Polling_(computer_science)
 //! [webhook]: https://en.wikipedia.org/wiki/Webhook
 
-use futures::{stream, Stream, StreamExt};
+use futures::{future::BoxFuture, Stream};
+use pin_project::pin_project;
+
+use crate::{dispatching::StopToken, types::Update};
 
-use crate::{
-    bot::Bot,
-    requests::Request,
-    types::{AllowedUpdate, Update},
-    RequestError,
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
 };
 
-use std::{convert::TryInto, time::Duration};
+mod acknowledger;
+mod offset_store;
+mod polling;
+mod webhook;
+
+pub(crate) use acknowledger::AckGuard;
+pub use acknowledger::Acknowledger;
+#[cfg(feature = "redis-storage")]
+pub use offset_store::RedisOffsetStore;
+pub use offset_store::{FileOffsetStore, InMemOffsetStore, OffsetStore};
+pub use polling::{polling, polling_default, polling_with_store, CommitPolicy, PollingError};
+pub use webhook::{webhook, WebhookError, WebhookOptions, WebhookServer};
 
 /// A generic update listener.
+///
+/// A listener can be stopped via a [`StopToken`] returned from
+/// [`UpdateListener::stop_token`]. After that, the listener must yield all the
+/// updates it has already received and then finish the stream.
+///
+/// [`StopToken`]: crate::dispatching::StopToken
+/// [`UpdateListener::stop_token`]:
+/// crate::dispatching::update_listeners::UpdateListener::stop_token
 pub trait UpdateListener<E>: Stream<Item = Result<Update, E>> {
-    // TODO: add some methods here (.shutdown(), etc).
-}
-impl<S, E> UpdateListener<E> for S where S: Stream<Item = Result<Update, E>> {}
+    /// Returns a token which stops this listener.
+    fn stop_token(&mut self) -> StopToken;
 
-/// Returns a long polling update listener with `timeout` of 10 seconds.
-///
-/// See also: [`polling`](polling).
-pub fn polling_default(bot: Bot) -> impl UpdateListener<RequestError> {
-    polling(bot, Some(Duration::from_secs(10)), None, None)
+    /// Returns a tracker of handled updates, if this listener needs to know
+    /// when updates are handled.
+    ///
+    /// By default, it returns `None`.
+    fn acknowledger(&mut self) -> Option<Acknowledger> {
+        None
+    }
 }
 
-/// Returns a long/short polling update listener with some additional options.
-///
-/// - `bot`: Using this bot, the returned update listener will receive updates.
-/// - `timeout`: A timeout for polling.
-/// - `limit`: Limits the number of updates to be retrieved at once. Values
-///   between 1—100 are accepted.
-/// - `allowed_updates`: A list the types of updates you want to receive.
-/// See [`GetUpdates`] for defaults.
+/// Turns an arbitrary stream of updates into an [`UpdateListener`].
 ///
-/// See also: [`polling_default`](polling_default).
+/// When the listener is stopped, the stream is finished immediately.
 ///
-/// [`GetUpdates`]: crate::requests::GetUpdates
-pub fn polling(
-    bot: Bot,
-    timeout: Option<Duration>,
-    limit: Option<u8>,
-    allowed_updates: Option<Vec<AllowedUpdate>>,
-) -> impl UpdateListener<RequestError> {
-    let timeout = timeout.map(|t| t.as_secs().try_into().expect("timeout is too big"));
+/// [`UpdateListener`]: crate::dispatching::update_listeners::UpdateListener
+pub fn from_stream<S, E>(stream: S) -> impl UpdateListener<E>
+where
+    S: Stream<Item = Result<Update, E>>,
+{
+    let stop = StopToken::new();
+    StreamListener { stream, stopped: stop.stopped(), stop }
+}
 
-    stream::unfold(
-        (allowed_updates, bot, 0),
-        move |(mut allowed_updates, bot, mut offset)| async move {
-            let mut req = bot.get_updates().offset(offset);
-            req.timeout = timeout;
-            req.limit = limit;
-            req.allowed_updates = allowed_updates.take();
+#[pin_project]
+struct StreamListener<S> {
+    #[pin]
+    stream: S,
+    stop: StopToken,
+    stopped: BoxFuture<'static, ()>,
+}
 
-            let updates = match req.send().await {
-                Err(err) => vec![Err(err)],
-                Ok(updates) => {
-                    // Set offset to the last update's id + 1
-                    if let Some(upd) = updates.last() {
-                        let id: i32 = match upd {
-                            Ok(ok) => ok.id,
-                            Err((value, _)) => value["update_id"]
-                                .as_i64()
-                                .expect("The 'update_id' field must always exist in Update")
-                                .try_into()
-                                .expect("update_id must be i32"),
-                        };
+impl<S, E> Stream for StreamListener<S>
+where
+    S: Stream<Item = Result<Update, E>>,
+{
+    type Item = Result<Update, E>;
 
-                        offset = id + 1;
-                    }
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
 
-                    let updates =
-                        updates.into_iter().filter_map(Result::ok).collect::<Vec<Update>>();
+        if this.stopped.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(None);
+        }
 
-                    updates.into_iter().map(Ok).collect::<Vec<_>>()
-                }
-            };
+        this.stream.poll_next(cx)
+    }
+}
 
-            Some((stream::iter(updates), (allowed_updates, bot, offset)))
-        },
-    )
-    .flatten()
+impl<S, E> UpdateListener<E> for StreamListener<S>
+where
+    S: Stream<Item = Result<Update, E>>,
+{
+    fn stop_token(&mut self) -> StopToken {
+        self.stop.clone()
+    }
 }
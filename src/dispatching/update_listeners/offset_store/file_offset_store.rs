@@ -0,0 +1,88 @@
+use super::OffsetStore;
+use futures::future::BoxFuture;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio::{fs, sync::Mutex};
+
+/// An offset store, which keeps an offset in a file.
+///
+/// The file is rewritten atomically: an offset is written into a temporary
+/// file next to it, which is then renamed.
+#[derive(Debug)]
+pub struct FileOffsetStore {
+    path: PathBuf,
+
+    /// Serializes writes to the temporary file.
+    lock: Mutex<()>,
+}
+
+impl FileOffsetStore {
+    /// Creates a store, which keeps an offset in the file at `path`.
+    ///
+    /// The file is created on the first save.
+    #[must_use]
+    pub fn new<P>(path: P) -> Arc<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Arc::new(Self { path: path.as_ref().to_owned(), lock: Mutex::new(()) })
+    }
+}
+
+impl OffsetStore for FileOffsetStore {
+    type Error = io::Error;
+
+    fn load_offset(self: Arc<Self>) -> BoxFuture<'static, Result<Option<i32>, Self::Error>> {
+        Box::pin(async move {
+            let content = match fs::read_to_string(&self.path).await {
+                Ok(content) => content,
+                Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+                Err(error) => return Err(error),
+            };
+
+            content
+                .trim()
+                .parse()
+                .map(Some)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+        })
+    }
+
+    fn save_offset(self: Arc<Self>, offset: i32) -> BoxFuture<'static, Result<(), Self::Error>> {
+        Box::pin(async move {
+            let _lock = self.lock.lock().await;
+
+            let mut tmp = self.path.clone().into_os_string();
+            tmp.push(".tmp");
+
+            fs::write(&tmp, offset.to_string()).await?;
+            fs::rename(&tmp, &self.path).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn saves_and_loads_offset() {
+        let path = std::env::temp_dir().join(format!("teloxide-offset-{}", std::process::id()));
+        let store = FileOffsetStore::new(&path);
+
+        assert_eq!(Arc::clone(&store).load_offset().await.unwrap(), None);
+
+        Arc::clone(&store).save_offset(10).await.unwrap();
+        Arc::clone(&store).save_offset(42).await.unwrap();
+        assert_eq!(FileOffsetStore::new(&path).load_offset().await.unwrap(), Some(42));
+
+        std::fs::write(&path, "garbage").unwrap();
+        let error = store.load_offset().await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,37 @@
+use super::OffsetStore;
+use futures::future::BoxFuture;
+use std::{
+    convert::Infallible,
+    sync::{Arc, Mutex},
+};
+
+/// An offset store, which keeps an offset in RAM.
+///
+/// ## Note
+/// The offset will be lost after you restart your bot.
+#[derive(Debug, Default)]
+pub struct InMemOffsetStore {
+    offset: Mutex<Option<i32>>,
+}
+
+impl InMemOffsetStore {
+    #[must_use]
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+}
+
+impl OffsetStore for InMemOffsetStore {
+    type Error = Infallible;
+
+    fn load_offset(self: Arc<Self>) -> BoxFuture<'static, Result<Option<i32>, Self::Error>> {
+        Box::pin(async move { Ok(*self.offset.lock().unwrap()) })
+    }
+
+    fn save_offset(self: Arc<Self>, offset: i32) -> BoxFuture<'static, Result<(), Self::Error>> {
+        Box::pin(async move {
+            *self.offset.lock().unwrap() = Some(offset);
+            Ok(())
+        })
+    }
+}
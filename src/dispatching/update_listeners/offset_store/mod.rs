@@ -0,0 +1,32 @@
+mod file_offset_store;
+mod in_mem_offset_store;
+
+#[cfg(feature = "redis-storage")]
+mod redis_offset_store;
+
+use futures::future::BoxFuture;
+use std::sync::Arc;
+
+pub use file_offset_store::FileOffsetStore;
+pub use in_mem_offset_store::InMemOffsetStore;
+#[cfg(feature = "redis-storage")]
+pub use redis_offset_store::RedisOffsetStore;
+
+/// A storage of a polling offset.
+///
+/// An offset is the ID of the first update, which hasn't been processed yet.
+/// [`polling_with_store`] loads it on start and saves it as updates are
+/// processed, so that after a restart the bot neither skips nor (depending
+/// on [`CommitPolicy`]) reprocesses updates.
+///
+/// [`polling_with_store`]: crate::dispatching::update_listeners::polling_with_store
+/// [`CommitPolicy`]: crate::dispatching::update_listeners::CommitPolicy
+pub trait OffsetStore {
+    type Error;
+
+    /// Returns the saved offset, if any.
+    fn load_offset(self: Arc<Self>) -> BoxFuture<'static, Result<Option<i32>, Self::Error>>;
+
+    /// Saves `offset`, replacing the previous one.
+    fn save_offset(self: Arc<Self>, offset: i32) -> BoxFuture<'static, Result<(), Self::Error>>;
+}
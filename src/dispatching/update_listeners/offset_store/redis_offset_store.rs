@@ -0,0 +1,36 @@
+use super::OffsetStore;
+use futures::future::BoxFuture;
+use redis::{AsyncCommands, IntoConnectionInfo, RedisError};
+use std::{ops::DerefMut, sync::Arc};
+use tokio::sync::Mutex;
+
+/// An offset store based on [Redis](https://redis.io/).
+pub struct RedisOffsetStore {
+    conn: Mutex<redis::aio::Connection>,
+    key: String,
+}
+
+impl RedisOffsetStore {
+    /// Connects to Redis, where an offset is kept under `key`.
+    pub async fn open<K>(url: impl IntoConnectionInfo, key: K) -> Result<Arc<Self>, RedisError>
+    where
+        K: Into<String>,
+    {
+        Ok(Arc::new(Self {
+            conn: Mutex::new(redis::Client::open(url)?.get_async_connection().await?),
+            key: key.into(),
+        }))
+    }
+}
+
+impl OffsetStore for RedisOffsetStore {
+    type Error = RedisError;
+
+    fn load_offset(self: Arc<Self>) -> BoxFuture<'static, Result<Option<i32>, Self::Error>> {
+        Box::pin(async move { self.conn.lock().await.deref_mut().get(&self.key).await })
+    }
+
+    fn save_offset(self: Arc<Self>, offset: i32) -> BoxFuture<'static, Result<(), Self::Error>> {
+        Box::pin(async move { self.conn.lock().await.deref_mut().set(&self.key, offset).await })
+    }
+}
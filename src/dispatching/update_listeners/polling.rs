@@ -0,0 +1,576 @@
+use futures::{
+    future::{self, Either},
+    stream, Stream, StreamExt,
+};
+use pin_project::pin_project;
+use thiserror::Error;
+
+use crate::{
+    bot::Bot,
+    dispatching::{
+        update_listeners::{Acknowledger, InMemOffsetStore, OffsetStore, UpdateListener},
+        StopToken,
+    },
+    requests::Request,
+    types::{AllowedUpdate, Update},
+    RequestError,
+};
+
+use std::{
+    convert::TryInto,
+    fmt::Debug,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+/// When [`polling_with_store`] saves an offset.
+///
+/// [`polling_with_store`]: crate::dispatching::update_listeners::polling_with_store
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum CommitPolicy {
+    /// Save an offset as soon as updates are received, before they are
+    /// handled.
+    ///
+    /// Updates, which are being handled when the bot crashes, are lost (at
+    /// most once processing).
+    OnReceive,
+
+    /// Save an offset after updates have been handled by [`Dispatcher`].
+    ///
+    /// Updates, which are being handled when the bot crashes, are received
+    /// again after a restart (at least once processing). Since the Bot API
+    /// forgets all the updates before a requested offset, at most `limit`
+    /// updates (100 by default) starting from the oldest unhandled one are
+    /// handled at the same time.
+    ///
+    /// An update, whose handler waits for a reply via [`UpdateWithCx::ask`],
+    /// is considered handled when waiting starts (see [`Completion`]), so
+    /// that the reply is received. Such an update isn't received again after
+    /// a restart.
+    ///
+    /// The listener must be run by [`Dispatcher`], otherwise updates are
+    /// never considered handled.
+    ///
+    /// [`UpdateWithCx::ask`]: crate::dispatching::UpdateWithCx::ask
+    /// [`Completion`]: crate::dispatching::Completion
+    /// [`Dispatcher`]: crate::dispatching::Dispatcher
+    OnHandled,
+}
+
+/// An error returned from [`polling_with_store`].
+///
+/// [`polling_with_store`]: crate::dispatching::update_listeners::polling_with_store
+#[derive(Debug, Error)]
+pub enum PollingError<E>
+where
+    E: Debug,
+{
+    #[error("A polling request has failed: {0}")]
+    Request(#[source] RequestError),
+
+    #[error("An offset store has failed: {0:?}")]
+    Store(E),
+}
+
+/// Returns a long polling update listener with `timeout` of 10 seconds.
+///
+/// See also: [`polling`](polling).
+pub fn polling_default(bot: Bot) -> impl UpdateListener<RequestError> {
+    polling(bot, Some(Duration::from_secs(10)), None, None)
+}
+
+/// Returns a long/short polling update listener with some additional options.
+///
+/// - `bot`: Using this bot, the returned update listener will receive updates.
+/// - `timeout`: A timeout for polling.
+/// - `limit`: Limits the number of updates to be retrieved at once. Values
+///   between 1—100 are accepted.
+/// - `allowed_updates`: A list the types of updates you want to receive.
+/// See [`GetUpdates`] for defaults.
+///
+/// When the returned listener is stopped, it cancels the pending request,
+/// yields the already received updates and then confirms them by one more
+/// [`GetUpdates`] call, so they won't be redelivered after a restart.
+///
+/// See also: [`polling_default`](polling_default),
+/// [`polling_with_store`](polling_with_store).
+///
+/// [`GetUpdates`]: crate::requests::GetUpdates
+pub fn polling(
+    bot: Bot,
+    timeout: Option<Duration>,
+    limit: Option<u8>,
+    allowed_updates: Option<Vec<AllowedUpdate>>,
+) -> impl UpdateListener<RequestError> {
+    let Polling { stream, stop, acknowledger } = listen(
+        bot,
+        timeout,
+        limit,
+        allowed_updates,
+        InMemOffsetStore::new(),
+        CommitPolicy::OnReceive,
+    );
+
+    let stream = stream.map(|result| {
+        result.map_err(|error| match error {
+            PollingError::Request(error) => error,
+            PollingError::Store(never) => match never {},
+        })
+    });
+
+    Polling { stream, stop, acknowledger }
+}
+
+/// Returns a polling update listener, which keeps an offset in `store`.
+///
+/// The listener starts from an offset loaded from `store` and saves it
+/// according to `commit`. The other parameters are the same as in
+/// [`polling`].
+///
+/// ## Example
+/// ```no_run
+/// use std::time::Duration;
+/// use teloxide::{
+///     dispatching::update_listeners::{self, CommitPolicy, FileOffsetStore},
+///     error_handlers::LoggingErrorHandler,
+///     prelude::*,
+/// };
+///
+/// # async fn run() {
+/// let bot = Bot::from_env();
+/// let listener = update_listeners::polling_with_store(
+///     bot.clone(),
+///     Some(Duration::from_secs(10)),
+///     None,
+///     None,
+///     FileOffsetStore::new("offset"),
+///     CommitPolicy::OnHandled,
+/// );
+///
+/// Dispatcher::new(bot)
+///     .messages_handler(|rx: DispatcherHandlerRx<Message>| {
+///         rx.for_each(|message| async move {
+///             message.answer_str("pong").await.log_on_error().await;
+///         })
+///     })
+///     .dispatch_with_listener(listener, LoggingErrorHandler::new())
+///     .await;
+/// # }
+/// ```
+///
+/// [`polling`]: crate::dispatching::update_listeners::polling
+pub fn polling_with_store<S>(
+    bot: Bot,
+    timeout: Option<Duration>,
+    limit: Option<u8>,
+    allowed_updates: Option<Vec<AllowedUpdate>>,
+    store: Arc<S>,
+    commit: CommitPolicy,
+) -> impl UpdateListener<PollingError<S::Error>>
+where
+    S: OffsetStore + Send + Sync + 'static,
+    S::Error: Debug + Send + 'static,
+{
+    listen(bot, timeout, limit, allowed_updates, store, commit)
+}
+
+struct State<S> {
+    bot: Bot,
+    allowed_updates: Option<Vec<AllowedUpdate>>,
+    store: Arc<S>,
+    stop: StopToken,
+    acknowledger: Option<Acknowledger>,
+
+    /// Whether an offset has been loaded from `store`.
+    loaded: bool,
+
+    /// Whether the listener has been stopped and has confirmed updates.
+    finished: bool,
+    offset: i32,
+    saved_offset: i32,
+}
+
+type Item<E> = Result<Update, PollingError<E>>;
+
+fn listen<S>(
+    bot: Bot,
+    timeout: Option<Duration>,
+    limit: Option<u8>,
+    allowed_updates: Option<Vec<AllowedUpdate>>,
+    store: Arc<S>,
+    commit: CommitPolicy,
+) -> Polling<impl Stream<Item = Item<S::Error>>>
+where
+    S: OffsetStore + Send + Sync + 'static,
+    S::Error: Debug + Send + 'static,
+{
+    let timeout = timeout.map(|t| t.as_secs().try_into().expect("timeout is too big"));
+    let stop = StopToken::new();
+
+    let acknowledger = match commit {
+        CommitPolicy::OnReceive => None,
+        CommitPolicy::OnHandled => Some(Acknowledger::new(0, {
+            let store = Arc::clone(&store);
+
+            move |offset| {
+                let store = Arc::clone(&store);
+                Box::pin(async move {
+                    if let Err(error) = store.save_offset(offset).await {
+                        log::error!("Cannot save a polling offset: {:?}", error);
+                    }
+                })
+            }
+        })),
+    };
+
+    let state = State {
+        bot,
+        allowed_updates,
+        store,
+        stop: stop.clone(),
+        acknowledger: acknowledger.clone(),
+        loaded: false,
+        finished: false,
+        offset: 0,
+        saved_offset: 0,
+    };
+
+    let stream = stream::unfold(state, move |mut state| async move {
+        if state.stop.is_stopped() {
+            if state.finished {
+                return None;
+            }
+            state.finished = true;
+
+            // Updates are confirmed by the next `GetUpdates` call only, so
+            // after the last batch has been yielded, we do one more call
+            // without waiting for new updates. With `CommitPolicy::OnHandled`,
+            // updates are confirmed after a restart instead, since they
+            // aren't handled yet.
+            let confirmed = match state.acknowledger {
+                None => confirm_on_stop(&state.bot, state.offset).await,
+                Some(_) => None,
+            };
+            return Some((confirmed.into_iter().collect(), state));
+        }
+
+        let mut items = Vec::new();
+
+        if !state.loaded {
+            state.loaded = true;
+            match Arc::clone(&state.store).load_offset().await {
+                Ok(offset) => {
+                    state.offset = offset.unwrap_or(0);
+                    state.saved_offset = state.offset;
+                }
+                Err(error) => items.push(Err(PollingError::Store(error))),
+            }
+        }
+
+        // Updates before the oldest unhandled one are confirmed by the
+        // request below.
+        if let Some(acknowledger) = &state.acknowledger {
+            state.offset = state.offset.max(acknowledger.offset());
+            items.extend(save(&mut state).await);
+        }
+
+        let mut req = state.bot.get_updates().offset(state.offset);
+        req.timeout = timeout;
+        req.limit = limit;
+        req.allowed_updates = state.allowed_updates.take();
+
+        match future::select(req.send(), state.stop.stopped()).await {
+            Either::Left((Err(err), _)) => items.push(Err(PollingError::Request(err))),
+            Either::Left((Ok(updates), _)) => {
+                // Updates that cannot be parsed are passed as
+                // `UpdateKind::Unknown`.
+                let updates: Vec<_> = updates
+                    .into_iter()
+                    .filter_map(|update| match update {
+                        Ok(update) => Some(update),
+                        Err((value, _)) => Update::unknown(value),
+                    })
+                    .collect();
+
+                match &state.acknowledger {
+                    None => {
+                        // Set offset to the last update's id + 1
+                        if let Some(update) = updates.last() {
+                            state.offset = update.id + 1;
+                            items.extend(save(&mut state).await);
+                        }
+
+                        items.extend(updates.into_iter().map(Ok));
+                    }
+                    Some(acknowledger) => {
+                        let received = !updates.is_empty();
+                        let new: Vec<_> = updates
+                            .into_iter()
+                            .filter(|update| acknowledger.receive(update.id))
+                            .collect();
+
+                        // All the received updates are being handled, so
+                        // there's nothing to request until one of them is
+                        // handled.
+                        if received && new.is_empty() {
+                            future::select(Box::pin(acknowledger.acked()), state.stop.stopped())
+                                .await;
+                        }
+
+                        items.extend(new.into_iter().map(Ok));
+                    }
+                }
+            }
+            // The pending request is dropped, so nothing is confirmed by it.
+            Either::Right(((), _)) => {}
+        };
+
+        Some((items, state))
+    })
+    .flat_map(stream::iter);
+
+    Polling { stream, stop, acknowledger }
+}
+
+/// Saves `state.offset` if it has changed.
+async fn save<S>(state: &mut State<S>) -> Option<Item<S::Error>>
+where
+    S: OffsetStore,
+    S::Error: Debug,
+{
+    if state.offset == state.saved_offset {
+        return None;
+    }
+
+    match Arc::clone(&state.store).save_offset(state.offset).await {
+        Ok(()) => {
+            state.saved_offset = state.offset;
+            None
+        }
+        Err(error) => Some(Err(PollingError::Store(error))),
+    }
+}
+
+/// Confirms updates before `offset`.
+async fn confirm_on_stop<E>(bot: &Bot, offset: i32) -> Option<Item<E>>
+where
+    E: Debug,
+{
+    if offset == 0 {
+        return None;
+    }
+
+    match bot.get_updates().offset(offset).timeout(0).limit(1).send().await {
+        Ok(_) => None,
+        Err(err) => Some(Err(PollingError::Request(err))),
+    }
+}
+
+#[pin_project]
+struct Polling<S> {
+    #[pin]
+    stream: S,
+    stop: StopToken,
+    acknowledger: Option<Acknowledger>,
+}
+
+impl<S, E> Stream for Polling<S>
+where
+    S: Stream<Item = Result<Update, E>>,
+{
+    type Item = Result<Update, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.project().stream.poll_next(cx)
+    }
+}
+
+impl<S, E> UpdateListener<E> for Polling<S>
+where
+    S: Stream<Item = Result<Update, E>>,
+{
+    fn stop_token(&mut self) -> StopToken {
+        self.stop.clone()
+    }
+
+    fn acknowledger(&mut self) -> Option<Acknowledger> {
+        self.acknowledger.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dispatching::{Conversations, Dispatcher, DispatcherHandlerRx, Router, UpdateWithCx},
+        error_handlers::IgnoringErrorHandler,
+        testing::MockServer,
+        types::Message,
+    };
+    use serde_json::json;
+    use std::{convert::Infallible, sync::Mutex};
+    use tokio::sync::Notify;
+
+    fn message_update(id: i32) -> serde_json::Value {
+        text_update(id, "hello there")
+    }
+
+    fn text_update(id: i32, text: &str) -> serde_json::Value {
+        json!({
+            "update_id": id,
+            "message": {
+                "message_id": id,
+                "chat": { "id": 1, "first_name": "Waffle", "type": "private" },
+                "date": 1_569_518_342,
+                "text": text
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn confirms_updates_on_stop() {
+        let server = MockServer::start();
+        server.respond("getUpdates", vec![message_update(1), message_update(2)]);
+
+        let mut listener = polling(server.bot(), None, None, None);
+        let stop = listener.stop_token();
+        let mut listener = Box::pin(listener);
+        assert_eq!(listener.next().await.unwrap().unwrap().id, 1);
+        assert_eq!(listener.next().await.unwrap().unwrap().id, 2);
+
+        // The listener is stopped while it's requesting the next updates.
+        tokio::spawn(async move {
+            tokio::time::delay_for(Duration::from_millis(20)).await;
+            stop.stop();
+        });
+        while listener.next().await.is_some() {}
+
+        let requests = server.requests();
+        let last = &requests.last().unwrap().params;
+        assert_eq!(
+            (&last["offset"], &last["limit"], &last["timeout"]),
+            (&json!(3), &json!(1), &json!(0))
+        );
+    }
+
+    #[tokio::test]
+    async fn commits_handled_updates() {
+        let server = MockServer::start();
+        let store = InMemOffsetStore::new();
+        Arc::clone(&store).save_offset(5).await.unwrap();
+
+        server.respond("getUpdates", vec![message_update(5), message_update(6)]);
+        // The update 5 is still being handled, so it's requested again.
+        server.respond("getUpdates", vec![message_update(6), message_update(7)]);
+        server.respond("getUpdates", vec![message_update(5)]);
+
+        let handled = Arc::new(Mutex::new(Vec::new()));
+        let seventh_handled = Arc::new(Notify::new());
+
+        let dispatcher = Dispatcher::new(server.bot());
+        let shutdown = dispatcher.shutdown_token();
+
+        dispatcher
+            .messages_handler({
+                let handled = Arc::clone(&handled);
+
+                move |rx: DispatcherHandlerRx<Message>| {
+                    rx.for_each_concurrent(None, move |cx| {
+                        let handled = Arc::clone(&handled);
+                        let seventh_handled = Arc::clone(&seventh_handled);
+                        let shutdown = shutdown.clone();
+
+                        async move {
+                            match cx.update.id {
+                                5 => seventh_handled.notified().await,
+                                7 => seventh_handled.notify(),
+                                _ => {}
+                            }
+
+                            let mut handled = handled.lock().unwrap();
+                            handled.push(cx.update.id);
+                            if handled.len() == 3 {
+                                shutdown.stop();
+                            }
+                        }
+                    })
+                }
+            })
+            .dispatch_with_listener(
+                polling_with_store(
+                    server.bot(),
+                    None,
+                    None,
+                    None,
+                    Arc::clone(&store),
+                    CommitPolicy::OnHandled,
+                ),
+                IgnoringErrorHandler::new(),
+            )
+            .await;
+
+        let requests = server.requests();
+        assert_eq!(requests[0].params["offset"], 5);
+        assert_eq!(requests[1].params["offset"], 5);
+        assert_eq!(*handled.lock().unwrap(), [6, 7, 5]);
+        assert_eq!(store.load_offset().await.unwrap(), Some(8));
+    }
+
+    #[tokio::test]
+    async fn replies_are_received_while_asking() {
+        let server = MockServer::start();
+        let store = InMemOffsetStore::new();
+        Arc::clone(&store).save_offset(1).await.unwrap();
+
+        server.respond("getUpdates", vec![text_update(1, "/start")]);
+        // The update 1 is still being handled, so it's requested again.
+        server.respond("getUpdates", vec![text_update(1, "/start")]);
+        server.respond("getUpdates", vec![text_update(2, "Alice")]);
+
+        let replies = Arc::new(Mutex::new(Vec::new()));
+
+        let dispatcher = Dispatcher::new(server.bot())
+            .conversations(Conversations::new().timeout(Duration::from_secs(1)));
+        let shutdown = dispatcher.shutdown_token();
+
+        dispatcher
+            .messages_handler(Router::new().endpoint({
+                let replies = Arc::clone(&replies);
+
+                move |cx: UpdateWithCx<Message>| {
+                    let replies = Arc::clone(&replies);
+                    let shutdown = shutdown.clone();
+
+                    async move {
+                        let reply = cx.ask("Name?").await.map(|reply| reply.id);
+                        replies.lock().unwrap().push(reply.map_err(|error| error.to_string()));
+                        shutdown.stop();
+                        Ok::<_, Infallible>(())
+                    }
+                }
+            }))
+            .dispatch_with_listener(
+                polling_with_store(
+                    server.bot(),
+                    None,
+                    Some(1),
+                    None,
+                    Arc::clone(&store),
+                    CommitPolicy::OnHandled,
+                ),
+                IgnoringErrorHandler::new(),
+            )
+            .await;
+
+        let requests: Vec<_> = server
+            .requests()
+            .into_iter()
+            .filter(|request| request.method == "getUpdates")
+            .collect();
+        assert_eq!(requests[2].params["offset"], 2);
+        assert_eq!(*replies.lock().unwrap(), [Ok(2)]);
+        assert_eq!(store.load_offset().await.unwrap(), Some(3));
+    }
+}
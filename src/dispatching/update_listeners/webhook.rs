@@ -0,0 +1,503 @@
+use futures::Stream;
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request as HttpRequest, Response, Server, StatusCode,
+};
+use reqwest::Url;
+use serde_json::Value;
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+use crate::{
+    bot::Bot,
+    dispatching::{update_listeners::UpdateListener, StopToken},
+    requests::Request,
+    types::{AllowedUpdate, InputFile, Update},
+    RequestError,
+};
+
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    convert::Infallible,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+/// Options of a webhook of a bot.
+///
+/// See [`webhook`] and [`WebhookServer::listen`].
+///
+/// [`webhook`]: crate::dispatching::update_listeners::webhook
+/// [`WebhookServer::listen`]:
+/// crate::dispatching::update_listeners::WebhookServer::listen
+#[derive(Debug, Clone)]
+pub struct WebhookOptions {
+    url: Url,
+    path: Option<String>,
+    certificate: Option<InputFile>,
+    max_connections: Option<i32>,
+    allowed_updates: Option<Vec<AllowedUpdate>>,
+}
+
+impl WebhookOptions {
+    /// Creates options of a webhook, which asks Telegram to send updates to
+    /// `url`.
+    ///
+    /// Only `POST` requests to the path of `url` are accepted, so it's
+    /// recommended to put a secret into it, e.g.
+    /// `https://example.com/<secret>`. Since nobody else knows the secret, you
+    /// can be pretty sure that updates come from Telegram.
+    #[must_use]
+    pub fn new(url: Url) -> Self {
+        Self { url, path: None, certificate: None, max_connections: None, allowed_updates: None }
+    }
+
+    /// Specifies a path to accept updates on, if it differs from the path of
+    /// the webhook URL (e.g. if a reverse proxy rewrites it).
+    #[must_use]
+    pub fn path<T>(mut self, path: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Specifies a public key certificate, so that the root certificate in use
+    /// can be checked.
+    ///
+    /// See [`SetWebhook::certificate`].
+    ///
+    /// [`SetWebhook::certificate`]: crate::requests::SetWebhook::certificate
+    #[must_use]
+    pub fn certificate(mut self, certificate: InputFile) -> Self {
+        self.certificate = Some(certificate);
+        self
+    }
+
+    /// Specifies the maximum allowed number of simultaneous HTTPS connections
+    /// to the webhook, 1-100.
+    ///
+    /// See [`SetWebhook::max_connections`].
+    ///
+    /// [`SetWebhook::max_connections`]:
+    /// crate::requests::SetWebhook::max_connections
+    #[must_use]
+    pub fn max_connections(mut self, max_connections: i32) -> Self {
+        self.max_connections = Some(max_connections);
+        self
+    }
+
+    /// Specifies the types of updates you want your bot to receive.
+    ///
+    /// See [`SetWebhook::allowed_updates`].
+    ///
+    /// [`SetWebhook::allowed_updates`]:
+    /// crate::requests::SetWebhook::allowed_updates
+    #[must_use]
+    pub fn allowed_updates<T>(mut self, allowed_updates: T) -> Self
+    where
+        T: Into<Vec<AllowedUpdate>>,
+    {
+        self.allowed_updates = Some(allowed_updates.into());
+        self
+    }
+}
+
+/// An error returned from [`webhook`].
+///
+/// [`webhook`]: crate::dispatching::update_listeners::webhook
+#[derive(Debug, Error)]
+pub enum WebhookError {
+    #[error("Cannot bind an HTTP server: {0}")]
+    Bind(#[source] hyper::Error),
+
+    #[error("Cannot set a webhook: {0}")]
+    SetWebhook(#[source] RequestError),
+
+    #[error("The path {0} is already used by another webhook")]
+    PathInUse(String),
+}
+
+/// Returns a webhook update listener.
+///
+/// This function binds an HTTP server to `address`, calls
+/// [`Bot::set_webhook`] with `options` and returns a stream of updates received by the
+/// server. When the returned listener is stopped or dropped, the server is
+/// gracefully shut down and the webhook is deleted via
+/// [`Bot::delete_webhook`].
+///
+/// Updates which cannot be parsed are logged and acknowledged, so that
+/// Telegram doesn't resend them infinitely.
+///
+/// ## Example
+/// ```no_run
+/// use teloxide::{
+///     dispatching::update_listeners::{self, WebhookOptions},
+///     prelude::*,
+/// };
+///
+/// # #[tokio::main]
+/// # async fn main_() {
+/// let bot = Bot::from_env();
+///
+/// let options = WebhookOptions::new("https://example.com/my-secret-path".parse().unwrap());
+/// let listener = update_listeners::webhook(bot.clone(), ([0, 0, 0, 0], 8443).into(), options)
+///     .await
+///     .expect("Cannot setup a webhook");
+///
+/// teloxide::repl_with_listener(
+///     bot,
+///     |message| async move {
+///         message.answer_str("pong").await?;
+///         ResponseResult::<()>::Ok(())
+///     },
+///     listener,
+/// )
+/// .await;
+/// # }
+/// ```
+///
+/// [`Bot::set_webhook`]: crate::Bot::set_webhook
+/// [`Bot::delete_webhook`]: crate::Bot::delete_webhook
+pub async fn webhook(
+    bot: Bot,
+    address: SocketAddr,
+    options: WebhookOptions,
+) -> Result<impl UpdateListener<Infallible>, WebhookError> {
+    let server = WebhookServer::bind(address)?;
+    let mut listener = server.listen_inner(bot, options).await?;
+    listener.server = Some(server);
+    Ok(listener)
+}
+
+/// An HTTP server, which receives updates of many bots.
+///
+/// Every bot gets its own webhook listener (see [`WebhookServer::listen`])
+/// with its own path, e.g. with a token of the bot in it. The server is
+/// gracefully shut down when it's dropped.
+///
+/// ## Example
+/// ```no_run
+/// use teloxide::{
+///     dispatching::update_listeners::{WebhookOptions, WebhookServer},
+///     BotBuilder,
+/// };
+///
+/// # async fn run(tokens: Vec<String>) {
+/// let server = WebhookServer::bind(([0, 0, 0, 0], 8443).into()).unwrap();
+///
+/// for token in tokens {
+///     let url = format!("https://example.com/{}", token).parse().unwrap();
+///     let bot = BotBuilder::new().token(token).build();
+///
+///     let listener = server.listen(bot.clone(), WebhookOptions::new(url)).await.unwrap();
+///     // Pass `listener` to a dispatcher of `bot`...
+/// }
+/// # }
+/// ```
+///
+/// [`WebhookServer::listen`]:
+/// crate::dispatching::update_listeners::WebhookServer::listen
+#[derive(Debug)]
+pub struct WebhookServer {
+    routes: Routes,
+    stop: StopToken,
+}
+
+/// Senders of updates by paths.
+type Routes = Arc<Mutex<HashMap<String, mpsc::UnboundedSender<Update>>>>;
+
+impl WebhookServer {
+    /// Binds an HTTP server to `address`.
+    ///
+    /// # Panics
+    /// If it's called outside of the Tokio runtime.
+    pub fn bind(address: SocketAddr) -> Result<Self, WebhookError> {
+        let builder = Server::try_bind(&address).map_err(WebhookError::Bind)?;
+
+        let routes = Routes::default();
+        let stop = StopToken::new();
+
+        let server = builder
+            .serve(make_service_fn({
+                let routes = Arc::clone(&routes);
+
+                move |_| {
+                    let routes = Arc::clone(&routes);
+
+                    async move {
+                        Ok::<_, Infallible>(service_fn(move |req| {
+                            route_request(req, Arc::clone(&routes))
+                        }))
+                    }
+                }
+            }))
+            .with_graceful_shutdown(stop.stopped());
+
+        tokio::spawn(async move {
+            if let Err(error) = server.await {
+                log::error!("The webhook server has failed: {}", error);
+            }
+        });
+
+        Ok(Self { routes, stop })
+    }
+
+    /// Calls [`Bot::set_webhook`] and returns a stream of updates received on
+    /// the path of the webhook.
+    ///
+    /// When the returned listener is stopped or dropped, the path is
+    /// released and the webhook is deleted via [`Bot::delete_webhook`].
+    ///
+    /// [`Bot::set_webhook`]: crate::Bot::set_webhook
+    /// [`Bot::delete_webhook`]: crate::Bot::delete_webhook
+    pub async fn listen(
+        &self,
+        bot: Bot,
+        options: WebhookOptions,
+    ) -> Result<impl UpdateListener<Infallible>, WebhookError> {
+        self.listen_inner(bot, options).await
+    }
+
+    async fn listen_inner(
+        &self,
+        bot: Bot,
+        options: WebhookOptions,
+    ) -> Result<Webhook, WebhookError> {
+        let WebhookOptions { url, path, certificate, max_connections, allowed_updates } = options;
+        let path = path.unwrap_or_else(|| url.path().to_owned());
+
+        if self.routes.lock().unwrap().contains_key(&path) {
+            return Err(WebhookError::PathInUse(path));
+        }
+
+        let mut req = bot.set_webhook(url.as_str());
+        if let Some(certificate) = certificate {
+            req = req.certificate(certificate);
+        }
+        if let Some(max_connections) = max_connections {
+            req = req.max_connections(max_connections);
+        }
+        if let Some(allowed_updates) = allowed_updates {
+            req = req.allowed_updates(allowed_updates);
+        }
+        req.send().await.map_err(WebhookError::SetWebhook)?;
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        match self.routes.lock().unwrap().entry(path.clone()) {
+            Entry::Occupied(_) => return Err(WebhookError::PathInUse(path)),
+            Entry::Vacant(entry) => entry.insert(tx),
+        };
+
+        let stop = StopToken::new();
+        let stopped = stop.stopped();
+        let routes = Arc::clone(&self.routes);
+
+        tokio::spawn(async move {
+            stopped.await;
+
+            // The stream is finished after the requests being handled have
+            // pushed their updates.
+            routes.lock().unwrap().remove(&path);
+
+            if let Err(error) = bot.delete_webhook().send().await {
+                log::error!("Cannot delete the webhook: {}", error);
+            }
+        });
+
+        Ok(Webhook { updates: rx, stop, server: None })
+    }
+}
+
+impl Drop for WebhookServer {
+    fn drop(&mut self) {
+        self.stop.stop();
+    }
+}
+
+/// A stream of updates received by the webhook server.
+///
+/// The stream is finished after the path has been released, i.e. all the
+/// updates received before [`StopToken::stop`] are yielded.
+///
+/// [`StopToken::stop`]: crate::dispatching::StopToken::stop
+struct Webhook {
+    updates: mpsc::UnboundedReceiver<Update>,
+    stop: StopToken,
+
+    /// A server, which is owned by this listener (see [`webhook`]).
+    server: Option<WebhookServer>,
+}
+
+impl Stream for Webhook {
+    type Item = Result<Update, Infallible>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.updates.poll_recv(cx).map(|update| update.map(Ok))
+    }
+}
+
+impl UpdateListener<Infallible> for Webhook {
+    fn stop_token(&mut self) -> StopToken {
+        self.stop.clone()
+    }
+}
+
+impl Drop for Webhook {
+    fn drop(&mut self) {
+        self.stop.stop();
+    }
+}
+
+async fn route_request(
+    req: HttpRequest<Body>,
+    routes: Routes,
+) -> Result<Response<Body>, Infallible> {
+    let path = req.uri().path();
+    let tx = routes.lock().unwrap().get(path).cloned();
+
+    match tx {
+        Some(tx) => {
+            let path = path.into();
+            handle_request(req, path, tx).await
+        }
+        None => Ok(with_status(StatusCode::NOT_FOUND)),
+    }
+}
+
+async fn handle_request(
+    req: HttpRequest<Body>,
+    path: Arc<str>,
+    tx: mpsc::UnboundedSender<Update>,
+) -> Result<Response<Body>, Infallible> {
+    if req.method() != Method::POST || req.uri().path() != &*path {
+        return Ok(with_status(StatusCode::NOT_FOUND));
+    }
+
+    let body = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(body) => body,
+        Err(error) => {
+            log::error!("Cannot read a webhook request: {}", error);
+            return Ok(with_status(StatusCode::BAD_REQUEST));
+        }
+    };
+
+    let value = match serde_json::from_slice::<Value>(&body) {
+        Ok(value) => value,
+        Err(error) => {
+            log::error!("A webhook request contains invalid JSON: {}", error);
+            return Ok(with_status(StatusCode::BAD_REQUEST));
+        }
+    };
+
+    if let Some(update) = Update::parse_or_unknown(value) {
+        if tx.send(update).is_err() {
+            log::error!("The webhook update listener is dropped, but an update is received");
+            return Ok(with_status(StatusCode::SERVICE_UNAVAILABLE));
+        }
+    }
+
+    Ok(with_status(StatusCode::OK))
+}
+
+fn with_status(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::UpdateKind;
+
+    const UPDATE: &str = r#"{
+        "update_id":892252934,
+        "message":{
+            "message_id":6557,
+            "from":{"id":218485655,"is_bot":false,"first_name":"Waffle"},
+            "chat":{"id":218485655,"first_name":"Waffle","type":"private"},
+            "date":1569518342,
+            "text":"hello there"
+        }
+    }"#;
+
+    fn request(method: Method, path: &str, body: &'static str) -> HttpRequest<Body> {
+        HttpRequest::builder().method(method).uri(path).body(Body::from(body)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn accepts_updates_on_secret_path() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let response =
+            handle_request(request(Method::POST, "/secret", UPDATE), "/secret".into(), tx)
+                .await
+                .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(rx.recv().await.unwrap().id, 892_252_934);
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_path_and_method() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let wrong_path =
+            handle_request(request(Method::POST, "/other", UPDATE), "/secret".into(), tx.clone())
+                .await
+                .unwrap();
+        let wrong_method =
+            handle_request(request(Method::GET, "/secret", ""), "/secret".into(), tx)
+                .await
+                .unwrap();
+
+        assert_eq!(wrong_path.status(), StatusCode::NOT_FOUND);
+        assert_eq!(wrong_method.status(), StatusCode::NOT_FOUND);
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn acknowledges_unparseable_updates() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let response = handle_request(
+            request(Method::POST, "/secret", r#"{"update_id":1,"unknown_kind":{}}"#),
+            "/secret".into(),
+            tx,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            rx.recv().await.unwrap().kind,
+            UpdateKind::Unknown(serde_json::json!({"update_id": 1, "unknown_kind": {}}))
+        );
+    }
+
+    #[tokio::test]
+    async fn routes_updates_by_paths() {
+        let (first_tx, mut first_rx) = mpsc::unbounded_channel();
+        let (second_tx, mut second_rx) = mpsc::unbounded_channel();
+        let routes = Routes::default();
+        routes.lock().unwrap().insert("/first".to_owned(), first_tx);
+        routes.lock().unwrap().insert("/second".to_owned(), second_tx);
+
+        for path in &["/second", "/first", "/third"] {
+            let response =
+                route_request(request(Method::POST, path, UPDATE), Arc::clone(&routes)).await;
+            let expected = if *path == "/third" { StatusCode::NOT_FOUND } else { StatusCode::OK };
+            assert_eq!(response.unwrap().status(), expected);
+        }
+        drop(routes);
+
+        assert_eq!(first_rx.recv().await.unwrap().id, 892_252_934);
+        assert!(first_rx.recv().await.is_none());
+        assert_eq!(second_rx.recv().await.unwrap().id, 892_252_934);
+        assert!(second_rx.recv().await.is_none());
+    }
+}
@@ -1,12 +1,15 @@
 use crate::{
-    dispatching::dialogue::GetChatId,
+    dispatching::{
+        admin_cache, conversations::Reply, dialogue::GetChatId, AdminCache, AdminCheckError,
+        AskError, Completion, Conversations, Dependencies, MissingDependency,
+    },
     requests::{
         DeleteMessage, EditMessageCaption, EditMessageText, ForwardMessage, PinChatMessage,
         Request, ResponseResult, SendAnimation, SendAudio, SendContact, SendDice, SendDocument,
         SendLocation, SendMediaGroup, SendMessage, SendPhoto, SendSticker, SendVenue, SendVideo,
         SendVideoNote, SendVoice,
     },
-    types::{ChatId, ChatOrInlineMessage, InputFile, InputMedia, Message},
+    types::{AdminRight, ChatId, ChatOrInlineMessage, InputFile, InputMedia, Message},
     Bot,
 };
 
@@ -20,6 +23,69 @@ use crate::{
 pub struct UpdateWithCx<Upd> {
     pub bot: Bot,
     pub update: Upd,
+
+    /// Dependencies registered in [`Dispatcher`].
+    ///
+    /// [`Dispatcher`]: crate::dispatching::Dispatcher
+    pub dependencies: Dependencies,
+
+    /// A guard, which marks the update as handled once dropped.
+    ///
+    /// See [`Completion`] for the details.
+    ///
+    /// [`Completion`]: crate::dispatching::Completion
+    pub completion: Completion,
+}
+
+impl<Upd> UpdateWithCx<Upd> {
+    /// Returns a dependency of type `T`.
+    ///
+    /// See [`Dependencies`] for the details and [`UpdateWithCx::try_dependency`]
+    /// for a non-panicking version.
+    ///
+    /// # Panics
+    /// If a dependency of type `T` hasn't been registered via
+    /// [`Dispatcher::dependency`].
+    ///
+    /// [`Dependencies`]: crate::dispatching::Dependencies
+    /// [`UpdateWithCx::try_dependency`]: crate::dispatching::UpdateWithCx::try_dependency
+    /// [`Dispatcher::dependency`]: crate::dispatching::Dispatcher::dependency
+    #[must_use]
+    pub fn dependency<T>(&self) -> T
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.dependencies.require().unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Returns a dependency of type `T`, if it has been registered via
+    /// [`Dispatcher::dependency`].
+    ///
+    /// [`Dispatcher::dependency`]: crate::dispatching::Dispatcher::dependency
+    #[must_use]
+    pub fn try_dependency<T>(&self) -> Option<T>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.dependencies.get()
+    }
+
+    /// Returns a [`tracing`] span of the update.
+    ///
+    /// [`Router`], [`Sequential`], [`DialogueDispatcher`] and the REPLs run
+    /// handlers within this span. Use it to instrument your own tasks, e.g.
+    /// in a handler passed directly to [`Dispatcher`].
+    ///
+    /// [`tracing`]: https://docs.rs/tracing
+    /// [`Router`]: crate::dispatching::Router
+    /// [`Sequential`]: crate::dispatching::Sequential
+    /// [`DialogueDispatcher`]: crate::dispatching::dialogue::DialogueDispatcher
+    /// [`Dispatcher`]: crate::dispatching::Dispatcher
+    #[cfg(feature = "tracing-spans")]
+    #[must_use]
+    pub fn span(&self) -> tracing::Span {
+        self.dependencies.get().unwrap_or_else(tracing::Span::none)
+    }
 }
 
 impl<Upd> GetChatId for UpdateWithCx<Upd>
@@ -40,6 +106,105 @@ impl UpdateWithCx<Message> {
         self.answer(text).send().await
     }
 
+    /// Sends `text` to the chat and waits for a reply of the same user.
+    ///
+    /// The reply is the next message from the user in the chat, which isn't
+    /// passed to the handlers. See [`Conversations`] for the details.
+    ///
+    /// Returns [`AskError::NotEnabled`] if conversations haven't been enabled
+    /// via [`Dispatcher::conversations`].
+    ///
+    /// [`Conversations`]: crate::dispatching::Conversations
+    /// [`AskError::NotEnabled`]: crate::dispatching::AskError::NotEnabled
+    /// [`Dispatcher::conversations`]: crate::dispatching::Dispatcher::conversations
+    pub async fn ask<T>(&self, text: T) -> Result<Message, AskError>
+    where
+        T: Into<String>,
+    {
+        let reply = self.expect_reply()?;
+        self.answer_str(text).await?;
+        reply.recv().await
+    }
+
+    /// Waits for a reply of the same user without sending anything.
+    ///
+    /// Returns [`AskError::NotEnabled`] if conversations haven't been enabled
+    /// via [`Dispatcher::conversations`].
+    ///
+    /// [`AskError::NotEnabled`]: crate::dispatching::AskError::NotEnabled
+    /// [`Dispatcher::conversations`]: crate::dispatching::Dispatcher::conversations
+    pub async fn wait_reply(&self) -> Result<Message, AskError> {
+        self.expect_reply()?.recv().await
+    }
+
+    fn expect_reply(&self) -> Result<Reply, MissingDependency> {
+        let conversations: Conversations = self.dependencies.require()?;
+        let reply =
+            conversations.expect(self.update.chat.id, self.update.from().map(|user| user.id));
+
+        // Otherwise, `CommitPolicy::OnHandled` may hold back the reply.
+        self.completion.acknowledge();
+        Ok(reply)
+    }
+
+    /// Returns `true` if the sender is the creator or an administrator of the
+    /// chat.
+    ///
+    /// Always `false` in private chats and for messages without a sender,
+    /// e.g. channel posts.
+    ///
+    /// Returns [`AdminCheckError::NotEnabled`] if the cache of administrators
+    /// hasn't been enabled via [`Dispatcher::admin_cache`].
+    ///
+    /// [`AdminCheckError::NotEnabled`]: crate::dispatching::AdminCheckError::NotEnabled
+    /// [`Dispatcher::admin_cache`]: crate::dispatching::Dispatcher::admin_cache
+    pub async fn sender_is_admin(&self) -> Result<bool, AdminCheckError> {
+        match self.update.from() {
+            Some(user) if !self.update.chat.is_private() => {
+                let cache: AdminCache = self.dependencies.require()?;
+                Ok(cache.is_admin(&self.bot, self.update.chat.id, user.id).await?)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Returns `true` if the sender has `right` in the chat.
+    ///
+    /// Always `false` in private chats and for messages without a sender.
+    ///
+    /// Returns [`AdminCheckError::NotEnabled`] if the cache of administrators
+    /// hasn't been enabled via [`Dispatcher::admin_cache`].
+    ///
+    /// [`AdminCheckError::NotEnabled`]: crate::dispatching::AdminCheckError::NotEnabled
+    /// [`Dispatcher::admin_cache`]: crate::dispatching::Dispatcher::admin_cache
+    pub async fn sender_can(&self, right: AdminRight) -> Result<bool, AdminCheckError> {
+        match self.update.from() {
+            Some(user) if !self.update.chat.is_private() => {
+                let cache: AdminCache = self.dependencies.require()?;
+                Ok(cache.can(&self.bot, self.update.chat.id, user.id, right).await?)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Returns `true` if the bot has `right` in the chat.
+    ///
+    /// Always `false` in private chats.
+    ///
+    /// Returns [`AdminCheckError::NotEnabled`] if the cache of administrators
+    /// hasn't been enabled via [`Dispatcher::admin_cache`].
+    ///
+    /// [`AdminCheckError::NotEnabled`]: crate::dispatching::AdminCheckError::NotEnabled
+    /// [`Dispatcher::admin_cache`]: crate::dispatching::Dispatcher::admin_cache
+    pub async fn bot_can(&self, right: AdminRight) -> Result<bool, AdminCheckError> {
+        if self.update.chat.is_private() {
+            return Ok(false);
+        }
+        let cache: AdminCache = self.dependencies.require()?;
+        let bot_id = admin_cache::bot_id(&self.bot).await?;
+        Ok(cache.can(&self.bot, self.update.chat.id, bot_id, right).await?)
+    }
+
     pub fn answer<T>(&self, text: T) -> SendMessage
     where
         T: Into<String>,
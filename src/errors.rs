@@ -34,6 +34,10 @@ pub enum RequestError {
 
     #[error("An error while parsing JSON: {0}")]
     InvalidJson(#[source] serde_json::Error),
+
+    /// A file to be uploaded cannot be read.
+    #[error("An I/O error: {0}")]
+    Io(#[source] std::io::Error),
 }
 
 /// A kind of an API error.
@@ -0,0 +1,262 @@
+//! Layers, which wrap requests sent by [`Bot`].
+//!
+//! A layer sees the name and the serialized parameters of every request sent
+//! by a bot, can change them, and sees the raw result of a request. So it's
+//! possible to implement cross-cutting behaviour like logging, metrics,
+//! caching or request mutation, and to compose it via [`Bot::layer`].
+//!
+//! The following layers are provided:
+//!  - [`DefaultParseMode`], see [`BotBuilder::parse_mode`].
+//!  - [`Retry`], see [`BotBuilder::retry_policy`].
+//!  - [`Throttle`], see [`BotBuilder::throttle`].
+//!  - [`RequestMetrics`], see the [`metrics`] module.
+//!
+//! ## Example
+//! ```
+//! use serde_json::value::RawValue;
+//! use teloxide::{
+//!     layers::{Layer, Next, RawRequest},
+//!     requests::ResponseResult,
+//!     BotBuilder,
+//! };
+//!
+//! struct Logging;
+//!
+//! #[async_trait::async_trait]
+//! impl Layer for Logging {
+//!     async fn call(&self, request: RawRequest, next: Next<'_>) -> ResponseResult<Box<RawValue>> {
+//!         log::info!("Sending {}", request.method_name);
+//!
+//!         let result = next.run(request).await;
+//!         if let Err(error) = &result {
+//!             log::error!("A request has failed: {}", error);
+//!         }
+//!         result
+//!     }
+//! }
+//!
+//! let bot = BotBuilder::new().token("TOKEN").build().layer(Logging);
+//! ```
+//!
+//! [`Bot`]: crate::Bot
+//! [`Bot::layer`]: crate::Bot::layer
+//! [`BotBuilder::parse_mode`]: crate::BotBuilder::parse_mode
+//! [`BotBuilder::retry_policy`]: crate::BotBuilder::retry_policy
+//! [`BotBuilder::throttle`]: crate::BotBuilder::throttle
+//! [`RequestMetrics`]: crate::metrics::RequestMetrics
+//! [`metrics`]: crate::metrics
+
+use std::{fmt, sync::Arc};
+
+use serde_json::{value::RawValue, Value};
+
+use crate::{
+    bot::Bot,
+    net,
+    requests::{Form, ResponseResult},
+    types::ChatId,
+};
+
+pub use parse_mode::DefaultParseMode;
+pub use retry::{ExponentialBackoff, Retry, RetryPolicy};
+pub use throttle::{Limits, Throttle};
+
+mod parse_mode;
+mod retry;
+mod throttle;
+
+/// A layer, which wraps requests sent by a bot.
+///
+/// See [the module-level documentation] for an example.
+///
+/// [the module-level documentation]: crate::layers
+#[async_trait::async_trait]
+pub trait Layer: Send + Sync + 'static {
+    /// Handles `request`.
+    ///
+    /// A layer sends a request further by [`Next::run`], possibly several
+    /// times or not at all.
+    async fn call(&self, request: RawRequest, next: Next<'_>) -> ResponseResult<Box<RawValue>>;
+}
+
+impl fmt::Debug for dyn Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Layer")
+    }
+}
+
+/// The rest of the layers of a bot, which a request is sent through.
+#[derive(Debug, Copy, Clone)]
+pub struct Next<'a> {
+    bot: &'a Bot,
+    layers: &'a [Arc<dyn Layer>],
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(bot: &'a Bot, layers: &'a [Arc<dyn Layer>]) -> Self {
+        Self { bot, layers }
+    }
+
+    /// Returns the bot, which sends a request.
+    #[must_use]
+    pub fn bot(&self) -> &'a Bot {
+        self.bot
+    }
+
+    /// Sends `request` through the rest of the layers to Telegram.
+    pub async fn run(self, request: RawRequest) -> ResponseResult<Box<RawValue>> {
+        match self.layers.split_first() {
+            Some((layer, layers)) => layer.call(request, Next { bot: self.bot, layers }).await,
+            None => net::send(self.bot, request).await,
+        }
+    }
+}
+
+/// A serialized request.
+#[derive(Debug, Clone)]
+pub struct RawRequest {
+    /// A name of a method, e.g. `sendMessage`.
+    pub method_name: &'static str,
+
+    /// Parameters of a request.
+    pub params: Params,
+}
+
+/// Parameters of a [`RawRequest`].
+#[derive(Debug, Clone)]
+pub enum Params {
+    /// Parameters of a request, which is sent as JSON.
+    Json(Value),
+
+    /// Parameters of a request, which is sent as `multipart/form-data`,
+    /// because it contains files.
+    Multipart(Form),
+}
+
+impl RawRequest {
+    #[must_use]
+    pub fn new(method_name: &'static str, params: Params) -> Self {
+        Self { method_name, params }
+    }
+
+    /// Returns a parameter named `name`.
+    ///
+    /// Text fields of multipart requests are parsed as JSON, if possible, or
+    /// returned as strings. Files are not returned.
+    #[must_use]
+    pub fn param(&self, name: &str) -> Option<Value> {
+        match &self.params {
+            Params::Json(params) => params.get(name).filter(|value| !value.is_null()).cloned(),
+            Params::Multipart(form) => form.text(name).map(|text| {
+                serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_owned()))
+            }),
+        }
+    }
+
+    /// Sets a parameter named `name` to `value`.
+    ///
+    /// Strings are put into multipart requests as is, other values are
+    /// serialized into JSON.
+    pub fn set_param(&mut self, name: &str, value: Value) {
+        match &mut self.params {
+            Params::Json(Value::Object(params)) => {
+                params.insert(name.to_owned(), value);
+            }
+            Params::Json(_) => log::error!("Parameters of {} are not an object", self.method_name),
+            Params::Multipart(form) => match value {
+                Value::String(text) => form.set_text(name, text),
+                value => form.set_text(name, value.to_string()),
+            },
+        }
+    }
+
+    /// Returns the chat this request is sent to, if any.
+    #[must_use]
+    pub fn chat_id(&self) -> Option<ChatId> {
+        match self.param("chat_id")? {
+            Value::Number(id) => id.as_i64().map(ChatId::Id),
+            Value::String(username) => Some(ChatId::ChannelUsername(username)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        requests::{Request, RequestWithFile},
+        testing::MockServer,
+        types::{InputFile, ParseMode},
+        BotBuilder,
+    };
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder {
+        requests: Mutex<Vec<(&'static str, Option<Value>)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Layer for Arc<Recorder> {
+        async fn call(&self, request: RawRequest, next: Next<'_>) -> ResponseResult<Box<RawValue>> {
+            let parse_mode = request.param("parse_mode");
+            self.requests.lock().unwrap().push((request.method_name, parse_mode));
+            next.run(request).await
+        }
+    }
+
+    #[tokio::test]
+    async fn default_parse_mode() {
+        let server = MockServer::start();
+        let bot = BotBuilder::new()
+            .token("TOKEN")
+            .api_url(server.url())
+            .parse_mode(ParseMode::HTML)
+            .build();
+
+        bot.send_message(1, "Hi").send().await.unwrap();
+        bot.send_message(1, "Hi").parse_mode(ParseMode::MarkdownV2).send().await.unwrap();
+        bot.send_photo(1, InputFile::memory("a.png", &b"..."[..])).send().await.unwrap().unwrap();
+        bot.send_poll(1, "?", vec!["a".to_owned(), "b".to_owned()]).send().await.unwrap();
+        bot.delete_message(1, 1).send().await.unwrap();
+
+        let params: Vec<_> = server.requests().into_iter().map(|req| req.params).collect();
+        assert_eq!(params[0]["parse_mode"], "HTML");
+        assert_eq!(params[1]["parse_mode"], "MarkdownV2");
+        assert_eq!(params[2]["parse_mode"], "HTML");
+        assert_eq!(params[3]["explanation_parse_mode"], "HTML");
+        assert_eq!(params[4].get("parse_mode"), None);
+    }
+
+    #[tokio::test]
+    async fn outer_layers_see_modified_requests() {
+        let server = MockServer::start();
+        let recorder = Arc::new(Recorder::default());
+        let bot = BotBuilder::new()
+            .token("TOKEN")
+            .api_url(server.url())
+            .parse_mode(ParseMode::HTML)
+            .build()
+            .layer(Arc::clone(&recorder));
+
+        bot.send_message(1, "Hi").send().await.unwrap();
+
+        // The recorder is applied before the default parse mode.
+        assert_eq!(*recorder.requests.lock().unwrap(), vec![("sendMessage", None)]);
+        assert_eq!(server.requests()[0].params["parse_mode"], "HTML");
+    }
+
+    #[test]
+    fn multipart_params() {
+        let mut request = RawRequest::new("sendPhoto", Params::Multipart(Form::default()));
+
+        request.set_param("chat_id", json!(-100));
+        request.set_param("caption", json!("Hi"));
+
+        assert_eq!(request.param("caption"), Some(json!("Hi")));
+        assert_eq!(request.chat_id(), Some(ChatId::Id(-100)));
+        assert_eq!(request.param("photo"), None);
+    }
+}
@@ -0,0 +1,54 @@
+use serde_json::{json, value::RawValue};
+
+use crate::{
+    layers::{Layer, Next, RawRequest},
+    requests::ResponseResult,
+    types::ParseMode,
+};
+
+/// A layer, which sets [`ParseMode`] of requests, if it isn't specified.
+///
+/// It's applied to the following methods:
+///  - `sendMessage`
+///  - `sendPhoto`
+///  - `sendAudio`
+///  - `sendDocument`
+///  - `sendVideo`
+///  - `sendAnimation`
+///  - `sendVoice`
+///  - `sendPoll` (`explanation_parse_mode`)
+///  - `editMessageText`
+///  - `editMessageCaption`
+///
+/// [`ParseMode`]: crate::types::ParseMode
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct DefaultParseMode {
+    parse_mode: ParseMode,
+}
+
+impl DefaultParseMode {
+    #[must_use]
+    pub fn new(parse_mode: ParseMode) -> Self {
+        Self { parse_mode }
+    }
+}
+
+#[async_trait::async_trait]
+impl Layer for DefaultParseMode {
+    async fn call(&self, mut request: RawRequest, next: Next<'_>) -> ResponseResult<Box<RawValue>> {
+        let param = match request.method_name {
+            "sendMessage" | "sendPhoto" | "sendAudio" | "sendDocument" | "sendVideo"
+            | "sendAnimation" | "sendVoice" | "editMessageText" | "editMessageCaption" => {
+                "parse_mode"
+            }
+            "sendPoll" => "explanation_parse_mode",
+            _ => return next.run(request).await,
+        };
+
+        if request.param(param).is_none() {
+            request.set_param(param, json!(self.parse_mode));
+        }
+
+        next.run(request).await
+    }
+}
@@ -0,0 +1,322 @@
+use std::time::Duration;
+
+use rand::Rng;
+use serde_json::value::RawValue;
+
+use crate::{
+    bot::sends_message,
+    layers::{Layer, Next, RawRequest},
+    requests::ResponseResult,
+    RequestError,
+};
+
+/// A policy of retrying failed requests.
+///
+/// This trait is used in [`Retry`] and [`BotBuilder::retry_policy`].
+///
+/// [`BotBuilder::retry_policy`]: crate::BotBuilder::retry_policy
+pub trait RetryPolicy: Send + Sync + 'static {
+    /// Returns a delay before resending a request named `method_name`, which
+    /// has failed with `error`, or `None` if the error must be returned to
+    /// the caller.
+    ///
+    /// `attempt` is the number of the failed attempt, starting from 1.
+    fn retry_after(
+        &self,
+        method_name: &str,
+        error: &RequestError,
+        attempt: u32,
+    ) -> Option<Duration>;
+}
+
+/// A layer, which resends failed requests according to [`RetryPolicy`].
+///
+/// See [`BotBuilder::retry_policy`].
+///
+/// [`BotBuilder::retry_policy`]: crate::BotBuilder::retry_policy
+#[derive(Debug, Clone)]
+pub struct Retry<P> {
+    policy: P,
+}
+
+impl<P> Retry<P>
+where
+    P: RetryPolicy,
+{
+    #[must_use]
+    pub fn new(policy: P) -> Self {
+        Self { policy }
+    }
+}
+
+#[async_trait::async_trait]
+impl<P> Layer for Retry<P>
+where
+    P: RetryPolicy,
+{
+    async fn call(&self, request: RawRequest, next: Next<'_>) -> ResponseResult<Box<RawValue>> {
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+
+            let error = match next.run(request.clone()).await {
+                Err(error) => error,
+                res => return res,
+            };
+
+            match self.policy.retry_after(request.method_name, &error, attempt) {
+                Some(delay) => {
+                    log::warn!(
+                        "Request {} has failed ({}), retrying in {:?}",
+                        request.method_name,
+                        error,
+                        delay
+                    );
+                    tokio::time::delay_for(delay).await;
+                }
+                None => return Err(error),
+            }
+        }
+    }
+}
+
+/// A retry policy with exponentially growing delays.
+///
+/// The delay before the `n`-th retry is `initial_delay * 2^(n - 1)`, but no
+/// more than `max_delay`. If jitter is enabled, a delay is randomly chosen
+/// from `[delay / 2, delay]`, so that many bots don't retry simultaneously.
+///
+/// The following errors are retried:
+///  - [`RequestError::NetworkError`], if [`retry_network_errors`] is enabled.
+///  - [`RequestError::ApiError`] with a 5xx status code, if
+///    [`retry_server_errors`] is enabled.
+///
+/// Requests which send messages (`send*`, except `sendChatAction`, and
+/// `forwardMessage`) are not idempotent: if such a request has reached
+/// Telegram, resending it can duplicate a message. So they are only retried
+/// if a connection to Telegram cannot be established, unless
+/// [`retry_non_idempotent`] is enabled.
+///
+/// [`RequestError::RetryAfter`] is never retried, use
+/// [`BotBuilder::throttle`] to handle it.
+///
+/// The default policy makes up to 3 attempts, starting with a 500ms delay up to
+/// 30s, with jitter, and retries both network and server errors.
+///
+/// [`retry_network_errors`]: ExponentialBackoff::retry_network_errors
+/// [`retry_server_errors`]: ExponentialBackoff::retry_server_errors
+/// [`retry_non_idempotent`]: ExponentialBackoff::retry_non_idempotent
+/// [`RequestError::NetworkError`]: crate::RequestError::NetworkError
+/// [`RequestError::ApiError`]: crate::RequestError::ApiError
+/// [`RequestError::RetryAfter`]: crate::RequestError::RetryAfter
+/// [`BotBuilder::throttle`]: crate::BotBuilder::throttle
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ExponentialBackoff {
+    max_attempts: u32,
+    initial_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+    retry_network_errors: bool,
+    retry_server_errors: bool,
+    retry_non_idempotent: bool,
+}
+
+impl ExponentialBackoff {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of attempts, including the first one.
+    ///
+    /// `1` disables retries.
+    #[must_use]
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Sets the delay before the first retry.
+    #[must_use]
+    pub fn initial_delay(mut self, initial_delay: Duration) -> Self {
+        self.initial_delay = initial_delay;
+        self
+    }
+
+    /// Sets the maximum delay between retries.
+    #[must_use]
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Enables or disables randomization of delays.
+    #[must_use]
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Specifies whether [`RequestError::NetworkError`] is retried.
+    ///
+    /// [`RequestError::NetworkError`]: crate::RequestError::NetworkError
+    #[must_use]
+    pub fn retry_network_errors(mut self, retry: bool) -> Self {
+        self.retry_network_errors = retry;
+        self
+    }
+
+    /// Specifies whether [`RequestError::ApiError`] with a 5xx status code is
+    /// retried.
+    ///
+    /// [`RequestError::ApiError`]: crate::RequestError::ApiError
+    #[must_use]
+    pub fn retry_server_errors(mut self, retry: bool) -> Self {
+        self.retry_server_errors = retry;
+        self
+    }
+
+    /// Specifies whether requests which send messages are retried after
+    /// errors, which may occur after Telegram has received a request.
+    ///
+    /// Enabling it can duplicate messages.
+    #[must_use]
+    pub fn retry_non_idempotent(mut self, retry: bool) -> Self {
+        self.retry_non_idempotent = retry;
+        self
+    }
+
+    fn is_retryable(&self, method_name: &str, error: &RequestError) -> bool {
+        let idempotent = self.retry_non_idempotent || !sends_message(method_name);
+
+        match error {
+            RequestError::NetworkError(error) if self.retry_network_errors => {
+                error.is_connect() || idempotent
+            }
+            RequestError::ApiError { status_code, .. } if self.retry_server_errors => {
+                status_code.is_server_error() && idempotent
+            }
+            _ => false,
+        }
+    }
+
+    fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let delay = self.initial_delay.checked_mul(factor).unwrap_or(self.max_delay);
+        let delay = delay.min(self.max_delay);
+
+        if self.jitter && delay > Duration::from_millis(1) {
+            let millis = delay.as_millis() as u64;
+            Duration::from_millis(rand::thread_rng().gen_range(millis / 2, millis + 1))
+        } else {
+            delay
+        }
+    }
+}
+
+impl Default for ExponentialBackoff {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+            retry_network_errors: true,
+            retry_server_errors: true,
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy for ExponentialBackoff {
+    fn retry_after(
+        &self,
+        method_name: &str,
+        error: &RequestError,
+        attempt: u32,
+    ) -> Option<Duration> {
+        if attempt < self.max_attempts && self.is_retryable(method_name, error) {
+            Some(self.delay(attempt))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ApiErrorKind;
+    use reqwest::StatusCode;
+
+    fn server_error() -> RequestError {
+        RequestError::ApiError {
+            status_code: StatusCode::BAD_GATEWAY,
+            kind: ApiErrorKind::Unknown("Bad Gateway".to_owned()),
+        }
+    }
+
+    #[test]
+    fn delays_grow_exponentially() {
+        let policy = ExponentialBackoff::new()
+            .max_attempts(10)
+            .initial_delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(5))
+            .jitter(false);
+
+        let delays: Vec<_> = (1..=4)
+            .map(|attempt| policy.retry_after("getMe", &server_error(), attempt).unwrap())
+            .collect();
+
+        assert_eq!(
+            delays,
+            [1, 2, 4, 5].iter().map(|s| Duration::from_secs(*s)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn jitter_halves_delays_at_most() {
+        let policy = ExponentialBackoff::new().initial_delay(Duration::from_secs(2));
+
+        for _ in 0..100 {
+            let delay = policy.retry_after("getMe", &server_error(), 1).unwrap();
+            assert!(delay >= Duration::from_secs(1) && delay <= Duration::from_secs(2));
+        }
+    }
+
+    #[test]
+    fn respects_max_attempts() {
+        let policy = ExponentialBackoff::new().max_attempts(2);
+
+        assert!(policy.retry_after("getMe", &server_error(), 1).is_some());
+        assert!(policy.retry_after("getMe", &server_error(), 2).is_none());
+    }
+
+    #[test]
+    fn does_not_retry_non_idempotent_requests() {
+        let policy = ExponentialBackoff::new();
+
+        assert!(policy.retry_after("sendMessage", &server_error(), 1).is_none());
+        assert!(policy
+            .retry_non_idempotent(true)
+            .retry_after("sendMessage", &server_error(), 1)
+            .is_some());
+    }
+
+    #[test]
+    fn retries_only_transient_errors() {
+        let policy = ExponentialBackoff::new();
+        let bad_request = RequestError::ApiError {
+            status_code: StatusCode::BAD_REQUEST,
+            kind: ApiErrorKind::Unknown("Bad Request".to_owned()),
+        };
+
+        assert!(policy.retry_after("getMe", &bad_request, 1).is_none());
+        assert!(policy.retry_after("getMe", &RequestError::RetryAfter(1), 1).is_none());
+        assert!(policy
+            .retry_server_errors(false)
+            .retry_after("getMe", &server_error(), 1)
+            .is_none());
+    }
+}
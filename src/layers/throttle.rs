@@ -0,0 +1,327 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde_json::value::RawValue;
+
+use crate::{
+    bot::sends_message,
+    layers::{Layer, Next, RawRequest},
+    requests::ResponseResult,
+    types::ChatId,
+    RequestError,
+};
+
+const SECOND: Duration = Duration::from_secs(1);
+const MINUTE: Duration = Duration::from_secs(60);
+
+/// The maximum number of attempts to send a request, if Telegram keeps
+/// responding with [`RequestError::RetryAfter`].
+///
+/// [`RequestError::RetryAfter`]: crate::RequestError::RetryAfter
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Telegram request limits.
+///
+/// This struct is used in [`Throttle`] and [`BotBuilder::throttle`].
+///
+/// The default limits are the ones described in the [Telegram FAQ]: one
+/// message per second in a single chat, 20 messages per minute in a single
+/// group and 30 messages per second overall.
+///
+/// [`BotBuilder::throttle`]: crate::BotBuilder::throttle
+/// [Telegram FAQ]: https://core.telegram.org/bots/faq#my-bot-is-hitting-limits-how-do-i-avoid-this
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Limits {
+    /// Allowed messages in one chat per second.
+    pub chat_s: u32,
+
+    /// Allowed messages per second.
+    pub overall_s: u32,
+
+    /// Allowed messages in one group per minute.
+    ///
+    /// It's applied to groups, supergroups and channels (i.e. chats with
+    /// negative IDs or usernames), but not to private chats.
+    pub chat_m: u32,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self { chat_s: 1, overall_s: 30, chat_m: 20 }
+    }
+}
+
+/// A layer, which delays requests to fit into [`Limits`].
+///
+/// Requests to the same chat are sent in the order they were made.
+///
+/// See [`BotBuilder::throttle`].
+///
+/// [`BotBuilder::throttle`]: crate::BotBuilder::throttle
+#[derive(Debug)]
+pub struct Throttle {
+    limits: Limits,
+    state: Mutex<State>,
+    chats: Mutex<HashMap<ChatId, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Requests sent during the last minute, the oldest ones first.
+    history: VecDeque<(Option<ChatId>, Instant)>,
+
+    /// All requests are delayed until this instant, because of
+    /// [`RequestError::RetryAfter`].
+    frozen_until: Option<Instant>,
+}
+
+impl Throttle {
+    #[must_use]
+    pub fn new(limits: Limits) -> Self {
+        Self { limits, state: Mutex::default(), chats: Mutex::default() }
+    }
+
+    /// Sends a request to `chat_id` via `send` as soon as [`Limits`] allow.
+    ///
+    /// If Telegram responds with [`RequestError::RetryAfter`], all the
+    /// requests are delayed for the specified amount of time and the request
+    /// is resent, up to [`MAX_ATTEMPTS`] times in total. After that, the error
+    /// is returned.
+    async fn send<T, F, Fut>(&self, chat_id: Option<ChatId>, mut send: F) -> ResponseResult<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = ResponseResult<T>>,
+    {
+        let mut attempt = 1;
+
+        loop {
+            self.acquire(chat_id.as_ref()).await;
+
+            let res = send().await;
+            if let Err(RequestError::RetryAfter(secs)) = res {
+                self.freeze(Duration::from_secs(secs.max(0) as u64));
+
+                if attempt < MAX_ATTEMPTS {
+                    log::warn!("Flood control is exceeded, retrying after {} seconds", secs);
+                    attempt += 1;
+                    continue;
+                }
+            }
+
+            return res;
+        }
+    }
+
+    /// Waits until a request to `chat_id` can be sent.
+    async fn acquire(&self, chat_id: Option<&ChatId>) {
+        let chat_id = match chat_id {
+            Some(chat_id) => chat_id,
+            None => return self.wait_for_slot(None).await,
+        };
+
+        let queue = {
+            let mut chats = self.chats.lock().unwrap();
+            Arc::clone(chats.entry(chat_id.clone()).or_default())
+        };
+
+        // `tokio::sync::Mutex` is fair, so requests to the same chat are sent in
+        // order.
+        let guard = queue.lock().await;
+        self.wait_for_slot(Some(chat_id)).await;
+        drop(guard);
+
+        let mut chats = self.chats.lock().unwrap();
+        // Nobody else waits for this chat, since new waiters can only appear
+        // under the `chats` lock.
+        if Arc::strong_count(&queue) == 2 {
+            chats.remove(chat_id);
+        }
+    }
+
+    async fn wait_for_slot(&self, chat_id: Option<&ChatId>) {
+        loop {
+            let delay = {
+                let mut state = self.state.lock().unwrap();
+                let now = Instant::now();
+
+                match state.delay(&self.limits, chat_id, now) {
+                    Some(delay) => delay,
+                    None => {
+                        state.history.push_back((chat_id.cloned(), now));
+                        return;
+                    }
+                }
+            };
+
+            tokio::time::delay_for(delay).await;
+        }
+    }
+
+    fn freeze(&self, duration: Duration) {
+        let mut state = self.state.lock().unwrap();
+        let until = Instant::now() + duration;
+
+        state.frozen_until = Some(state.frozen_until.map_or(until, |frozen| frozen.max(until)));
+    }
+}
+
+#[async_trait::async_trait]
+impl Layer for Throttle {
+    async fn call(&self, request: RawRequest, next: Next<'_>) -> ResponseResult<Box<RawValue>> {
+        if !sends_message(request.method_name) {
+            return next.run(request).await;
+        }
+
+        let chat_id = request.chat_id();
+        self.send(chat_id, || next.run(request.clone())).await
+    }
+}
+
+impl State {
+    /// Returns how long a request to `chat_id` must wait or `None` if it can
+    /// be sent right now.
+    fn delay(
+        &mut self,
+        limits: &Limits,
+        chat_id: Option<&ChatId>,
+        now: Instant,
+    ) -> Option<Duration> {
+        while let Some((_, sent)) = self.history.front() {
+            if now - *sent < MINUTE {
+                break;
+            }
+            self.history.pop_front();
+        }
+
+        let mut delay = self.frozen_until.filter(|until| *until > now).map(|until| until - now);
+        let mut wait = |oldest: Option<&Instant>, window: Duration| {
+            if let Some(oldest) = oldest {
+                let wait = window - (now - *oldest);
+                delay = Some(delay.map_or(wait, |delay| delay.max(wait)));
+            }
+        };
+
+        let last_second = self.history.iter().filter(|(_, sent)| now - *sent < SECOND);
+        wait(nth_oldest(last_second.map(|(_, sent)| sent), limits.overall_s), SECOND);
+
+        if let Some(chat_id) = chat_id {
+            let in_chat = || {
+                self.history
+                    .iter()
+                    .filter(move |(id, _)| id.as_ref() == Some(chat_id))
+                    .map(|(_, sent)| sent)
+            };
+
+            wait(nth_oldest(in_chat().filter(|sent| now - **sent < SECOND), limits.chat_s), SECOND);
+            if is_group(chat_id) {
+                wait(nth_oldest(in_chat(), limits.chat_m), MINUTE);
+            }
+        }
+
+        delay
+    }
+}
+
+/// Returns `true` if `chat_id` is a group, a supergroup or a channel.
+fn is_group(chat_id: &ChatId) -> bool {
+    match chat_id {
+        ChatId::Id(id) => *id < 0,
+        ChatId::ChannelUsername(_) => true,
+    }
+}
+
+/// Returns the instant after which a new request fits into `limit`, if there
+/// are already `limit` or more requests in `sent` (sorted from the oldest).
+fn nth_oldest<'a, I>(sent: I, limit: u32) -> Option<&'a Instant>
+where
+    I: DoubleEndedIterator<Item = &'a Instant>,
+{
+    sent.rev().nth(limit.max(1) as usize - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delays_requests_over_limits() {
+        let limits = Limits { chat_s: 1, overall_s: 2, chat_m: 3 };
+        let mut state = State::default();
+        let now = Instant::now();
+        let chat = ChatId::Id(1);
+
+        state.history.push_back((Some(chat.clone()), now - Duration::from_millis(1500)));
+        assert_eq!(state.delay(&limits, Some(&chat), now), None);
+
+        // One message per second in a chat.
+        state.history.push_back((Some(chat.clone()), now - Duration::from_millis(300)));
+        assert_eq!(state.delay(&limits, Some(&chat), now), Some(Duration::from_millis(700)));
+        assert_eq!(state.delay(&limits, Some(&ChatId::Id(2)), now), None);
+
+        // Two messages per second overall.
+        state.history.push_back((None, now - Duration::from_millis(100)));
+        assert_eq!(
+            state.delay(&limits, Some(&ChatId::Id(2)), now),
+            Some(Duration::from_millis(700))
+        );
+
+        // Three messages per minute in a group, but not in a private chat.
+        let mut state = State::default();
+        let group = ChatId::Id(-1);
+        for secs in &[50, 40, 30] {
+            state.history.push_back((Some(group.clone()), now - Duration::from_secs(*secs)));
+            state.history.push_back((Some(chat.clone()), now - Duration::from_secs(*secs)));
+        }
+        assert_eq!(state.delay(&limits, Some(&group), now), Some(Duration::from_secs(10)));
+        assert_eq!(state.delay(&limits, Some(&chat), now), None);
+        assert_eq!(state.delay(&limits, None, now), None);
+    }
+
+    #[test]
+    fn retry_after_freezes_all_requests() {
+        let throttle = Throttle::new(Limits::default());
+        throttle.freeze(Duration::from_secs(5));
+
+        let mut state = throttle.state.lock().unwrap();
+        let delay = state.delay(&throttle.limits, None, Instant::now()).unwrap();
+        assert!(delay > Duration::from_secs(4) && delay <= Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn resends_on_retry_after() {
+        let throttle = Throttle::new(Limits { chat_s: 2, ..Limits::default() });
+        let mut attempts = 0;
+
+        let res = throttle
+            .send(Some(ChatId::Id(1)), || {
+                attempts += 1;
+                let res =
+                    if attempts == 1 { Err(RequestError::RetryAfter(0)) } else { Ok(attempts) };
+                async move { res }
+            })
+            .await;
+
+        assert_eq!(res.unwrap(), 2);
+        assert!(throttle.chats.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let throttle = Throttle::new(Limits { chat_s: 10, ..Limits::default() });
+        let mut attempts = 0;
+
+        let res: ResponseResult<()> = throttle
+            .send(Some(ChatId::Id(1)), || {
+                attempts += 1;
+                async { Err(RequestError::RetryAfter(0)) }
+            })
+            .await;
+
+        assert!(matches!(res, Err(RequestError::RetryAfter(0))));
+        assert_eq!(attempts, MAX_ATTEMPTS);
+    }
+}
@@ -43,7 +43,8 @@
 
 pub use bot::{Bot, BotBuilder};
 pub use dispatching::repls::{
-    commands_repl, commands_repl_with_listener, dialogues_repl, dialogues_repl_with_listener, repl,
+    commands_repl, commands_repl_with_dependencies, commands_repl_with_listener, dialogues_repl,
+    dialogues_repl_with_dependencies, dialogues_repl_with_listener, repl, repl_with_dependencies,
     repl_with_listener,
 };
 pub use errors::{ApiErrorKind, DownloadError, KnownApiErrorKind, RequestError};
@@ -54,9 +55,16 @@ mod net;
 mod bot;
 pub mod dispatching;
 pub mod error_handlers;
+pub mod layers;
 mod logging;
+pub mod metrics;
 pub mod prelude;
 pub mod requests;
+pub mod scheduling;
+#[cfg(feature = "tracing-spans")]
+mod spans;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod types;
 pub mod utils;
 
@@ -0,0 +1,214 @@
+//! Metrics of updates, handlers and requests.
+//!
+//! Metrics are passed to a [`Recorder`]: either the built-in [`Prometheus`]
+//! recorder, which exposes them in the Prometheus text format, or your own
+//! one, e.g. an adapter to another monitoring system.
+//!
+//! - Updates are recorded by [`Dispatcher`] with a recorder set via
+//!   [`Dispatcher::metrics`]: the number of updates of every kind, the time of
+//!   handling of every update and the depths of the queues of the handlers.
+//!   The queues are named after the kinds of updates, except for the per-chat
+//!   queues of [`DialogueDispatcher`], which are summed up as `dialogues`.
+//! - Requests are recorded by the [`RequestMetrics`] layer: the number of
+//!   requests by methods, their latency and errors.
+//!
+//! The time of handling of an update is recorded after it has been handled,
+//! see [`Completion`]. An update is considered failed if [`Router`]'s
+//! endpoint or a REPL's handler has returned an error, or a handler has
+//! panicked.
+//!
+//! ## Example
+//! ```no_run
+//! use std::sync::Arc;
+//! use teloxide::{
+//!     metrics::{Prometheus, RequestMetrics},
+//!     prelude::*,
+//! };
+//!
+//! # async fn run() {
+//! let metrics = Arc::new(Prometheus::new());
+//! // Serve the metrics on `http://0.0.0.0:9090/metrics`.
+//! let _server = metrics.serve(([0, 0, 0, 0], 9090).into()).unwrap();
+//!
+//! let bot = Bot::from_env().layer(RequestMetrics::new(Arc::clone(&metrics)));
+//!
+//! Dispatcher::new(bot)
+//!     .metrics(metrics)
+//!     .messages_handler(|rx: DispatcherHandlerRx<Message>| {
+//!         rx.for_each(|message| async move {
+//!             message.answer_str("pong").await.log_on_error().await;
+//!         })
+//!     })
+//!     .dispatch()
+//!     .await;
+//! # }
+//! ```
+//!
+//! [`Recorder`]: crate::metrics::Recorder
+//! [`Prometheus`]: crate::metrics::Prometheus
+//! [`RequestMetrics`]: crate::metrics::RequestMetrics
+//! [`Dispatcher`]: crate::dispatching::Dispatcher
+//! [`Dispatcher::metrics`]: crate::dispatching::Dispatcher::metrics
+//! [`DialogueDispatcher`]: crate::dispatching::dialogue::DialogueDispatcher
+//! [`Completion`]: crate::dispatching::Completion
+//! [`Router`]: crate::dispatching::Router
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use serde_json::value::RawValue;
+
+use crate::{
+    layers::{Layer, Next, RawRequest},
+    requests::ResponseResult,
+    RequestError,
+};
+
+pub use prometheus::Prometheus;
+
+mod prometheus;
+
+/// A recorder of metrics.
+///
+/// All the methods do nothing by default.
+pub trait Recorder: Send + Sync + 'static {
+    /// An update of `kind` (e.g. `message`) has been received.
+    fn record_update(&self, _kind: &'static str) {}
+
+    /// An update of `kind` has been handled in `duration`.
+    ///
+    /// `failed` is `true` if a handler has returned an error or panicked.
+    fn record_handling(&self, _kind: &'static str, _duration: Duration, _failed: bool) {}
+
+    /// `depth` updates are in `queue` (e.g. `message`) after an update has
+    /// been pushed to it.
+    fn record_queue_depth(&self, _queue: &'static str, _depth: usize) {}
+
+    /// A request to `method` has been completed in `duration`.
+    ///
+    /// `error` is a kind of [`RequestError`] (e.g. `network_error`) if the
+    /// request has failed.
+    ///
+    /// [`RequestError`]: crate::RequestError
+    fn record_request(
+        &self,
+        _method: &'static str,
+        _duration: Duration,
+        _error: Option<&'static str>,
+    ) {
+    }
+}
+
+/// A recorder, stored in [`Dependencies`] of [`Dispatcher`].
+///
+/// [`Dependencies`]: crate::dispatching::Dependencies
+/// [`Dispatcher`]: crate::dispatching::Dispatcher
+#[derive(Clone)]
+pub(crate) struct Metrics(pub(crate) Arc<dyn Recorder>);
+
+impl Metrics {
+    /// Returns a guard, which records handling of an update of `kind` on
+    /// drop.
+    pub(crate) fn handling(&self, kind: &'static str) -> HandlingGuard {
+        HandlingGuard {
+            recorder: Arc::clone(&self.0),
+            kind,
+            start: Instant::now(),
+            failed: AtomicBool::new(false),
+        }
+    }
+}
+
+impl fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Metrics")
+    }
+}
+
+/// Records handling of an update on drop.
+pub(crate) struct HandlingGuard {
+    recorder: Arc<dyn Recorder>,
+    kind: &'static str,
+    start: Instant,
+    failed: AtomicBool,
+}
+
+impl HandlingGuard {
+    /// Marks the update as failed.
+    pub(crate) fn fail(&self) {
+        self.failed.store(true, Ordering::SeqCst);
+    }
+
+    /// Records the depth of the queue, which the update has been pushed to.
+    pub(crate) fn record_queue_depth(&self, depth: usize) {
+        self.recorder.record_queue_depth(self.kind, depth);
+    }
+}
+
+impl Drop for HandlingGuard {
+    fn drop(&mut self) {
+        let failed = self.failed.load(Ordering::SeqCst);
+        self.recorder.record_handling(self.kind, self.start.elapsed(), failed);
+    }
+}
+
+/// Returns a name of the variant of `error`.
+fn request_error(error: &RequestError) -> &'static str {
+    match error {
+        RequestError::ApiError { .. } => "api_error",
+        RequestError::MigrateToChatId(_) => "migrate_to_chat_id",
+        RequestError::RetryAfter(_) => "retry_after",
+        RequestError::NetworkError(_) => "network_error",
+        RequestError::InvalidJson(_) => "invalid_json",
+        RequestError::Io(_) => "io",
+    }
+}
+
+/// A layer, which records requests to a [`Recorder`].
+///
+/// Since a layer added later wraps the previous ones, retries and throttling
+/// (see [`BotBuilder`]) are included into the latency of a request if this
+/// layer is added via [`Bot::layer`].
+///
+/// [`Recorder`]: crate::metrics::Recorder
+/// [`BotBuilder`]: crate::BotBuilder
+/// [`Bot::layer`]: crate::Bot::layer
+pub struct RequestMetrics {
+    recorder: Arc<dyn Recorder>,
+}
+
+impl RequestMetrics {
+    #[must_use]
+    pub fn new<R>(recorder: Arc<R>) -> Self
+    where
+        R: Recorder,
+    {
+        Self { recorder }
+    }
+}
+
+impl fmt::Debug for RequestMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RequestMetrics")
+    }
+}
+
+#[async_trait::async_trait]
+impl Layer for RequestMetrics {
+    async fn call(&self, request: RawRequest, next: Next<'_>) -> ResponseResult<Box<RawValue>> {
+        let method = request.method_name;
+        let start = Instant::now();
+
+        let result = next.run(request).await;
+        let error = result.as_ref().err().map(request_error);
+        self.recorder.record_request(method, start.elapsed(), error);
+
+        result
+    }
+}
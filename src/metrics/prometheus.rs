@@ -0,0 +1,277 @@
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    fmt::Write,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use hyper::{
+    header,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+
+use crate::{dispatching::StopToken, metrics::Recorder};
+
+/// Upper bounds of the buckets of histograms, in seconds.
+const BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Label names and values of a metric.
+type Labels = Vec<(&'static str, &'static str)>;
+
+/// A recorder, which exposes metrics in the [Prometheus text format].
+///
+/// The following metrics are exposed:
+///  - `teloxide_updates_total{kind}`, a counter of received updates.
+///  - `teloxide_update_handling_seconds{kind}`, a histogram of the time of
+///    handling of updates.
+///  - `teloxide_handler_failures_total{kind}`, a counter of updates, handlers
+///    of which have failed.
+///  - `teloxide_queue_depth{queue}`, a gauge of updates waiting in the queues
+///    of handlers.
+///  - `teloxide_requests_total{method}`, a counter of requests to the Bot API.
+///  - `teloxide_request_errors_total{method, error}`, a counter of failed
+///    requests.
+///  - `teloxide_request_duration_seconds{method}`, a histogram of the latency
+///    of requests.
+///
+/// [Prometheus text format]: https://prometheus.io/docs/instrumenting/exposition_formats/
+#[derive(Debug, Default)]
+pub struct Prometheus {
+    metrics: Mutex<Metrics>,
+}
+
+#[derive(Debug, Default)]
+struct Metrics {
+    counters: BTreeMap<&'static str, BTreeMap<Labels, u64>>,
+    gauges: BTreeMap<&'static str, BTreeMap<Labels, u64>>,
+    histograms: BTreeMap<&'static str, BTreeMap<Labels, Histogram>>,
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Prometheus {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Renders the metrics in the Prometheus text format.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let metrics = self.metrics.lock().unwrap();
+        let mut out = String::new();
+
+        for (name, series) in &metrics.counters {
+            writeln!(out, "# TYPE {} counter", name).unwrap();
+            for (labels, value) in series {
+                writeln!(out, "{}{} {}", name, render_labels(labels, None), value).unwrap();
+            }
+        }
+
+        for (name, series) in &metrics.gauges {
+            writeln!(out, "# TYPE {} gauge", name).unwrap();
+            for (labels, value) in series {
+                writeln!(out, "{}{} {}", name, render_labels(labels, None), value).unwrap();
+            }
+        }
+
+        for (name, series) in &metrics.histograms {
+            writeln!(out, "# TYPE {} histogram", name).unwrap();
+            for (labels, histogram) in series {
+                let mut cumulative = 0;
+                for (bound, count) in BUCKETS.iter().zip(&histogram.buckets) {
+                    cumulative += count;
+                    let le = bound.to_string();
+                    writeln!(
+                        out,
+                        "{}_bucket{} {}",
+                        name,
+                        render_labels(labels, Some(&le)),
+                        cumulative
+                    )
+                    .unwrap();
+                }
+                writeln!(
+                    out,
+                    "{}_bucket{} {}",
+                    name,
+                    render_labels(labels, Some("+Inf")),
+                    histogram.count
+                )
+                .unwrap();
+                writeln!(out, "{}_sum{} {}", name, render_labels(labels, None), histogram.sum)
+                    .unwrap();
+                writeln!(out, "{}_count{} {}", name, render_labels(labels, None), histogram.count)
+                    .unwrap();
+            }
+        }
+
+        out
+    }
+
+    /// Binds an HTTP server to `address`, which serves the metrics on
+    /// `GET /metrics`.
+    ///
+    /// The server is gracefully shut down after the returned token is
+    /// stopped.
+    ///
+    /// # Panics
+    /// If it's called outside of the Tokio runtime.
+    pub fn serve(self: &Arc<Self>, address: SocketAddr) -> Result<StopToken, hyper::Error> {
+        let builder = Server::try_bind(&address)?;
+        let stop = StopToken::new();
+
+        let server = builder
+            .serve(make_service_fn({
+                let this = Arc::clone(self);
+
+                move |_| {
+                    let this = Arc::clone(&this);
+
+                    async move {
+                        Ok::<_, Infallible>(service_fn(move |req| {
+                            let response = this.respond(&req);
+                            async move { Ok::<_, Infallible>(response) }
+                        }))
+                    }
+                }
+            }))
+            .with_graceful_shutdown(stop.stopped());
+
+        tokio::spawn(async move {
+            if let Err(error) = server.await {
+                log::error!("The metrics server has failed: {}", error);
+            }
+        });
+
+        Ok(stop)
+    }
+
+    fn respond(&self, req: &Request<Body>) -> Response<Body> {
+        let mut response = Response::new(Body::empty());
+
+        if req.method() != Method::GET || req.uri().path() != "/metrics" {
+            *response.status_mut() = StatusCode::NOT_FOUND;
+            return response;
+        }
+
+        *response.body_mut() = Body::from(self.render());
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("text/plain; version=0.0.4"),
+        );
+        response
+    }
+
+    fn increment(&self, name: &'static str, labels: Labels) {
+        let mut metrics = self.metrics.lock().unwrap();
+        *metrics.counters.entry(name).or_default().entry(labels).or_default() += 1;
+    }
+
+    fn set(&self, name: &'static str, labels: Labels, value: u64) {
+        let mut metrics = self.metrics.lock().unwrap();
+        metrics.gauges.entry(name).or_default().insert(labels, value);
+    }
+
+    fn observe(&self, name: &'static str, labels: Labels, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+
+        let mut metrics = self.metrics.lock().unwrap();
+        let histogram = metrics.histograms.entry(name).or_default().entry(labels).or_default();
+
+        if let Some(i) = BUCKETS.iter().position(|&bound| seconds <= bound) {
+            histogram.buckets[i] += 1;
+        }
+        histogram.sum += seconds;
+        histogram.count += 1;
+    }
+}
+
+impl Recorder for Prometheus {
+    fn record_update(&self, kind: &'static str) {
+        self.increment("teloxide_updates_total", vec![("kind", kind)]);
+    }
+
+    fn record_handling(&self, kind: &'static str, duration: Duration, failed: bool) {
+        self.observe("teloxide_update_handling_seconds", vec![("kind", kind)], duration);
+        if failed {
+            self.increment("teloxide_handler_failures_total", vec![("kind", kind)]);
+        }
+    }
+
+    fn record_queue_depth(&self, queue: &'static str, depth: usize) {
+        self.set("teloxide_queue_depth", vec![("queue", queue)], depth as u64);
+    }
+
+    fn record_request(
+        &self,
+        method: &'static str,
+        duration: Duration,
+        error: Option<&'static str>,
+    ) {
+        self.increment("teloxide_requests_total", vec![("method", method)]);
+        self.observe("teloxide_request_duration_seconds", vec![("method", method)], duration);
+        if let Some(error) = error {
+            self.increment(
+                "teloxide_request_errors_total",
+                vec![("method", method), ("error", error)],
+            );
+        }
+    }
+}
+
+fn render_labels(labels: &[(&'static str, &'static str)], le: Option<&str>) -> String {
+    let mut pairs: Vec<_> =
+        labels.iter().map(|(name, value)| format!("{}=\"{}\"", name, escape(value))).collect();
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{}\"", le));
+    }
+
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_metrics() {
+        let metrics = Prometheus::new();
+        metrics.record_update("message");
+        metrics.record_update("message");
+        metrics.record_queue_depth("message", 3);
+        metrics.record_request("sendMessage", Duration::from_millis(20), Some("network_error"));
+
+        let rendered = metrics.render();
+        assert!(rendered.contains("# TYPE teloxide_updates_total counter\n"));
+        assert!(rendered.contains("teloxide_updates_total{kind=\"message\"} 2\n"));
+        assert!(rendered.contains("teloxide_queue_depth{queue=\"message\"} 3\n"));
+        assert!(rendered.contains(
+            "teloxide_request_errors_total{method=\"sendMessage\",error=\"network_error\"} 1\n"
+        ));
+        assert!(rendered.contains(
+            "teloxide_request_duration_seconds_bucket{method=\"sendMessage\",le=\"0.01\"} 0\n"
+        ));
+        assert!(rendered.contains(
+            "teloxide_request_duration_seconds_bucket{method=\"sendMessage\",le=\"0.025\"} 1\n"
+        ));
+        assert!(rendered
+            .contains("teloxide_request_duration_seconds_count{method=\"sendMessage\"} 1\n"));
+    }
+}
@@ -1,21 +1,18 @@
-use reqwest::Client;
 use tokio::io::{AsyncWrite, AsyncWriteExt};
 
-use crate::errors::DownloadError;
-
-use super::TELEGRAM_API_URL;
+use crate::{bot::Bot, errors::DownloadError};
 
 pub async fn download_file<D>(
-    client: &Client,
-    token: &str,
+    bot: &Bot,
     path: &str,
     destination: &mut D,
 ) -> Result<(), DownloadError>
 where
     D: AsyncWrite + Unpin,
 {
-    let mut res = client
-        .get(&super::file_url(TELEGRAM_API_URL, token, path))
+    let mut res = bot
+        .client()
+        .get(&super::file_url(bot.api_url().as_str(), bot.token(), path))
         .send()
         .await?
         .error_for_status()?;
@@ -29,12 +26,12 @@ where
 
 #[cfg(feature = "unstable-stream")]
 pub async fn download_file_stream(
-    client: &Client,
-    token: &str,
+    bot: &Bot,
     path: &str,
 ) -> Result<impl Stream<Item = reqwest::Result<Bytes>>, reqwest::Error> {
-    let res = client
-        .get(&super::file_url(TELEGRAM_API_URL, token, path))
+    let res = bot
+        .client()
+        .get(&super::file_url(bot.api_url().as_str(), bot.token(), path))
         .send()
         .await?
         .error_for_status()?;
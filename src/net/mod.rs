@@ -3,7 +3,7 @@ pub use download::download_file_stream;
 
 pub use self::{
     download::download_file,
-    request::{request_json, request_multipart},
+    request::{request_json, request_multipart, send},
     telegram_response::TelegramResponse,
 };
 
@@ -11,12 +11,13 @@ mod download;
 mod request;
 mod telegram_response;
 
-const TELEGRAM_API_URL: &str = "https://api.telegram.org";
+pub(crate) const TELEGRAM_API_URL: &str = "https://api.telegram.org";
 
 /// Creates URL for making HTTPS requests. See the [Telegram documentation].
 ///
 /// [Telegram documentation]: https://core.telegram.org/bots/api#making-requests
 fn method_url(base: &str, token: &str, method_name: &str) -> String {
+    let base = base.trim_end_matches('/');
     format!("{url}/bot{token}/{method}", url = base, token = token, method = method_name,)
 }
 
@@ -24,6 +25,7 @@ fn method_url(base: &str, token: &str, method_name: &str) -> String {
 ///
 /// [Telegram documentation]: https://core.telegram.org/bots/api#file
 fn file_url(base: &str, token: &str, file_path: &str) -> String {
+    let base = base.trim_end_matches('/');
     format!("{url}/file/bot{token}/{file}", url = base, token = token, file = file_path,)
 }
 
@@ -58,4 +60,13 @@ mod tests {
             "https://api.telegram.org/file/bot535362388:AAF7-g0gYncWnm5IyfZlpPRqRRv6kNAGlao/AgADAgADyqoxG2g8aEsu_KjjVsGF4-zetw8ABAEAAwIAA20AA_8QAwABFgQ"
         );
     }
+
+    #[test]
+    fn custom_api_url_test() {
+        let url = method_url("http://127.0.0.1:8081/", "TOKEN", "getMe");
+        assert_eq!(url, "http://127.0.0.1:8081/botTOKEN/getMe");
+
+        let url = file_url("https://example.com/telegram", "TOKEN", "photos/file_1.jpg");
+        assert_eq!(url, "https://example.com/telegram/file/botTOKEN/photos/file_1.jpg");
+    }
 }
@@ -1,48 +1,74 @@
-use reqwest::{multipart::Form, Client, Response};
+use reqwest::Response;
 use serde::{de::DeserializeOwned, Serialize};
+use serde_json::value::RawValue;
 
-use crate::{requests::ResponseResult, RequestError};
+use crate::{
+    bot::Bot,
+    layers::{Params, RawRequest},
+    requests::{Form, ResponseResult},
+    ApiErrorKind, RequestError,
+};
 
-use super::{TelegramResponse, TELEGRAM_API_URL};
-use std::time::Duration;
-
-const DELAY_ON_SERVER_ERROR: Duration = Duration::from_secs(10);
+use super::TelegramResponse;
 
 pub async fn request_multipart<T>(
-    client: &Client,
-    token: &str,
-    method_name: &str,
+    bot: &Bot,
+    method_name: &'static str,
     params: Form,
 ) -> ResponseResult<T>
 where
     T: DeserializeOwned,
 {
-    let response = client
-        .post(&super::method_url(TELEGRAM_API_URL, token, method_name))
-        .multipart(params)
-        .send()
-        .await
-        .map_err(RequestError::NetworkError)?;
-
-    process_response(response).await
+    execute(bot, RawRequest::new(method_name, Params::Multipart(params))).await
 }
 
 pub async fn request_json<T, P>(
-    client: &Client,
-    token: &str,
-    method_name: &str,
+    bot: &Bot,
+    method_name: &'static str,
     params: &P,
 ) -> ResponseResult<T>
 where
     T: DeserializeOwned,
     P: Serialize,
 {
-    let response = client
-        .post(&super::method_url(TELEGRAM_API_URL, token, method_name))
-        .json(params)
-        .send()
-        .await
-        .map_err(RequestError::NetworkError)?;
+    let params = serde_json::to_value(params).map_err(RequestError::InvalidJson)?;
+    execute(bot, RawRequest::new(method_name, Params::Json(params))).await
+}
+
+async fn execute<T>(bot: &Bot, request: RawRequest) -> ResponseResult<T>
+where
+    T: DeserializeOwned,
+{
+    #[cfg(feature = "tracing-spans")]
+    let method_name = request.method_name;
+
+    let fut = execute_inner(bot, request);
+    #[cfg(feature = "tracing-spans")]
+    let fut = crate::spans::request(method_name, fut);
+
+    fut.await
+}
+
+async fn execute_inner<T>(bot: &Bot, request: RawRequest) -> ResponseResult<T>
+where
+    T: DeserializeOwned,
+{
+    let result = bot.execute(request).await?;
+    serde_json::from_str(result.get()).map_err(RequestError::InvalidJson)
+}
+
+/// Sends `request` to Telegram, bypassing the layers of `bot`.
+pub async fn send(bot: &Bot, request: RawRequest) -> ResponseResult<Box<RawValue>> {
+    let url = super::method_url(bot.api_url().as_str(), bot.token(), request.method_name);
+    let builder = bot.client().post(&url);
+
+    let builder = match &request.params {
+        Params::Json(params) => builder.json(params),
+        Params::Multipart(params) => {
+            builder.multipart(params.to_multipart().await.map_err(RequestError::Io)?)
+        }
+    };
+    let response = builder.send().await.map_err(RequestError::NetworkError)?;
 
     process_response(response).await
 }
@@ -51,13 +77,18 @@ async fn process_response<T>(response: Response) -> ResponseResult<T>
 where
     T: DeserializeOwned,
 {
-    if response.status().is_server_error() {
-        tokio::time::delay_for(DELAY_ON_SERVER_ERROR).await;
-    }
+    let status_code = response.status();
+    #[cfg(feature = "tracing-spans")]
+    crate::spans::record_status(status_code);
 
-    serde_json::from_str::<TelegramResponse<T>>(
-        &response.text().await.map_err(RequestError::NetworkError)?,
-    )
-    .map_err(RequestError::InvalidJson)?
-    .into()
+    let text = response.text().await.map_err(RequestError::NetworkError)?;
+
+    match serde_json::from_str::<TelegramResponse<T>>(&text) {
+        Ok(response) => response.into(),
+        // E.g. a proxy in front of Telegram returns an HTML page.
+        Err(_) if status_code.is_server_error() => {
+            Err(RequestError::ApiError { status_code, kind: ApiErrorKind::Unknown(text) })
+        }
+        Err(error) => Err(RequestError::InvalidJson(error)),
+    }
 }
@@ -1,70 +1,79 @@
 use reqwest::StatusCode;
 use serde::Deserialize;
 
-use crate::{
-    requests::ResponseResult,
-    types::{False, ResponseParameters, True},
-    ApiErrorKind, RequestError,
-};
+use crate::{requests::ResponseResult, types::ResponseParameters, ApiErrorKind, RequestError};
 
+/// A response of Telegram.
+///
+/// It's not an untagged enum, because `RawValue` cannot be deserialized from
+/// untagged enums.
 #[derive(Deserialize)]
-#[serde(untagged)]
-pub enum TelegramResponse<R> {
-    Ok {
-        /// A dummy field. Used only for deserialization.
-        #[allow(dead_code)]
-        ok: True,
+pub struct TelegramResponse<R> {
+    ok: bool,
+    result: Option<R>,
 
-        result: R,
-    },
-    Err {
-        /// A dummy field. Used only for deserialization.
-        #[allow(dead_code)]
-        ok: False,
-
-        #[serde(rename = "description")]
-        kind: ApiErrorKind,
-        error_code: u16,
-        response_parameters: Option<ResponseParameters>,
-    },
+    #[serde(rename = "description")]
+    kind: Option<ApiErrorKind>,
+    error_code: Option<u16>,
+    parameters: Option<ResponseParameters>,
 }
 
 impl<R> Into<ResponseResult<R>> for TelegramResponse<R> {
     fn into(self) -> Result<R, RequestError> {
-        match self {
-            TelegramResponse::Ok { result, .. } => Ok(result),
-            TelegramResponse::Err { kind, error_code, response_parameters, .. } => {
-                if let Some(params) = response_parameters {
-                    match params {
-                        ResponseParameters::RetryAfter(i) => Err(RequestError::RetryAfter(i)),
-                        ResponseParameters::MigrateToChatId(to) => {
-                            Err(RequestError::MigrateToChatId(to))
-                        }
-                    }
-                } else {
-                    Err(RequestError::ApiError {
-                        kind,
-                        status_code: StatusCode::from_u16(error_code).unwrap(),
-                    })
-                }
-            }
+        let TelegramResponse { ok, result, kind, error_code, parameters } = self;
+
+        if ok {
+            return result.ok_or_else(|| RequestError::InvalidJson(missing_field("result")));
+        }
+
+        match parameters {
+            Some(ResponseParameters::RetryAfter(i)) => Err(RequestError::RetryAfter(i)),
+            Some(ResponseParameters::MigrateToChatId(to)) => Err(RequestError::MigrateToChatId(to)),
+            None => Err(RequestError::ApiError {
+                kind: kind
+                    .ok_or_else(|| RequestError::InvalidJson(missing_field("description")))?,
+                status_code: error_code
+                    .and_then(|code| StatusCode::from_u16(code).ok())
+                    .ok_or_else(|| RequestError::InvalidJson(missing_field("error_code")))?,
+            }),
         }
     }
 }
 
+fn missing_field(field: &'static str) -> serde_json::Error {
+    <serde_json::Error as serde::de::Error>::missing_field(field)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{errors::KnownApiErrorKind, types::Update};
 
+    fn parse(response: &str) -> ResponseResult<Update> {
+        serde_json::from_str::<TelegramResponse<Update>>(response).unwrap().into()
+    }
+
     #[test]
     fn terminated_by_other_get_updates() {
         let expected = ApiErrorKind::Known(KnownApiErrorKind::TerminatedByOtherGetUpdates);
-        if let TelegramResponse::Err{ kind, .. } = serde_json::from_str::<TelegramResponse<Update>>(r#"{"ok":false,"error_code":409,"description":"Conflict: terminated by other getUpdates request; make sure that only one bot instance is running"}"#).unwrap() {
-            assert_eq!(expected, kind);
+        match parse(
+            r#"{"ok":false,"error_code":409,"description":"Conflict: terminated by other getUpdates request; make sure that only one bot instance is running"}"#,
+        ) {
+            Err(RequestError::ApiError { kind, status_code }) => {
+                assert_eq!(expected, kind);
+                assert_eq!(status_code, StatusCode::CONFLICT);
+            }
+            _ => panic!("Expected ApiErrorKind::TerminatedByOtherGetUpdates"),
         }
-        else {
-            panic!("Expected ApiErrorKind::TerminatedByOtherGetUpdates");
+    }
+
+    #[test]
+    fn retry_after() {
+        match parse(
+            r#"{"ok":false,"error_code":429,"description":"Too Many Requests: retry after 5","parameters":{"retry_after":5}}"#,
+        ) {
+            Err(RequestError::RetryAfter(5)) => {}
+            other => panic!("Expected RequestError::RetryAfter(5), got {:?}", other),
         }
     }
 }
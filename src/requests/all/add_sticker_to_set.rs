@@ -39,13 +39,7 @@ impl RequestWithFile for AddStickerToSet {
         .add_text("emojis", &self.emojis)
         .add_text("mask_position", &self.mask_position);
 
-        Ok(net::request_multipart(
-            self.bot.client(),
-            self.bot.token(),
-            "addStickerToSet",
-            builder.build(),
-        )
-        .await)
+        Ok(net::request_multipart(&self.bot, "addStickerToSet", builder.build()).await)
     }
 }
 
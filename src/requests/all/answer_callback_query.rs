@@ -33,7 +33,7 @@ impl Request for AnswerCallbackQuery {
     type Output = True;
 
     async fn send(&self) -> ResponseResult<True> {
-        net::request_json(self.bot.client(), self.bot.token(), "answerCallbackQuery", &self).await
+        net::request_json(&self.bot, "answerCallbackQuery", &self).await
     }
 }
 
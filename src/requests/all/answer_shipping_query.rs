@@ -31,7 +31,7 @@ impl Request for AnswerShippingQuery {
     type Output = True;
 
     async fn send(&self) -> ResponseResult<True> {
-        net::request_json(self.bot.client(), self.bot.token(), "answerShippingQuery", &self).await
+        net::request_json(&self.bot, "answerShippingQuery", &self).await
     }
 }
 
@@ -40,13 +40,7 @@ impl RequestWithFile for CreateNewStickerSet {
         .add_text("contains_masks", &self.contains_masks)
         .add_text("mask_position", &self.mask_position);
 
-        Ok(net::request_multipart(
-            self.bot.client(),
-            self.bot.token(),
-            "createNewStickerSet",
-            builder.build(),
-        )
-        .await)
+        Ok(net::request_multipart(&self.bot, "createNewStickerSet", builder.build()).await)
     }
 }
 
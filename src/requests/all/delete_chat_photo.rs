@@ -25,7 +25,7 @@ impl Request for DeleteChatPhoto {
     type Output = True;
 
     async fn send(&self) -> ResponseResult<True> {
-        net::request_json(self.bot.client(), self.bot.token(), "deleteChatPhoto", &self).await
+        net::request_json(&self.bot, "deleteChatPhoto", &self).await
     }
 }
 
@@ -30,7 +30,7 @@ impl Request for DeleteChatStickerSet {
     type Output = True;
 
     async fn send(&self) -> ResponseResult<True> {
-        net::request_json(self.bot.client(), self.bot.token(), "deleteChatStickerSet", &self).await
+        net::request_json(&self.bot, "deleteChatStickerSet", &self).await
     }
 }
 
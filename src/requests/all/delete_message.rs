@@ -36,7 +36,7 @@ impl Request for DeleteMessage {
     type Output = True;
 
     async fn send(&self) -> ResponseResult<True> {
-        net::request_json(self.bot.client(), self.bot.token(), "deleteMessage", &self).await
+        net::request_json(&self.bot, "deleteMessage", &self).await
     }
 }
 
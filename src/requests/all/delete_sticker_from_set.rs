@@ -23,7 +23,7 @@ impl Request for DeleteStickerFromSet {
     type Output = True;
 
     async fn send(&self) -> ResponseResult<True> {
-        net::request_json(self.bot.client(), self.bot.token(), "deleteStickerFromSet", &self).await
+        net::request_json(&self.bot, "deleteStickerFromSet", &self).await
     }
 }
 
@@ -33,7 +33,7 @@ impl Request for EditMessageCaption {
     type Output = Message;
 
     async fn send(&self) -> ResponseResult<Message> {
-        net::request_json(self.bot.client(), self.bot.token(), "editMessageCaption", &self).await
+        net::request_json(&self.bot, "editMessageCaption", &self).await
     }
 }
 
@@ -35,8 +35,7 @@ impl Request for EditMessageLiveLocation {
     type Output = Message;
 
     async fn send(&self) -> ResponseResult<Message> {
-        net::request_json(self.bot.client(), self.bot.token(), "editMessageLiveLocation", &self)
-            .await
+        net::request_json(&self.bot, "editMessageLiveLocation", &self).await
     }
 }
 
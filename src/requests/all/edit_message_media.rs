@@ -44,8 +44,7 @@ impl Request for EditMessageMedia {
         }
 
         net::request_multipart(
-            self.bot.client(),
-            self.bot.token(),
+            &self.bot,
             "editMessageMedia",
             params
                 .add_text("media", &self.media)
@@ -31,8 +31,7 @@ impl Request for EditMessageReplyMarkup {
     type Output = Message;
 
     async fn send(&self) -> ResponseResult<Message> {
-        net::request_json(self.bot.client(), self.bot.token(), "editMessageReplyMarkup", &self)
-            .await
+        net::request_json(&self.bot, "editMessageReplyMarkup", &self).await
     }
 }
 
@@ -34,7 +34,7 @@ impl Request for EditMessageText {
     type Output = Message;
 
     async fn send(&self) -> ResponseResult<Message> {
-        net::request_json(self.bot.client(), self.bot.token(), "editMessageText", &self).await
+        net::request_json(&self.bot, "editMessageText", &self).await
     }
 }
 
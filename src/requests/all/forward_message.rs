@@ -26,7 +26,7 @@ impl Request for ForwardMessage {
     type Output = Message;
 
     async fn send(&self) -> ResponseResult<Message> {
-        net::request_json(self.bot.client(), self.bot.token(), "forwardMessage", &self).await
+        net::request_json(&self.bot, "forwardMessage", &self).await
     }
 }
 
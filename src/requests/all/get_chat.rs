@@ -25,7 +25,7 @@ impl Request for GetChat {
     type Output = Chat;
 
     async fn send(&self) -> ResponseResult<Chat> {
-        net::request_json(self.bot.client(), self.bot.token(), "getChat", &self).await
+        net::request_json(&self.bot, "getChat", &self).await
     }
 }
 
@@ -28,7 +28,7 @@ impl Request for GetChatAdministrators {
     /// On success, returns an array that contains information about all chat
     /// administrators except other bots.
     async fn send(&self) -> ResponseResult<Vec<ChatMember>> {
-        net::request_json(self.bot.client(), self.bot.token(), "getChatAdministrators", &self).await
+        net::request_json(&self.bot, "getChatAdministrators", &self).await
     }
 }
 
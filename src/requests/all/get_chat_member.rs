@@ -24,7 +24,7 @@ impl Request for GetChatMember {
     type Output = ChatMember;
 
     async fn send(&self) -> ResponseResult<ChatMember> {
-        net::request_json(self.bot.client(), self.bot.token(), "getChatMember", &self).await
+        net::request_json(&self.bot, "getChatMember", &self).await
     }
 }
 
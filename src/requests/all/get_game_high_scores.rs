@@ -34,7 +34,7 @@ impl Request for GetGameHighScores {
     type Output = Vec<GameHighScore>;
 
     async fn send(&self) -> ResponseResult<Vec<GameHighScore>> {
-        net::request_json(self.bot.client(), self.bot.token(), "getGameHighScores", &self).await
+        net::request_json(&self.bot, "getGameHighScores", &self).await
     }
 }
 
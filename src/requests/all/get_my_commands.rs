@@ -22,7 +22,7 @@ impl Request for GetMyCommands {
     type Output = Vec<BotCommand>;
 
     async fn send(&self) -> ResponseResult<Self::Output> {
-        net::request_json(self.bot.client(), self.bot.token(), "getMyCommands", &self).await
+        net::request_json(&self.bot, "getMyCommands", &self).await
     }
 }
 
@@ -23,7 +23,7 @@ impl Request for GetStickerSet {
     type Output = StickerSet;
 
     async fn send(&self) -> ResponseResult<StickerSet> {
-        net::request_json(self.bot.client(), self.bot.token(), "getStickerSet", &self).await
+        net::request_json(&self.bot, "getStickerSet", &self).await
     }
 }
 
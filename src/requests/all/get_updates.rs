@@ -37,8 +37,7 @@ impl Request for GetUpdates {
     /// `Vec<Update>`, because we want to parse the rest of updates even if our
     /// library hasn't parsed one.
     async fn send(&self) -> ResponseResult<Vec<Result<Update, (Value, serde_json::Error)>>> {
-        let value: Value =
-            net::request_json(self.bot.client(), self.bot.token(), "getUpdates", &self).await?;
+        let value: Value = net::request_json(&self.bot, "getUpdates", &self).await?;
 
         match value {
             Value::Array(array) => Ok(array
@@ -32,7 +32,7 @@ impl Request for KickChatMember {
     type Output = True;
 
     async fn send(&self) -> ResponseResult<True> {
-        net::request_json(self.bot.client(), self.bot.token(), "kickChatMember", &self).await
+        net::request_json(&self.bot, "kickChatMember", &self).await
     }
 }
 
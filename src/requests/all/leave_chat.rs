@@ -23,7 +23,7 @@ impl Request for LeaveChat {
     type Output = True;
 
     async fn send(&self) -> ResponseResult<True> {
-        net::request_json(self.bot.client(), self.bot.token(), "leaveChat", &self).await
+        net::request_json(&self.bot, "leaveChat", &self).await
     }
 }
 
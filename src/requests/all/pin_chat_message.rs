@@ -29,7 +29,7 @@ impl Request for PinChatMessage {
     type Output = True;
 
     async fn send(&self) -> ResponseResult<True> {
-        net::request_json(self.bot.client(), self.bot.token(), "pinChatMessage", &self).await
+        net::request_json(&self.bot, "pinChatMessage", &self).await
     }
 }
 
@@ -36,7 +36,7 @@ impl Request for PromoteChatMember {
     type Output = True;
 
     async fn send(&self) -> ResponseResult<True> {
-        net::request_json(self.bot.client(), self.bot.token(), "promoteChatMember", &self).await
+        net::request_json(&self.bot, "promoteChatMember", &self).await
     }
 }
 
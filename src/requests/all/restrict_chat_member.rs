@@ -30,7 +30,7 @@ impl Request for RestrictChatMember {
     type Output = True;
 
     async fn send(&self) -> ResponseResult<True> {
-        net::request_json(self.bot.client(), self.bot.token(), "restrictChatMember", &self).await
+        net::request_json(&self.bot, "restrictChatMember", &self).await
     }
 }
 
@@ -48,13 +48,7 @@ impl RequestWithFile for SendAnimation {
         if let Some(thumb) = self.thumb.as_ref() {
             builder = builder.add_input_file("thumb", thumb).await?;
         }
-        Ok(net::request_multipart(
-            self.bot.client(),
-            self.bot.token(),
-            "sendAnimation",
-            builder.build(),
-        )
-        .await)
+        Ok(net::request_multipart(&self.bot, "sendAnimation", builder.build()).await)
     }
 }
 
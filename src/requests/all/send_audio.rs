@@ -52,13 +52,7 @@ impl RequestWithFile for SendAudio {
         if let Some(thumb) = self.thumb.as_ref() {
             builder = builder.add_input_file("thumb", thumb).await?;
         }
-        Ok(net::request_multipart(
-            self.bot.client(),
-            self.bot.token(),
-            "sendAudio",
-            builder.build(),
-        )
-        .await)
+        Ok(net::request_multipart(&self.bot, "sendAudio", builder.build()).await)
     }
 }
 
@@ -75,7 +75,7 @@ impl Request for SendChatAction {
     type Output = True;
 
     async fn send(&self) -> ResponseResult<True> {
-        net::request_json(self.bot.client(), self.bot.token(), "sendChatAction", &self).await
+        net::request_json(&self.bot, "sendChatAction", &self).await
     }
 }
 
@@ -30,7 +30,7 @@ impl Request for SendContact {
     type Output = Message;
 
     async fn send(&self) -> ResponseResult<Message> {
-        net::request_json(self.bot.client(), self.bot.token(), "sendContact", &self).await
+        net::request_json(&self.bot, "sendContact", &self).await
     }
 }
 
@@ -29,7 +29,7 @@ impl Request for SendDice {
     type Output = Message;
 
     async fn send(&self) -> ResponseResult<Message> {
-        net::request_json(self.bot.client(), self.bot.token(), "sendDice", &self).await
+        net::request_json(&self.bot, "sendDice", &self).await
     }
 }
 
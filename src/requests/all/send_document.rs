@@ -41,13 +41,7 @@ impl RequestWithFile for SendDocument {
         if let Some(thumb) = self.thumb.as_ref() {
             builder = builder.add_input_file("thumb", thumb).await?;
         }
-        Ok(net::request_multipart(
-            self.bot.client(),
-            self.bot.token(),
-            "sendDocument",
-            builder.build(),
-        )
-        .await)
+        Ok(net::request_multipart(&self.bot, "sendDocument", builder.build()).await)
     }
 }
 
@@ -45,7 +45,7 @@ impl Request for SendInvoice {
     type Output = Message;
 
     async fn send(&self) -> ResponseResult<Message> {
-        net::request_json(self.bot.client(), self.bot.token(), "sendInvoice", &self).await
+        net::request_json(&self.bot, "sendInvoice", &self).await
     }
 }
 
@@ -29,7 +29,7 @@ impl Request for SendLocation {
     type Output = Message;
 
     async fn send(&self) -> ResponseResult<Message> {
-        net::request_json(self.bot.client(), self.bot.token(), "sendLocation", &self).await
+        net::request_json(&self.bot, "sendLocation", &self).await
     }
 }
 
@@ -23,8 +23,7 @@ impl Request for SendMediaGroup {
 
     async fn send(&self) -> ResponseResult<Vec<Message>> {
         net::request_multipart(
-            self.bot.client(),
-            self.bot.token(),
+            &self.bot,
             "sendMediaGroup",
             FormBuilder::new()
                 .add_text("chat_id", &self.chat_id)
@@ -29,7 +29,7 @@ impl Request for SendMessage {
     type Output = Message;
 
     async fn send(&self) -> ResponseResult<Message> {
-        net::request_json(self.bot.client(), self.bot.token(), "sendMessage", &self).await
+        net::request_json(&self.bot, "sendMessage", &self).await
     }
 }
 
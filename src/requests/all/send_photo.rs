@@ -26,8 +26,7 @@ impl RequestWithFile for SendPhoto {
 
     async fn send(&self) -> tokio::io::Result<ResponseResult<Message>> {
         Ok(net::request_multipart(
-            self.bot.client(),
-            self.bot.token(),
+            &self.bot,
             "sendPhoto",
             FormBuilder::new()
                 .add_text("chat_id", &self.chat_id)
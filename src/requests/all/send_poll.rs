@@ -37,7 +37,7 @@ impl Request for SendPoll {
     type Output = Message;
 
     async fn send(&self) -> ResponseResult<Message> {
-        net::request_json(self.bot.client(), self.bot.token(), "sendPoll", &self).await
+        net::request_json(&self.bot, "sendPoll", &self).await
     }
 }
 
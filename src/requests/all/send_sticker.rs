@@ -26,8 +26,7 @@ impl RequestWithFile for SendSticker {
 
     async fn send(&self) -> tokio::io::Result<ResponseResult<Message>> {
         Ok(net::request_multipart(
-            self.bot.client(),
-            self.bot.token(),
+            &self.bot,
             "sendSticker",
             FormBuilder::new()
                 .add_text("chat_id", &self.chat_id)
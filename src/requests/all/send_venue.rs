@@ -32,7 +32,7 @@ impl Request for SendVenue {
     type Output = Message;
 
     async fn send(&self) -> ResponseResult<Message> {
-        net::request_json(self.bot.client(), self.bot.token(), "sendVenue", &self).await
+        net::request_json(&self.bot, "sendVenue", &self).await
     }
 }
 
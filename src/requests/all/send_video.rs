@@ -50,13 +50,7 @@ impl RequestWithFile for SendVideo {
         if let Some(thumb) = self.thumb.as_ref() {
             builder = builder.add_input_file("thumb", thumb).await?;
         }
-        Ok(net::request_multipart(
-            self.bot.client(),
-            self.bot.token(),
-            "sendVideo",
-            builder.build(),
-        )
-        .await)
+        Ok(net::request_multipart(&self.bot, "sendVideo", builder.build()).await)
     }
 }
 
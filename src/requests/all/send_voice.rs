@@ -36,8 +36,7 @@ impl RequestWithFile for SendVoice {
 
     async fn send(&self) -> tokio::io::Result<ResponseResult<Message>> {
         Ok(net::request_multipart(
-            self.bot.client(),
-            self.bot.token(),
+            &self.bot,
             "sendVoice",
             FormBuilder::new()
                 .add_text("chat_id", &self.chat_id)
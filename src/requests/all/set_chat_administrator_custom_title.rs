@@ -26,13 +26,7 @@ impl Request for SetChatAdministratorCustomTitle {
     type Output = True;
 
     async fn send(&self) -> ResponseResult<True> {
-        net::request_json(
-            self.bot.client(),
-            self.bot.token(),
-            "setChatAdministratorCustomTitle",
-            &self,
-        )
-        .await
+        net::request_json(&self.bot, "setChatAdministratorCustomTitle", &self).await
     }
 }
 
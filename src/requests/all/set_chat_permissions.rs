@@ -27,7 +27,7 @@ impl Request for SetChatPermissions {
     type Output = True;
 
     async fn send(&self) -> ResponseResult<True> {
-        net::request_json(self.bot.client(), self.bot.token(), "sendChatPermissions", &self).await
+        net::request_json(&self.bot, "sendChatPermissions", &self).await
     }
 }
 
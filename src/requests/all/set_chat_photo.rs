@@ -27,7 +27,7 @@ impl Request for SetChatPhoto {
     type Output = True;
 
     async fn send(&self) -> ResponseResult<True> {
-        net::request_json(self.bot.client(), self.bot.token(), "setChatPhoto", &self).await
+        net::request_json(&self.bot, "setChatPhoto", &self).await
     }
 }
 
@@ -28,7 +28,7 @@ impl Request for SetChatStickerSet {
     type Output = True;
 
     async fn send(&self) -> ResponseResult<True> {
-        net::request_json(self.bot.client(), self.bot.token(), "setChatStickerSet", &self).await
+        net::request_json(&self.bot, "setChatStickerSet", &self).await
     }
 }
 
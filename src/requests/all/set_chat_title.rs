@@ -27,7 +27,7 @@ impl Request for SetChatTitle {
     type Output = True;
 
     async fn send(&self) -> ResponseResult<True> {
-        net::request_json(self.bot.client(), self.bot.token(), "setChatTitle", &self).await
+        net::request_json(&self.bot, "setChatTitle", &self).await
     }
 }
 
@@ -36,7 +36,7 @@ impl Request for SetGameScore {
     type Output = Message;
 
     async fn send(&self) -> ResponseResult<Message> {
-        net::request_json(self.bot.client(), self.bot.token(), "setGameScore", &self).await
+        net::request_json(&self.bot, "setGameScore", &self).await
     }
 }
 
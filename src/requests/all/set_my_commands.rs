@@ -24,7 +24,7 @@ impl Request for SetMyCommands {
     type Output = True;
 
     async fn send(&self) -> ResponseResult<Self::Output> {
-        net::request_json(self.bot.client(), self.bot.token(), "setMyCommands", &self).await
+        net::request_json(&self.bot, "setMyCommands", &self).await
     }
 }
 
@@ -26,7 +26,7 @@ impl Request for SetStickerSetThumb {
     type Output = True;
 
     async fn send(&self) -> ResponseResult<Self::Output> {
-        net::request_json(self.bot.client(), self.bot.token(), "setStickerSetThumb", &self).await
+        net::request_json(&self.bot, "setStickerSetThumb", &self).await
     }
 }
 
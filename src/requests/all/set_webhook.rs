@@ -39,7 +39,7 @@ impl Request for SetWebhook {
     type Output = True;
 
     async fn send(&self) -> ResponseResult<True> {
-        net::request_json(self.bot.client(), self.bot.token(), "setWebhook", &self).await
+        net::request_json(&self.bot, "setWebhook", &self).await
     }
 }
 
@@ -32,8 +32,7 @@ impl Request for StopMessageLiveLocation {
     type Output = Message;
 
     async fn send(&self) -> ResponseResult<Message> {
-        net::request_json(self.bot.client(), self.bot.token(), "stopMessageLiveLocation", &self)
-            .await
+        net::request_json(&self.bot, "stopMessageLiveLocation", &self).await
     }
 }
 
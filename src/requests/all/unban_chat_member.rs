@@ -27,7 +27,7 @@ impl Request for UnbanChatMember {
     type Output = True;
 
     async fn send(&self) -> ResponseResult<True> {
-        net::request_json(self.bot.client(), self.bot.token(), "unbanChatMember", &self).await
+        net::request_json(&self.bot, "unbanChatMember", &self).await
     }
 }
 
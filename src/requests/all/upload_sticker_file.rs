@@ -28,7 +28,7 @@ impl Request for UploadStickerFile {
     type Output = File;
 
     async fn send(&self) -> ResponseResult<File> {
-        net::request_json(self.bot.client(), self.bot.token(), "uploadStickerFile", &self).await
+        net::request_json(&self.bot, "uploadStickerFile", &self).await
     }
 }
 
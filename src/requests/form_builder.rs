@@ -1,23 +1,95 @@
 use std::{borrow::Cow, path::PathBuf};
 
-use reqwest::multipart::Form;
+use bytes::Bytes;
+use reqwest::{
+    multipart::{self, Part},
+    Body,
+};
 
 use crate::{
-    requests::utils::{file_from_memory_to_part, file_to_part},
+    requests::utils::file_to_part,
     types::{
         ChatId, InlineKeyboardMarkup, InputFile, InputMedia, MaskPosition, ParseMode, ReplyMarkup,
     },
 };
 
-/// This is a convenient struct that builds `reqwest::multipart::Form`
-/// from scratch.
+/// Parameters of a request with files, which is sent as
+/// `multipart/form-data`.
+///
+/// Unlike `reqwest::multipart::Form`, the form can be cloned and resent (e.g.
+/// after [`RequestError::RetryAfter`]): files from disk are opened and
+/// streamed anew every time the form is sent, and only files from memory are
+/// kept in memory.
+///
+/// See [`Params::Multipart`].
+///
+/// [`RequestError::RetryAfter`]: crate::RequestError::RetryAfter
+/// [`Params::Multipart`]: crate::layers::Params::Multipart
+#[derive(Debug, Clone, Default)]
+pub struct Form {
+    parts: Vec<(String, FormPart)>,
+}
+
+#[derive(Debug, Clone)]
+enum FormPart {
+    Text(String),
+    File(PathBuf),
+    Memory { file_name: String, data: Bytes },
+}
+
+impl Form {
+    /// Returns the value of a text field named `name`.
+    #[must_use]
+    pub fn text(&self, name: &str) -> Option<&str> {
+        self.parts.iter().find_map(|(part_name, part)| match part {
+            FormPart::Text(text) if part_name == name => Some(text.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Sets the value of a text field named `name`.
+    ///
+    /// If there's a file named `name`, it's replaced.
+    pub fn set_text<S>(&mut self, name: &str, value: S)
+    where
+        S: Into<String>,
+    {
+        let value = FormPart::Text(value.into());
+
+        match self.parts.iter_mut().find(|(part_name, _)| part_name == name) {
+            Some((_, part)) => *part = value,
+            None => self.parts.push((name.to_owned(), value)),
+        }
+    }
+
+    /// Builds a form to be sent, opening files from disk.
+    pub(crate) async fn to_multipart(&self) -> std::io::Result<multipart::Form> {
+        let mut form = multipart::Form::new();
+
+        for (name, part) in &self.parts {
+            form = match part {
+                FormPart::Text(text) => form.text(name.clone(), text.clone()),
+                FormPart::File(path) => form.part(name.clone(), file_to_part(path.clone()).await?),
+                FormPart::Memory { file_name, data } => form.part(
+                    name.clone(),
+                    Part::stream_with_length(Body::from(data.clone()), data.len() as u64)
+                        .file_name(file_name.clone()),
+                ),
+            };
+        }
+
+        Ok(form)
+    }
+}
+
+/// This is a convenient struct that builds [`Form`] from scratch.
 pub(crate) struct FormBuilder {
     form: Form,
 }
 
 impl FormBuilder {
     pub(crate) fn new() -> Self {
-        Self { form: Form::new() }
+        Self { form: Form::default() }
     }
 
     pub fn add_text<'a, T, N>(self, name: N, value: &T) -> Self
@@ -26,7 +98,7 @@ impl FormBuilder {
         T: IntoFormText,
     {
         match value.into_form_text() {
-            Some(val) => Self { form: self.form.text(name.into().into_owned(), val) },
+            Some(val) => self.add_part(name, FormPart::Text(val)),
             None => self,
         }
     }
@@ -50,9 +122,11 @@ impl FormBuilder {
     where
         N: Into<Cow<'a, str>>,
     {
-        Ok(Self {
-            form: self.form.part(name.into().into_owned(), file_to_part(path_to_file).await?),
-        })
+        // The file is opened when the form is sent, but a missing file is
+        // reported right away.
+        tokio::fs::metadata(&path_to_file).await?;
+
+        Ok(self.add_part(name, FormPart::File(path_to_file)))
     }
 
     fn add_file_from_memory<'a, N>(
@@ -64,11 +138,20 @@ impl FormBuilder {
     where
         N: Into<Cow<'a, str>>,
     {
-        Self {
-            form: self
-                .form
-                .part(name.into().into_owned(), file_from_memory_to_part(data, file_name)),
-        }
+        let data = match data {
+            Cow::Borrowed(data) => Bytes::from_static(data),
+            Cow::Owned(data) => Bytes::from(data),
+        };
+
+        self.add_part(name, FormPart::Memory { file_name, data })
+    }
+
+    fn add_part<'a, N>(mut self, name: N, part: FormPart) -> Self
+    where
+        N: Into<Cow<'a, str>>,
+    {
+        self.form.parts.push((name.into().into_owned(), part));
+        self
     }
 
     pub fn build(self) -> Form {
@@ -154,3 +237,46 @@ impl IntoFormText for String {
         Some(self.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        layers::ExponentialBackoff,
+        requests::RequestWithFile,
+        testing::{MockServer, MOCK_TOKEN},
+        types::InputFile,
+        BotBuilder,
+    };
+    use serde_json::json;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn files_are_read_on_every_attempt() {
+        let server = MockServer::start();
+        server.respond_error("sendDocument", 502, "Bad Gateway");
+
+        let bot = BotBuilder::new()
+            .token(MOCK_TOKEN)
+            .api_url(server.url())
+            .retry_policy(
+                ExponentialBackoff::new()
+                    .initial_delay(Duration::from_millis(1))
+                    .jitter(false)
+                    .retry_non_idempotent(true),
+            )
+            .build();
+
+        let path = std::env::temp_dir().join(format!("teloxide-form-{}.txt", std::process::id()));
+        tokio::fs::write(&path, b"Some notes").await.unwrap();
+        let sent = bot.send_document(1, InputFile::file(&path)).send().await;
+        tokio::fs::remove_file(&path).await.unwrap();
+        sent.unwrap().unwrap();
+
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        for request in requests {
+            assert_eq!(request.params["document"], json!({ "file_name": file_name, "size": 10 }));
+        }
+    }
+}
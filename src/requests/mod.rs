@@ -4,6 +4,8 @@ mod all;
 mod form_builder;
 mod utils;
 
+pub use form_builder::Form;
+
 pub use all::*;
 
 /// A type that is returned after making a request to Telegram.
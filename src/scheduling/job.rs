@@ -0,0 +1,61 @@
+use std::{fmt, time::SystemTime};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::scheduling::Schedule;
+
+/// An ID of a job.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct JobId(pub String);
+
+impl JobId {
+    /// Generates a random ID.
+    pub(crate) fn random() -> Self {
+        Self(format!("{:016x}", rand::thread_rng().gen::<u64>()))
+    }
+}
+
+impl fmt::Display for JobId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for JobId {
+    fn from(id: String) -> Self {
+        Self(id)
+    }
+}
+
+impl From<&str> for JobId {
+    fn from(id: &str) -> Self {
+        Self(id.to_owned())
+    }
+}
+
+/// A scheduled job.
+///
+/// A job is plain data, so that it can be kept in a [`JobStore`]: it's run
+/// by a handler registered for its kind via [`Scheduler::handler`], which
+/// receives its payload.
+///
+/// [`JobStore`]: crate::scheduling::JobStore
+/// [`Scheduler::handler`]: crate::scheduling::Scheduler::handler
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    pub id: JobId,
+
+    /// A kind of the job, which determines its handler.
+    pub kind: String,
+
+    /// The serialized payload, passed to the handler.
+    pub payload: Value,
+
+    pub schedule: Schedule,
+
+    /// The time of the next run.
+    pub next_run: SystemTime,
+}
@@ -0,0 +1,46 @@
+use super::JobStore;
+use crate::scheduling::{Job, JobId};
+use futures::future::BoxFuture;
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{Arc, Mutex},
+};
+
+/// A job store, which keeps jobs in RAM.
+///
+/// ## Note
+/// All the jobs will be lost after you restart your bot.
+#[derive(Debug, Default)]
+pub struct InMemJobStore {
+    jobs: Mutex<HashMap<JobId, Job>>,
+}
+
+impl InMemJobStore {
+    #[must_use]
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+}
+
+impl JobStore for InMemJobStore {
+    type Error = Infallible;
+
+    fn load_jobs(self: Arc<Self>) -> BoxFuture<'static, Result<Vec<Job>, Self::Error>> {
+        Box::pin(async move { Ok(self.jobs.lock().unwrap().values().cloned().collect()) })
+    }
+
+    fn save_job(self: Arc<Self>, job: Job) -> BoxFuture<'static, Result<(), Self::Error>> {
+        Box::pin(async move {
+            self.jobs.lock().unwrap().insert(job.id.clone(), job);
+            Ok(())
+        })
+    }
+
+    fn remove_job(self: Arc<Self>, id: JobId) -> BoxFuture<'static, Result<(), Self::Error>> {
+        Box::pin(async move {
+            self.jobs.lock().unwrap().remove(&id);
+            Ok(())
+        })
+    }
+}
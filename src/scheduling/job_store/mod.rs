@@ -0,0 +1,40 @@
+mod in_mem_job_store;
+
+#[cfg(feature = "redis-storage")]
+mod redis_job_store;
+
+#[cfg(feature = "sqlite-storage")]
+mod sqlite_job_store;
+
+use futures::future::BoxFuture;
+use std::sync::Arc;
+
+use crate::scheduling::{Job, JobId};
+
+pub use in_mem_job_store::InMemJobStore;
+#[cfg(feature = "redis-storage")]
+pub use redis_job_store::{RedisJobStore, RedisJobStoreError};
+#[cfg(feature = "sqlite-storage")]
+pub use sqlite_job_store::{SqliteJobStore, SqliteJobStoreError};
+
+/// A storage of scheduled jobs.
+///
+/// [`Scheduler`] loads all the jobs on start and saves every change, so that
+/// after a restart of the bot the jobs keep being run.
+///
+/// For a storage based on a simple hash map, see [`InMemJobStore`].
+///
+/// [`Scheduler`]: crate::scheduling::Scheduler
+/// [`InMemJobStore`]: crate::scheduling::InMemJobStore
+pub trait JobStore {
+    type Error;
+
+    /// Returns all the saved jobs.
+    fn load_jobs(self: Arc<Self>) -> BoxFuture<'static, Result<Vec<Job>, Self::Error>>;
+
+    /// Saves `job`, replacing a job with the same ID.
+    fn save_job(self: Arc<Self>, job: Job) -> BoxFuture<'static, Result<(), Self::Error>>;
+
+    /// Removes a job with `id`, if any.
+    fn remove_job(self: Arc<Self>, id: JobId) -> BoxFuture<'static, Result<(), Self::Error>>;
+}
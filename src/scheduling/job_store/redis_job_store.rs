@@ -0,0 +1,70 @@
+use super::JobStore;
+use crate::scheduling::{Job, JobId};
+use futures::future::BoxFuture;
+use redis::{AsyncCommands, IntoConnectionInfo, RedisError};
+use std::{ops::DerefMut, sync::Arc};
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+/// An error returned from [`RedisJobStore`].
+///
+/// [`RedisJobStore`]: crate::scheduling::RedisJobStore
+#[derive(Debug, Error)]
+pub enum RedisJobStoreError {
+    #[error("parsing/serializing error: {0}")]
+    SerdeError(#[from] serde_json::Error),
+    #[error("error from Redis: {0}")]
+    RedisError(#[from] RedisError),
+}
+
+/// A job store based on [Redis](https://redis.io/).
+///
+/// Jobs are kept as JSON in a hash under a single key.
+pub struct RedisJobStore {
+    conn: Mutex<redis::aio::Connection>,
+    key: String,
+}
+
+impl RedisJobStore {
+    /// Connects to Redis, where jobs are kept in a hash under `key`.
+    pub async fn open<K>(url: impl IntoConnectionInfo, key: K) -> Result<Arc<Self>, RedisError>
+    where
+        K: Into<String>,
+    {
+        Ok(Arc::new(Self {
+            conn: Mutex::new(redis::Client::open(url)?.get_async_connection().await?),
+            key: key.into(),
+        }))
+    }
+}
+
+impl JobStore for RedisJobStore {
+    type Error = RedisJobStoreError;
+
+    fn load_jobs(self: Arc<Self>) -> BoxFuture<'static, Result<Vec<Job>, Self::Error>> {
+        Box::pin(async move {
+            let jobs: Vec<String> = self.conn.lock().await.deref_mut().hvals(&self.key).await?;
+            jobs.iter().map(|job| serde_json::from_str(job).map_err(Into::into)).collect()
+        })
+    }
+
+    fn save_job(self: Arc<Self>, job: Job) -> BoxFuture<'static, Result<(), Self::Error>> {
+        Box::pin(async move {
+            let json = serde_json::to_string(&job)?;
+            self.conn
+                .lock()
+                .await
+                .deref_mut()
+                .hset::<_, _, _, ()>(&self.key, job.id.0, json)
+                .await?;
+            Ok(())
+        })
+    }
+
+    fn remove_job(self: Arc<Self>, id: JobId) -> BoxFuture<'static, Result<(), Self::Error>> {
+        Box::pin(async move {
+            self.conn.lock().await.deref_mut().hdel::<_, _, ()>(&self.key, id.0).await?;
+            Ok(())
+        })
+    }
+}
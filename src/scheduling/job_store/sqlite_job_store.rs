@@ -0,0 +1,117 @@
+use super::JobStore;
+use crate::scheduling::{Job, JobId};
+use futures::future::BoxFuture;
+use rusqlite::{params, Connection};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use thiserror::Error;
+
+/// An error returned from [`SqliteJobStore`].
+///
+/// [`SqliteJobStore`]: crate::scheduling::SqliteJobStore
+#[derive(Debug, Error)]
+pub enum SqliteJobStoreError {
+    #[error("parsing/serializing error: {0}")]
+    SerdeError(#[from] serde_json::Error),
+    #[error("error from SQLite: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+/// A job store based on [SQLite](https://sqlite.org/).
+///
+/// Jobs are kept as JSON in the `teloxide_jobs` table, which is created if it
+/// doesn't exist. Queries are run on a thread pool for blocking operations.
+pub struct SqliteJobStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteJobStore {
+    /// Opens (or creates) a database at `path`.
+    pub fn open<P>(path: P) -> Result<Arc<Self>, rusqlite::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let conn = Connection::open(path)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS teloxide_jobs (id TEXT PRIMARY KEY, job TEXT NOT NULL)",
+            params![],
+        )?;
+
+        Ok(Arc::new(Self { conn: Mutex::new(conn) }))
+    }
+
+    /// Runs `f` with the connection on a thread pool for blocking operations.
+    fn with_conn<F, T>(self: Arc<Self>, f: F) -> BoxFuture<'static, Result<T, SqliteJobStoreError>>
+    where
+        F: FnOnce(&Connection) -> Result<T, SqliteJobStoreError> + Send + 'static,
+        T: Send + 'static,
+    {
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || f(&self.conn.lock().unwrap()))
+                .await
+                .expect("A query to SQLite has panicked")
+        })
+    }
+}
+
+impl JobStore for SqliteJobStore {
+    type Error = SqliteJobStoreError;
+
+    fn load_jobs(self: Arc<Self>) -> BoxFuture<'static, Result<Vec<Job>, Self::Error>> {
+        self.with_conn(|conn| {
+            let mut statement = conn.prepare("SELECT job FROM teloxide_jobs")?;
+            let jobs = statement.query_map(params![], |row| row.get::<_, String>(0))?;
+
+            jobs.map(|job| Ok(serde_json::from_str(&job?)?)).collect()
+        })
+    }
+
+    fn save_job(self: Arc<Self>, job: Job) -> BoxFuture<'static, Result<(), Self::Error>> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO teloxide_jobs (id, job) VALUES (?1, ?2)",
+                params![job.id.0, serde_json::to_string(&job)?],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn remove_job(self: Arc<Self>, id: JobId) -> BoxFuture<'static, Result<(), Self::Error>> {
+        self.with_conn(move |conn| {
+            conn.execute("DELETE FROM teloxide_jobs WHERE id = ?1", params![id.0])?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduling::Schedule;
+    use std::time::{Duration, SystemTime};
+
+    #[tokio::test]
+    async fn jobs_are_persisted() {
+        let path = std::env::temp_dir().join(format!("teloxide-jobs-{}.db", JobId::random()));
+        let job = Job {
+            id: "unmute".into(),
+            kind: "unmute".to_owned(),
+            payload: serde_json::json!({ "chat_id": 1 }),
+            schedule: Schedule::every(Duration::from_secs(60)),
+            next_run: SystemTime::now(),
+        };
+
+        let store = SqliteJobStore::open(&path).unwrap();
+        Arc::clone(&store).save_job(job.clone()).await.unwrap();
+        Arc::clone(&store).save_job(Job { id: "other".into(), ..job.clone() }).await.unwrap();
+        store.remove_job("other".into()).await.unwrap();
+
+        // Reopen the database, as after a restart.
+        let store = SqliteJobStore::open(&path).unwrap();
+        assert_eq!(store.load_jobs().await.unwrap(), [job]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,474 @@
+//! Delayed and recurring jobs.
+//!
+//! A [`Scheduler`] runs jobs according to their [`Schedule`]s: once at a
+//! specified time, every interval or at the times matching a [`Cron`]
+//! expression. Jobs are kept in a [`JobStore`], so they survive restarts of
+//! the bot if a persistent store is used: [`RedisJobStore`] (the
+//! `redis-storage` feature) or [`SqliteJobStore`] (the `sqlite-storage`
+//! feature).
+//!
+//! Since a job has to be stored, it's plain data: a kind and a serializable
+//! payload. When a job is due, it's passed to the handler registered for its
+//! kind via [`Scheduler::handler`].
+//!
+//! A store is updated before a job is run, so a job isn't repeated if the bot
+//! is stopped while it's running. Jobs missed while the bot was down are run
+//! once right after a start.
+//!
+//! ## Example
+//! ```no_run
+//! use serde::{Deserialize, Serialize};
+//! use std::time::Duration;
+//! use teloxide::{
+//!     prelude::*,
+//!     scheduling::{InMemJobStore, Schedule, Scheduler, SchedulerHandle},
+//!     types::ChatPermissions,
+//! };
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Unmute {
+//!     chat_id: i64,
+//!     user_id: i32,
+//! }
+//!
+//! # async fn run() {
+//! let bot = Bot::from_env();
+//!
+//! let scheduler = Scheduler::new(bot.clone(), InMemJobStore::new())
+//!     .handler("unmute", |bot: Bot, job: Unmute| async move {
+//!         let mut permissions = ChatPermissions::new();
+//!         permissions.can_send_messages = Some(true);
+//!         bot.restrict_chat_member(job.chat_id, job.user_id, permissions).send().await.map(drop)
+//!     })
+//!     .start()
+//!     .await
+//!     .unwrap();
+//!
+//! Dispatcher::new(bot)
+//!     .dependency(scheduler)
+//!     .messages_handler(|rx: DispatcherHandlerRx<Message>| {
+//!         rx.for_each_concurrent(None, |cx| async move {
+//!             let scheduler: SchedulerHandle<InMemJobStore> = cx.dependency();
+//!             let user = match cx.update.from() {
+//!                 Some(user) => user,
+//!                 None => return,
+//!             };
+//!
+//!             // Mute the user somehow, and then:
+//!             let job = Unmute { chat_id: cx.chat_id(), user_id: user.id };
+//!             scheduler
+//!                 .schedule("unmute", &job, Schedule::after(Duration::from_secs(600)))
+//!                 .await
+//!                 .unwrap();
+//!         })
+//!     })
+//!     .dispatch()
+//!     .await;
+//! # }
+//! ```
+//!
+//! [`Scheduler`]: crate::scheduling::Scheduler
+//! [`Schedule`]: crate::scheduling::Schedule
+//! [`Cron`]: crate::scheduling::Cron
+//! [`JobStore`]: crate::scheduling::JobStore
+//! [`RedisJobStore`]: crate::scheduling::RedisJobStore
+//! [`SqliteJobStore`]: crate::scheduling::SqliteJobStore
+//! [`Scheduler::handler`]: crate::scheduling::Scheduler::handler
+
+mod job;
+mod job_store;
+mod schedule;
+
+pub use job::{Job, JobId};
+pub use job_store::{InMemJobStore, JobStore};
+#[cfg(feature = "redis-storage")]
+pub use job_store::{RedisJobStore, RedisJobStoreError};
+#[cfg(feature = "sqlite-storage")]
+pub use job_store::{SqliteJobStore, SqliteJobStoreError};
+pub use schedule::{Cron, CronError, Schedule};
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug},
+    future::Future,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use futures::future::{self, BoxFuture};
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+use tokio::{
+    sync::{Mutex, Notify},
+    task::JoinHandle,
+};
+
+use crate::{dispatching::StopToken, error_handlers::OnError, Bot};
+
+/// How long the scheduler sleeps at most, so that it notices changes of the
+/// system clock.
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+/// An error returned from [`SchedulerHandle`].
+///
+/// [`SchedulerHandle`]: crate::scheduling::SchedulerHandle
+#[derive(Debug, Error)]
+pub enum SchedulerError<E>
+where
+    E: Debug,
+{
+    #[error("Cannot serialize a payload of a job: {0}")]
+    Payload(#[source] serde_json::Error),
+
+    #[error("A job would never run according to its schedule")]
+    NeverRuns,
+
+    #[error("A job store has failed: {0:?}")]
+    Store(E),
+}
+
+type JobHandler = Arc<dyn Fn(Bot, Job) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// A scheduler of jobs, which is being configured.
+///
+/// See the [module-level documentation](crate::scheduling) for the design
+/// overview.
+pub struct Scheduler<S> {
+    bot: Bot,
+    store: Arc<S>,
+    handlers: HashMap<String, JobHandler>,
+}
+
+impl<S> Scheduler<S>
+where
+    S: JobStore + Send + Sync + 'static,
+    S::Error: Debug + Send,
+{
+    /// Creates a scheduler, which passes `bot` to the handlers and keeps jobs
+    /// in `store`.
+    #[must_use]
+    pub fn new(bot: Bot, store: Arc<S>) -> Self {
+        Self { bot, store, handlers: HashMap::new() }
+    }
+
+    /// Registers a handler of jobs of `kind`.
+    ///
+    /// A handler receives a payload of a job, deserialized into `T`. Errors
+    /// returned from a handler are logged.
+    #[must_use]
+    pub fn handler<K, H, T, Fut, E>(mut self, kind: K, handler: H) -> Self
+    where
+        K: Into<String>,
+        H: Fn(Bot, T) -> Fut + Send + Sync + 'static,
+        T: DeserializeOwned,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Debug + Send,
+    {
+        let handler: JobHandler =
+            Arc::new(move |bot, job| match serde_json::from_value(job.payload) {
+                Ok(payload) => {
+                    let fut = handler(bot, payload);
+                    Box::pin(async move { fut.await.log_on_error().await })
+                }
+                Err(error) => {
+                    log::error!("Cannot parse a payload of the job {}: {}", job.id, error);
+                    Box::pin(future::ready(()))
+                }
+            });
+
+        self.handlers.insert(kind.into(), handler);
+        self
+    }
+
+    /// Loads jobs from the store and starts running them.
+    ///
+    /// # Panics
+    /// If it's called outside of the Tokio runtime.
+    pub async fn start(self) -> Result<SchedulerHandle<S>, S::Error> {
+        let jobs = Arc::clone(&self.store).load_jobs().await?;
+
+        let inner = Arc::new(Inner {
+            store: self.store,
+            jobs: Mutex::new(jobs.into_iter().map(|job| (job.id.clone(), job)).collect()),
+            changed: Notify::new(),
+            stop: StopToken::new(),
+            task: std::sync::Mutex::new(None),
+        });
+
+        let task = tokio::spawn(run(Arc::clone(&inner), self.bot, self.handlers));
+        *inner.task.lock().unwrap() = Some(task);
+
+        Ok(SchedulerHandle { inner })
+    }
+}
+
+impl<S> Debug for Scheduler<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scheduler").field("kinds", &self.handlers.keys()).finish()
+    }
+}
+
+/// A handle of a running [`Scheduler`], which schedules and cancels jobs.
+///
+/// It's cheap to clone, so you can register it as a dependency of a
+/// [`Dispatcher`] to use it in handlers.
+///
+/// [`Scheduler`]: crate::scheduling::Scheduler
+/// [`Dispatcher`]: crate::dispatching::Dispatcher
+pub struct SchedulerHandle<S> {
+    inner: Arc<Inner<S>>,
+}
+
+struct Inner<S> {
+    store: Arc<S>,
+
+    /// All the jobs, which are also kept in `store`.
+    ///
+    /// The lock is held while `store` is updated, so that the both are
+    /// changed consistently.
+    jobs: Mutex<HashMap<JobId, Job>>,
+
+    /// Wakes up the scheduler after a job has been scheduled.
+    changed: Notify,
+
+    stop: StopToken,
+    task: std::sync::Mutex<Option<JoinHandle<()>>>,
+}
+
+impl<S> SchedulerHandle<S>
+where
+    S: JobStore + Send + Sync + 'static,
+    S::Error: Debug + Send,
+{
+    /// Schedules a job of `kind` with `payload` and returns its random ID.
+    pub async fn schedule<T>(
+        &self,
+        kind: &str,
+        payload: &T,
+        schedule: Schedule,
+    ) -> Result<JobId, SchedulerError<S::Error>>
+    where
+        T: Serialize,
+    {
+        let id = JobId::random();
+        self.schedule_with_id(id.clone(), kind, payload, schedule).await?;
+        Ok(id)
+    }
+
+    /// Schedules a job with `id`, replacing a job with the same ID, if any.
+    ///
+    /// It's useful to derive an ID from a payload, e.g.
+    /// `format!("unmute:{}:{}", chat_id, user_id)`, to be able to cancel the
+    /// job without storing its ID.
+    pub async fn schedule_with_id<T>(
+        &self,
+        id: JobId,
+        kind: &str,
+        payload: &T,
+        schedule: Schedule,
+    ) -> Result<(), SchedulerError<S::Error>>
+    where
+        T: Serialize,
+    {
+        let payload = serde_json::to_value(payload).map_err(SchedulerError::Payload)?;
+        let next_run = schedule.first_run(SystemTime::now()).ok_or(SchedulerError::NeverRuns)?;
+        let job = Job { id: id.clone(), kind: kind.to_owned(), payload, schedule, next_run };
+
+        let mut jobs = self.inner.jobs.lock().await;
+        Arc::clone(&self.inner.store).save_job(job.clone()).await.map_err(SchedulerError::Store)?;
+        jobs.insert(id, job);
+
+        self.inner.changed.notify();
+        Ok(())
+    }
+
+    /// Cancels a job with `id`.
+    ///
+    /// Returns `false` if there's no such job.
+    pub async fn cancel(&self, id: &JobId) -> Result<bool, S::Error> {
+        let mut jobs = self.inner.jobs.lock().await;
+        if !jobs.contains_key(id) {
+            return Ok(false);
+        }
+
+        Arc::clone(&self.inner.store).remove_job(id.clone()).await?;
+        jobs.remove(id);
+        Ok(true)
+    }
+
+    /// Returns all the scheduled jobs.
+    pub async fn jobs(&self) -> Vec<Job> {
+        self.inner.jobs.lock().await.values().cloned().collect()
+    }
+
+    /// Stops running jobs and waits until the scheduler has stopped.
+    ///
+    /// Jobs, which are already running, aren't waited for.
+    pub async fn shutdown(&self) {
+        self.inner.stop.stop();
+
+        let task = self.inner.task.lock().unwrap().take();
+        if let Some(task) = task {
+            if let Err(error) = task.await {
+                log::error!("The scheduler has failed: {}", error);
+            }
+        }
+    }
+}
+
+impl<S> Clone for SchedulerHandle<S> {
+    fn clone(&self) -> Self {
+        Self { inner: Arc::clone(&self.inner) }
+    }
+}
+
+impl<S> Debug for SchedulerHandle<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SchedulerHandle")
+    }
+}
+
+async fn run<S>(inner: Arc<Inner<S>>, bot: Bot, handlers: HashMap<String, JobHandler>)
+where
+    S: JobStore + Send + Sync + 'static,
+    S::Error: Debug + Send,
+{
+    while !inner.stop.is_stopped() {
+        let next_run = {
+            let mut jobs = inner.jobs.lock().await;
+            let now = SystemTime::now();
+
+            let due: Vec<_> = jobs.values().filter(|job| job.next_run <= now).cloned().collect();
+            for job in due {
+                advance(&inner.store, &mut jobs, &job, now).await;
+
+                match handlers.get(&job.kind) {
+                    Some(handler) => {
+                        tokio::spawn(handler(bot.clone(), job));
+                    }
+                    None => log::error!("No handler of the job {} of kind {}", job.id, job.kind),
+                }
+            }
+
+            jobs.values().map(|job| job.next_run).min()
+        };
+
+        let sleep = next_run
+            .map(|time| time.duration_since(SystemTime::now()).unwrap_or_default())
+            .unwrap_or(MAX_SLEEP)
+            .min(MAX_SLEEP);
+
+        let woken = future::select(
+            Box::pin(tokio::time::delay_for(sleep)),
+            Box::pin(inner.changed.notified()),
+        );
+        future::select(woken, inner.stop.stopped()).await;
+    }
+}
+
+/// Updates `job`, which is run at `now`, in `jobs` and `store`: it's either
+/// rescheduled or removed.
+async fn advance<S>(store: &Arc<S>, jobs: &mut HashMap<JobId, Job>, job: &Job, now: SystemTime)
+where
+    S: JobStore,
+    S::Error: Debug,
+{
+    let result = match job.schedule.next_run(job.next_run, now) {
+        Some(next_run) => {
+            let job = Job { next_run, ..job.clone() };
+            jobs.insert(job.id.clone(), job.clone());
+            Arc::clone(store).save_job(job).await
+        }
+        None => {
+            jobs.remove(&job.id);
+            Arc::clone(store).remove_job(job.id.clone()).await
+        }
+    };
+
+    if let Err(error) = result {
+        log::error!("Cannot update the job {} in a job store: {:?}", job.id, error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockServer;
+    use std::convert::Infallible;
+    use tokio::{sync::mpsc, time::timeout};
+
+    type Runs = mpsc::UnboundedReceiver<(String, u32)>;
+
+    async fn start(store: &Arc<InMemJobStore>) -> (SchedulerHandle<InMemJobStore>, Runs) {
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        let handler = move |kind: &'static str| {
+            let tx = tx.clone();
+            move |_: Bot, n: u32| {
+                tx.send((kind.to_owned(), n)).unwrap();
+                future::ok::<_, Infallible>(())
+            }
+        };
+
+        let scheduler = Scheduler::new(MockServer::start().bot(), Arc::clone(store))
+            .handler("once", handler("once"))
+            .handler("every", handler("every"))
+            .start()
+            .await
+            .unwrap();
+        (scheduler, rx)
+    }
+
+    async fn next_run(runs: &mut Runs) -> (String, u32) {
+        timeout(Duration::from_secs(5), runs.recv()).await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn jobs_are_run_and_cancelled() {
+        let store = InMemJobStore::new();
+        let (scheduler, mut runs) = start(&store).await;
+
+        let ms = Duration::from_millis;
+        scheduler.schedule("once", &1, Schedule::after(ms(50))).await.unwrap();
+        let cancelled = scheduler.schedule("once", &2, Schedule::after(ms(100))).await.unwrap();
+        scheduler
+            .schedule_with_id("every".into(), "every", &3, Schedule::every(ms(30)))
+            .await
+            .unwrap();
+
+        assert!(scheduler.cancel(&cancelled).await.unwrap());
+        assert!(!scheduler.cancel(&cancelled).await.unwrap());
+
+        let mut seen = Vec::new();
+        while seen.iter().filter(|run| **run == ("every".to_owned(), 3)).count() < 4 {
+            seen.push(next_run(&mut runs).await);
+        }
+        assert!(seen.contains(&("once".to_owned(), 1)));
+        assert!(!seen.contains(&("once".to_owned(), 2)));
+
+        // Only the recurring job is left.
+        let jobs = Arc::clone(&store).load_jobs().await.unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].id, "every".into());
+        assert_eq!(scheduler.jobs().await, jobs);
+
+        scheduler.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn jobs_survive_restarts() {
+        let store = InMemJobStore::new();
+
+        let (scheduler, _) = start(&store).await;
+        let at = SystemTime::now() + Duration::from_millis(100);
+        scheduler.schedule("once", &1, Schedule::at(at)).await.unwrap();
+        scheduler.shutdown().await;
+
+        // The job is missed while the scheduler is down.
+        tokio::time::delay_for(Duration::from_millis(150)).await;
+
+        let (scheduler, mut runs) = start(&store).await;
+        assert_eq!(next_run(&mut runs).await, ("once".to_owned(), 1));
+        scheduler.shutdown().await;
+    }
+}
@@ -0,0 +1,359 @@
+use std::{
+    cmp,
+    convert::TryFrom,
+    fmt,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// When a job runs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Schedule {
+    /// Run once at the specified time.
+    ///
+    /// If the time has already passed (e.g. while the bot was down), the job
+    /// runs immediately.
+    Once(SystemTime),
+
+    /// Run repeatedly with the specified interval, starting one interval
+    /// after the job is scheduled.
+    Every(Duration),
+
+    /// Run at the times matching a cron expression.
+    Cron(Cron),
+}
+
+impl Schedule {
+    /// Runs a job once at `time`.
+    #[must_use]
+    pub fn at(time: SystemTime) -> Self {
+        Self::Once(time)
+    }
+
+    /// Runs a job once after `delay`.
+    #[must_use]
+    pub fn after(delay: Duration) -> Self {
+        Self::Once(SystemTime::now() + delay)
+    }
+
+    /// Runs a job every `interval`.
+    ///
+    /// # Panics
+    /// If `interval` is zero.
+    #[must_use]
+    pub fn every(interval: Duration) -> Self {
+        assert!(interval > Duration::from_secs(0), "The interval of a job must be positive");
+        Self::Every(interval)
+    }
+
+    /// Runs a job at the times matching a cron `expression`, see [`Cron`].
+    ///
+    /// [`Cron`]: crate::scheduling::Cron
+    pub fn cron(expression: &str) -> Result<Self, CronError> {
+        expression.parse().map(Self::Cron)
+    }
+
+    /// Returns the time of the first run of a job scheduled at `now`.
+    pub(crate) fn first_run(&self, now: SystemTime) -> Option<SystemTime> {
+        match self {
+            Self::Once(time) => Some(*time),
+            Self::Every(interval) => Some(now + *interval),
+            Self::Cron(cron) => cron.next_after(now),
+        }
+    }
+
+    /// Returns the time of the run of a job following a run `scheduled` at
+    /// some time, which has happened at `now`.
+    ///
+    /// Missed runs of recurring jobs are skipped.
+    pub(crate) fn next_run(&self, scheduled: SystemTime, now: SystemTime) -> Option<SystemTime> {
+        match self {
+            Self::Once(_) => None,
+            Self::Every(interval) => {
+                let next = scheduled + *interval;
+                Some(if next > now { next } else { now + *interval })
+            }
+            Self::Cron(cron) => cron.next_after(cmp::max(scheduled, now)),
+        }
+    }
+}
+
+/// An error returned from [`Schedule::cron`].
+///
+/// [`Schedule::cron`]: crate::scheduling::Schedule::cron
+#[derive(Debug, Error)]
+#[error("Invalid cron expression {expression:?}: {reason}")]
+pub struct CronError {
+    expression: String,
+    reason: &'static str,
+}
+
+/// A cron expression.
+///
+/// An expression consists of 5 fields: minutes (0-59), hours (0-23), days of
+/// a month (1-31), months (1-12) and days of a week (0-7, where both 0 and 7
+/// are Sunday). A field is a comma-separated list of `*`, `a`, `a-b`, `*/n`,
+/// `a-b/n` and `a/n` (from `a` to the maximum value with the step `n`). As in
+/// the traditional cron, if both days of a month and days of a week are
+/// restricted, a day matching either of them matches. The macros `@yearly`,
+/// `@monthly`, `@weekly`, `@daily` and `@hourly` are supported as well.
+///
+/// Times are in UTC.
+///
+/// ## Example
+/// ```
+/// use teloxide::scheduling::Cron;
+///
+/// // At 09:00 on weekdays.
+/// let cron: Cron = "0 9 * * 1-5".parse().unwrap();
+/// ```
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Cron {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+/// For how many days matching times are looked for.
+///
+/// It's enough to find February 29 on a specified day of a week, and even
+/// across the years 2096-2104.
+const SEARCH_DAYS: i64 = 366 * 9;
+
+impl Cron {
+    /// Returns the first matching time after `time`, or `None` if there's no
+    /// such time (e.g. for February 30).
+    #[must_use]
+    pub fn next_after(&self, time: SystemTime) -> Option<SystemTime> {
+        let seconds = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0) as i64;
+        let start = seconds / 60 + 1;
+        let (start_day, start_minute) = (start / 1440, start % 1440);
+
+        for day in start_day..start_day + SEARCH_DAYS {
+            if !self.matches_day(day) {
+                continue;
+            }
+
+            let from = if day == start_day { start_minute } else { 0 };
+            for minute in from..1440 {
+                if has(self.hours, minute / 60) && has(self.minutes, minute % 60) {
+                    let minutes = (day * 1440 + minute) as u64;
+                    return Some(UNIX_EPOCH + Duration::from_secs(minutes * 60));
+                }
+            }
+        }
+
+        None
+    }
+
+    fn matches_day(&self, day: i64) -> bool {
+        let (month, day_of_month) = month_and_day(day);
+        // 1970-01-01 is Thursday.
+        let day_of_week = (day + 4).rem_euclid(7);
+
+        if !has(self.months, month) {
+            return false;
+        }
+
+        let by_month = has(self.days_of_month, day_of_month);
+        let by_week = has(self.days_of_week, day_of_week);
+
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (true, true) => true,
+            (true, false) => by_week,
+            (false, true) => by_month,
+            (false, false) => by_month || by_week,
+        }
+    }
+}
+
+impl FromStr for Cron {
+    type Err = CronError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let error = |reason| CronError { expression: expression.to_owned(), reason };
+
+        let expanded = match expression.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+
+        let fields: Vec<_> = expanded.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(error("expected 5 fields"));
+        }
+
+        let mut days_of_week =
+            parse_field(fields[4], 0, 7).ok_or_else(|| error("invalid days of a week"))?;
+        // Both 0 and 7 are Sunday.
+        if has(days_of_week, 7) {
+            days_of_week = (days_of_week & !(1 << 7)) | 1;
+        }
+
+        Ok(Self {
+            expression: expression.to_owned(),
+            minutes: parse_field(fields[0], 0, 59).ok_or_else(|| error("invalid minutes"))?,
+            hours: parse_field(fields[1], 0, 23).ok_or_else(|| error("invalid hours"))?,
+            days_of_month: parse_field(fields[2], 1, 31)
+                .ok_or_else(|| error("invalid days of a month"))?,
+            months: parse_field(fields[3], 1, 12).ok_or_else(|| error("invalid months"))?,
+            days_of_week,
+            any_day_of_month: fields[2].starts_with('*'),
+            any_day_of_week: fields[4].starts_with('*'),
+        })
+    }
+}
+
+impl TryFrom<String> for Cron {
+    type Error = CronError;
+
+    fn try_from(expression: String) -> Result<Self, Self::Error> {
+        expression.parse()
+    }
+}
+
+impl From<Cron> for String {
+    fn from(cron: Cron) -> Self {
+        cron.expression
+    }
+}
+
+impl fmt::Debug for Cron {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Cron").field(&self.expression).finish()
+    }
+}
+
+impl fmt::Display for Cron {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+fn has(set: u64, value: i64) -> bool {
+    set & (1 << value) != 0
+}
+
+/// Parses a field of a cron expression into a bit set of values.
+fn parse_field(field: &str, min: i64, max: i64) -> Option<u64> {
+    let mut set = 0;
+
+    for item in field.split(',') {
+        let (range, step) = match item.find('/') {
+            Some(i) => (&item[..i], Some(item[i + 1..].parse::<i64>().ok()?)),
+            None => (item, None),
+        };
+
+        let (from, to) = if range == "*" {
+            (min, max)
+        } else if let Some(i) = range.find('-') {
+            (range[..i].parse().ok()?, range[i + 1..].parse().ok()?)
+        } else {
+            let value = range.parse().ok()?;
+            (value, if step.is_some() { max } else { value })
+        };
+
+        let step = step.unwrap_or(1);
+        if from < min || to > max || from > to || step <= 0 {
+            return None;
+        }
+
+        let mut value = from;
+        while value <= to {
+            set |= 1 << value;
+            value += step;
+        }
+    }
+
+    Some(set)
+}
+
+/// Returns the month (1-12) and the day of the month (1-31) of `day` days
+/// since 1970-01-01.
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn month_and_day(day: i64) -> (i64, i64) {
+    let z = day + 719_468;
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+
+    let day_of_month = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 };
+    (month, day_of_month)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2020-08-03 (Monday) 12:34:56 UTC.
+    const NOW: u64 = 1_596_458_096;
+
+    fn next(expression: &str) -> Option<u64> {
+        let cron: Cron = expression.parse().unwrap();
+        let next = cron.next_after(UNIX_EPOCH + Duration::from_secs(NOW))?;
+        Some(next.duration_since(UNIX_EPOCH).unwrap().as_secs())
+    }
+
+    #[test]
+    fn next_times() {
+        // 12:35
+        assert_eq!(next("* * * * *"), Some(1_596_458_100));
+        // 13:00
+        assert_eq!(next("@hourly"), Some(1_596_459_600));
+        // 2020-08-04 09:00
+        assert_eq!(next("0 9 * * *"), Some(1_596_531_600));
+        // 2020-08-07 (Friday) 00:00
+        assert_eq!(next("0 0 * * 5"), Some(1_596_758_400));
+        // 2020-08-09 (Sunday) 00:00
+        assert_eq!(next("0 0 * * 7"), Some(1_596_931_200));
+        // 2020-08-07 (Friday) 00:00, either the 15th or Friday.
+        assert_eq!(next("0 0 15 * 5"), Some(1_596_758_400));
+        // 2021-01-01 00:00
+        assert_eq!(next("@yearly"), Some(1_609_459_200));
+        // 2024-02-29 00:00
+        assert_eq!(next("0 0 29 2 *"), Some(1_709_164_800));
+        // 12:40
+        assert_eq!(next("*/20 12 * * 1-5"), Some(1_596_458_400));
+        assert_eq!(next("0 0 30 2 *"), None);
+    }
+
+    #[test]
+    fn invalid_expressions() {
+        for expression in &["", "* * * *", "60 * * * *", "* * 0 * *", "*/0 * * * *", "5-1 * * * *"]
+        {
+            assert!(expression.parse::<Cron>().is_err(), "{} is parsed", expression);
+        }
+    }
+
+    #[test]
+    fn recurring_runs() {
+        let now = UNIX_EPOCH + Duration::from_secs(NOW);
+        let minute = Duration::from_secs(60);
+
+        let every = Schedule::every(minute);
+        assert_eq!(every.first_run(now), Some(now + minute));
+        assert_eq!(every.next_run(now, now), Some(now + minute));
+        // Missed runs are skipped.
+        assert_eq!(every.next_run(now - minute * 10, now), Some(now + minute));
+
+        assert_eq!(Schedule::at(now).next_run(now, now), None);
+    }
+}
@@ -0,0 +1,210 @@
+//! [`tracing`] spans of updates and requests (the `tracing-spans` feature).
+//!
+//! [`Dispatcher`] creates an `update` span of every update and stores it in
+//! the update's [`Dependencies`], so handlers run by [`Router`],
+//! [`Sequential`], [`DialogueDispatcher`] and the REPLs (as well as
+//! middlewares) are instrumented with it. Every request is sent within a
+//! `request` span, which is a child of the current span.
+//!
+//! [`tracing`]: https://docs.rs/tracing
+//! [`Dispatcher`]: crate::dispatching::Dispatcher
+//! [`Dependencies`]: crate::dispatching::Dependencies
+//! [`Router`]: crate::dispatching::Router
+//! [`Sequential`]: crate::dispatching::Sequential
+//! [`DialogueDispatcher`]: crate::dispatching::dialogue::DialogueDispatcher
+
+use std::{future::Future, time::Instant};
+
+use reqwest::StatusCode;
+use tracing::{field, instrument::Instrumented, Instrument, Span};
+
+use crate::{dispatching::Dependencies, requests::ResponseResult, types::Update};
+
+/// Returns a span of `update` with its ID, kind and the IDs of its chat and
+/// user (if any).
+pub(crate) fn update(update: &Update) -> Span {
+    let span = tracing::info_span!(
+        "update",
+        id = update.id,
+        kind = update.kind.name(),
+        chat_id = field::Empty,
+        user_id = field::Empty,
+    );
+
+    if let Some(chat) = update.chat() {
+        span.record("chat_id", chat.id);
+    }
+    if let Some(user) = update.user() {
+        span.record("user_id", user.id);
+    }
+
+    span
+}
+
+/// Instruments `fut` with a span of an update with `dependencies` (if any).
+pub(crate) fn instrument<F>(dependencies: &Dependencies, fut: F) -> Instrumented<F>
+where
+    F: Future,
+{
+    fut.instrument(dependencies.get::<Span>().unwrap_or_else(Span::none))
+}
+
+/// Runs `fut`, which sends a request to `method`, within a `request` span.
+///
+/// The span records the HTTP status (see [`record_status`]), the duration of
+/// the request (including retries) and an error, if any.
+pub(crate) async fn request<F, T>(method: &'static str, fut: F) -> ResponseResult<T>
+where
+    F: Future<Output = ResponseResult<T>>,
+{
+    let span = tracing::info_span!(
+        "request",
+        method,
+        status = field::Empty,
+        duration_ms = field::Empty,
+        error = field::Empty,
+    );
+    let start = Instant::now();
+
+    let result = fut.instrument(span.clone()).await;
+
+    span.record("duration_ms", start.elapsed().as_millis() as u64);
+    if let Err(error) = &result {
+        span.record("error", field::display(error));
+    }
+
+    result
+}
+
+/// Records `status` of a response in the current `request` span.
+pub(crate) fn record_status(status: StatusCode) {
+    Span::current().record("status", status.as_u16());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dispatching::{update_listeners, Dispatcher, Router, UpdateWithCx},
+        error_handlers::IgnoringErrorHandlerSafe,
+        testing::{MockMessage, MockServer},
+        types::{Message, UpdateKind},
+    };
+    use futures::stream;
+    use std::{
+        collections::HashMap,
+        convert::Infallible,
+        fmt::Debug,
+        sync::{Arc, Mutex},
+    };
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+        Event, Metadata, Subscriber,
+    };
+    use tracing_core::span::Current;
+
+    #[derive(Debug)]
+    struct RecordedSpan {
+        name: &'static str,
+        parent: Option<u64>,
+        fields: HashMap<&'static str, String>,
+    }
+
+    impl Visit for RecordedSpan {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.fields.insert(field.name(), format!("{:?}", value));
+        }
+    }
+
+    /// Records all the spans with their fields and parents.
+    #[derive(Clone, Default)]
+    struct Recorder {
+        spans: Arc<Mutex<Vec<(RecordedSpan, &'static Metadata<'static>)>>>,
+        stack: Arc<Mutex<Vec<u64>>>,
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+            let parent = match attrs.parent() {
+                Some(parent) => Some(parent.into_u64()),
+                None if attrs.is_contextual() => self.stack.lock().unwrap().last().copied(),
+                None => None,
+            };
+
+            let mut span =
+                RecordedSpan { name: attrs.metadata().name(), parent, fields: HashMap::new() };
+            attrs.record(&mut span);
+
+            let mut spans = self.spans.lock().unwrap();
+            spans.push((span, attrs.metadata()));
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, id: &Id, values: &Record<'_>) {
+            values.record(&mut self.spans.lock().unwrap()[id.into_u64() as usize - 1].0);
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, _: &Event<'_>) {}
+
+        fn enter(&self, id: &Id) {
+            self.stack.lock().unwrap().push(id.into_u64());
+        }
+
+        fn exit(&self, _: &Id) {
+            self.stack.lock().unwrap().pop();
+        }
+
+        fn current_span(&self) -> Current {
+            match self.stack.lock().unwrap().last() {
+                Some(&id) => {
+                    let metadata = self.spans.lock().unwrap()[id as usize - 1].1;
+                    Current::new(Id::from_u64(id), metadata)
+                }
+                None => Current::none(),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_are_within_update_spans() {
+        let recorder = Recorder::default();
+        let _guard = tracing::dispatcher::set_default(&recorder.clone().into());
+
+        let server = MockServer::start();
+        let update = Update::new(7, UpdateKind::Message(MockMessage::new("Hi").build()));
+
+        Dispatcher::new(server.bot())
+            .messages_handler(Router::new().endpoint(|cx: UpdateWithCx<Message>| async move {
+                cx.answer_str("Hello").await.map(drop)
+            }))
+            .dispatch_with_listener(
+                update_listeners::from_stream(stream::iter(vec![Ok::<_, Infallible>(update)])),
+                IgnoringErrorHandlerSafe::new(),
+            )
+            .await;
+
+        let spans = recorder.spans.lock().unwrap();
+        let find = |name| {
+            let index = spans.iter().position(|(span, _)| span.name == name).unwrap();
+            (index as u64 + 1, &spans[index].0)
+        };
+
+        let (update_id, update) = find("update");
+        assert_eq!(update.fields["id"], "7");
+        assert_eq!(update.fields["kind"], "\"message\"");
+        assert!(update.fields.contains_key("chat_id"));
+
+        let (_, request) = find("request");
+        assert_eq!(request.parent, Some(update_id));
+        assert_eq!(request.fields["method"], "\"sendMessage\"");
+        assert_eq!(request.fields["status"], "200");
+        assert!(request.fields.contains_key("duration_ms"));
+    }
+}
@@ -0,0 +1,173 @@
+use serde_json::json;
+
+use crate::types::{CallbackQuery, Message, User};
+
+/// The sender of fixtures by default.
+#[must_use]
+pub fn mock_user() -> User {
+    User::new(1, false, "User")
+}
+
+/// A builder of text [`Message`] fixtures.
+///
+/// By default, a message is sent by [`mock_user`] to a private chat with the
+/// bot.
+///
+/// ## Example
+/// ```
+/// use teloxide::testing::MockMessage;
+///
+/// let message = MockMessage::new("/start").chat_id(-100).build();
+/// assert_eq!(message.text(), Some("/start"));
+/// assert_eq!(message.chat_id(), -100);
+/// ```
+///
+/// [`Message`]: crate::types::Message
+#[derive(Debug, Clone)]
+pub struct MockMessage {
+    id: i32,
+    date: i32,
+    chat_id: Option<i64>,
+    from: User,
+    text: String,
+    reply_to_message: Option<Message>,
+}
+
+impl MockMessage {
+    #[must_use]
+    pub fn new<S>(text: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            id: 1,
+            date: 0,
+            chat_id: None,
+            from: mock_user(),
+            text: text.into(),
+            reply_to_message: None,
+        }
+    }
+
+    #[must_use]
+    pub fn id(mut self, id: i32) -> Self {
+        self.id = id;
+        self
+    }
+
+    #[must_use]
+    pub fn date(mut self, date: i32) -> Self {
+        self.date = date;
+        self
+    }
+
+    /// Sets a chat of the message.
+    ///
+    /// Negative IDs are group chats, positive IDs are private chats. By
+    /// default, the ID of the sender is used.
+    #[must_use]
+    pub fn chat_id(mut self, chat_id: i64) -> Self {
+        self.chat_id = Some(chat_id);
+        self
+    }
+
+    #[must_use]
+    pub fn from(mut self, from: User) -> Self {
+        self.from = from;
+        self
+    }
+
+    #[must_use]
+    pub fn reply_to_message(mut self, message: Message) -> Self {
+        self.reply_to_message = Some(message);
+        self
+    }
+
+    #[must_use]
+    pub fn build(self) -> Message {
+        let chat_id = self.chat_id.unwrap_or_else(|| i64::from(self.from.id));
+        let chat = if chat_id < 0 {
+            json!({ "id": chat_id, "type": "group", "title": "Group" })
+        } else {
+            json!({ "id": chat_id, "type": "private", "first_name": self.from.first_name })
+        };
+
+        let mut message = json!({
+            "message_id": self.id,
+            "date": self.date,
+            "chat": chat,
+            "from": self.from,
+            "text": self.text,
+        });
+        if let Some(reply_to_message) = self.reply_to_message {
+            message["reply_to_message"] = json!(reply_to_message);
+        }
+
+        // `serde_json::from_value` doesn't handle untagged enums in `Chat`, so
+        // a message is parsed from a string.
+        serde_json::from_str(&message.to_string()).expect("building a message fixture")
+    }
+}
+
+/// A builder of [`CallbackQuery`] fixtures.
+///
+/// By default, a query is sent by [`mock_user`] and contains no message.
+///
+/// ## Example
+/// ```
+/// use teloxide::testing::{MockCallbackQuery, MockMessage};
+///
+/// let query = MockCallbackQuery::new("like").message(MockMessage::new("A post").build()).build();
+/// assert_eq!(query.data.as_deref(), Some("like"));
+/// ```
+///
+/// [`CallbackQuery`]: crate::types::CallbackQuery
+#[derive(Debug, Clone)]
+pub struct MockCallbackQuery {
+    id: String,
+    from: User,
+    data: String,
+    message: Option<Message>,
+}
+
+impl MockCallbackQuery {
+    #[must_use]
+    pub fn new<S>(data: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self { id: "1".to_owned(), from: mock_user(), data: data.into(), message: None }
+    }
+
+    #[must_use]
+    pub fn id<S>(mut self, id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.id = id.into();
+        self
+    }
+
+    #[must_use]
+    pub fn from(mut self, from: User) -> Self {
+        self.from = from;
+        self
+    }
+
+    /// Sets a message with the button that originated the query.
+    #[must_use]
+    pub fn message(mut self, message: Message) -> Self {
+        self.message = Some(message);
+        self
+    }
+
+    #[must_use]
+    pub fn build(self) -> CallbackQuery {
+        let query = CallbackQuery::new(self.id, self.from, "1").data(self.data);
+
+        match self.message {
+            Some(message) => query.message(message),
+            None => query,
+        }
+    }
+}
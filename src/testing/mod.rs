@@ -0,0 +1,204 @@
+//! Utilities for testing bots without Telegram.
+//!
+//! [`MockServer`] is a fake Bot API server, which records requests of a bot
+//! and responds with scripted results. Synthetic updates can be fed into a
+//! [`Dispatcher`] via [`updates`], and [`MockMessage`] &
+//! [`MockCallbackQuery`] build common update fixtures.
+//!
+//! This module is enabled by the `testing` feature, so add it to
+//! `[dev-dependencies]`:
+//! ```toml
+//! [dev-dependencies]
+//! teloxide = { version = "0.3", features = ["testing"] }
+//! ```
+//!
+//! ## Example
+//! ```
+//! use teloxide::{
+//!     prelude::*,
+//!     testing::{self, MockMessage, MockServer},
+//!     types::UpdateKind,
+//! };
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let server = MockServer::start();
+//!
+//! Dispatcher::new(server.bot())
+//!     .messages_handler(|rx: DispatcherHandlerRx<Message>| {
+//!         rx.for_each(|message| async move {
+//!             message.answer_str("pong").await.log_on_error().await;
+//!         })
+//!     })
+//!     .dispatch_with_listener(
+//!         testing::updates(vec![UpdateKind::Message(MockMessage::new("ping").build())]),
+//!         LoggingErrorHandler::new(),
+//!     )
+//!     .await;
+//!
+//! let requests = server.requests();
+//! assert_eq!(requests.len(), 1);
+//! assert_eq!(requests[0].method, "sendMessage");
+//! assert_eq!(requests[0].params["text"], "pong");
+//! # }
+//! ```
+//!
+//! [`Dispatcher`]: crate::dispatching::Dispatcher
+
+use std::convert::Infallible;
+
+use futures::stream;
+
+use crate::{
+    dispatching::update_listeners::{self, UpdateListener},
+    types::{Update, UpdateKind},
+};
+
+pub use fixtures::{mock_user, MockCallbackQuery, MockMessage};
+pub use server::{MockRequest, MockServer, MOCK_BOT_ID, MOCK_TOKEN};
+
+mod fixtures;
+mod server;
+
+/// Returns an update listener, which yields updates of the given kinds and
+/// then finishes.
+///
+/// Update IDs are assigned sequentially, starting from 1. Since the listener
+/// finishes, [`Dispatcher::dispatch_with_listener`] returns after all the
+/// updates have been handled.
+///
+/// [`Dispatcher::dispatch_with_listener`]:
+/// crate::dispatching::Dispatcher::dispatch_with_listener
+pub fn updates<I>(kinds: I) -> impl UpdateListener<Infallible>
+where
+    I: IntoIterator<Item = UpdateKind>,
+{
+    let updates: Vec<_> = (1..).zip(kinds).map(|(id, kind)| Ok(Update::new(id, kind))).collect();
+
+    update_listeners::from_stream(stream::iter(updates))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dispatching::{
+            dialogue::{DialogueDispatcher, DialogueStage, DialogueWithCx},
+            Dispatcher, DispatcherHandlerRx,
+        },
+        error_handlers::LoggingErrorHandler,
+        prelude::*,
+        requests::RequestWithFile,
+        types::{CallbackQuery, InlineKeyboardMarkup, InputFile, Message, ReplyMarkup},
+        ApiErrorKind, KnownApiErrorKind, RequestError,
+    };
+
+    #[tokio::test]
+    async fn records_multipart_requests() {
+        let server = MockServer::start();
+
+        let message = server
+            .bot()
+            .send_document(-42, InputFile::memory("notes.txt", &b"Some notes"[..]))
+            .caption("123")
+            .reply_markup(ReplyMarkup::InlineKeyboardMarkup(InlineKeyboardMarkup::default()))
+            .send()
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(message.chat_id(), -42);
+        assert_eq!(
+            server.requests(),
+            vec![MockRequest {
+                method: "sendDocument".to_owned(),
+                params: serde_json::json!({
+                    "chat_id": "-42",
+                    "document": { "file_name": "notes.txt", "size": 10 },
+                    "caption": "123",
+                    "reply_markup": { "inline_keyboard": [] },
+                }),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn scripted_responses() {
+        let server = MockServer::start();
+        let bot = server.bot();
+
+        server.respond_error("sendMessage", 400, "Bad Request: chat not found");
+        server.respond("getMe", serde_json::json!({"unexpected": true}));
+
+        match bot.send_message(1, "Hi").send().await {
+            Err(RequestError::ApiError { kind, .. }) => {
+                assert_eq!(kind, ApiErrorKind::Known(KnownApiErrorKind::ChatNotFound))
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        assert!(bot.get_me().send().await.is_err());
+
+        // The scripted responses are consumed.
+        assert!(bot.send_message(1, "Hi").send().await.is_ok());
+        assert_eq!(bot.get_me().send().await.unwrap().user.id, MOCK_BOT_ID);
+        assert_eq!(server.take_requests().len(), 4);
+        assert!(server.requests().is_empty());
+    }
+
+    #[derive(Clone, Debug)]
+    enum Counter {
+        Counting(u32),
+    }
+
+    impl Default for Counter {
+        fn default() -> Self {
+            Self::Counting(0)
+        }
+    }
+
+    #[tokio::test]
+    async fn feeds_updates_through_dialogue_dispatcher() {
+        let server = MockServer::start();
+
+        Dispatcher::new(server.bot())
+            .messages_handler(DialogueDispatcher::new(
+                |DialogueWithCx { cx, dialogue }: DialogueWithCx<Message, Counter, Infallible>| async move {
+                    let Counter::Counting(count) = dialogue.unwrap();
+                    cx.answer_str(format!("{}: {}", count + 1, cx.update.text().unwrap()))
+                        .await
+                        .unwrap();
+                    DialogueStage::Next(Counter::Counting(count + 1))
+                },
+            ))
+            .callback_queries_handler(|rx: DispatcherHandlerRx<CallbackQuery>| {
+                rx.for_each(|cx| async move {
+                    cx.bot.answer_callback_query(cx.update.id).send().await.unwrap();
+                })
+            })
+            .dispatch_with_listener(
+                updates(vec![
+                    UpdateKind::Message(MockMessage::new("a").build()),
+                    UpdateKind::Message(MockMessage::new("b").build()),
+                    UpdateKind::Message(MockMessage::new("c").chat_id(2).build()),
+                    UpdateKind::CallbackQuery(MockCallbackQuery::new("data").id("7").build()),
+                ]),
+                LoggingErrorHandler::new(),
+            )
+            .await;
+
+        let mut texts: Vec<_> = server
+            .requests()
+            .into_iter()
+            .map(|req| match req.method.as_str() {
+                "sendMessage" => format!("{} {}", req.params["chat_id"], req.params["text"]),
+                _ => format!("{} {}", req.method, req.params["callback_query_id"]),
+            })
+            .collect();
+        texts.sort();
+
+        assert_eq!(
+            texts,
+            [r#"1 "1: a""#, r#"1 "2: b""#, r#"2 "1: c""#, r#"answerCallbackQuery "7""#]
+        );
+    }
+}
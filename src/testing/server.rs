@@ -0,0 +1,332 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Request as HttpRequest, Response, Server,
+};
+use reqwest::Url;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::{
+    bot::{build_sound_bot, sends_message},
+    dispatching::StopToken,
+    Bot, BotBuilder,
+};
+
+/// The token of bots returned from [`MockServer::bot`].
+pub const MOCK_TOKEN: &str = "1234567890:MOCK_TOKEN";
+
+/// The user returned from `getMe` by default.
+pub const MOCK_BOT_ID: i32 = 1_234_567_890;
+
+/// A request received by [`MockServer`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockRequest {
+    /// A name of a method, e.g. `sendMessage`.
+    pub method: String,
+
+    /// Parameters of a request.
+    ///
+    /// Text fields of multipart requests are strings, as they were sent
+    /// (e.g. `"chat_id": "-42"`), except for the fields which are encoded as
+    /// JSON (`reply_markup`, `media` and `mask_position`), which are parsed.
+    /// Files are represented as `{"file_name": ..., "size": ...}`.
+    pub params: Value,
+}
+
+/// A fake Telegram Bot API server.
+///
+/// The server is bound to a random local port and records all received
+/// requests, which can be inspected via [`MockServer::requests`]. Responses
+/// can be scripted per method via [`MockServer::respond`] and
+/// [`MockServer::respond_error`]. If there's no scripted response for a
+/// request, the server responds with:
+///  - A text message from the bot to the `chat_id` chat, with the sent `text`
+///    or `caption`, to requests which send messages.
+///  - An empty array to `sendMediaGroup`.
+///  - The bot user to `getMe`.
+///  - `true` to all the other requests.
+///
+/// The server is shut down when dropped.
+///
+/// ## Example
+/// ```
+/// use teloxide::{prelude::*, testing::MockServer};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let server = MockServer::start();
+/// let bot = server.bot();
+///
+/// bot.send_message(42, "Hello!").send().await.unwrap();
+///
+/// let requests = server.requests();
+/// assert_eq!(requests[0].method, "sendMessage");
+/// assert_eq!(requests[0].params["chat_id"], 42);
+/// assert_eq!(requests[0].params["text"], "Hello!");
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MockServer {
+    url: Url,
+    state: Arc<Mutex<State>>,
+    stop: StopToken,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    requests: Vec<MockRequest>,
+    responses: HashMap<String, VecDeque<Value>>,
+    last_message_id: i32,
+}
+
+impl MockServer {
+    /// Starts a server on a random local port.
+    ///
+    /// # Panics
+    /// If it's called outside of the Tokio runtime or the server cannot be
+    /// bound.
+    #[must_use]
+    pub fn start() -> Self {
+        let state = Arc::new(Mutex::new(State::default()));
+        let stop = StopToken::new();
+
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service_fn({
+            let state = Arc::clone(&state);
+
+            move |_| {
+                let state = Arc::clone(&state);
+
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |req| {
+                        handle_request(req, Arc::clone(&state))
+                    }))
+                }
+            }
+        }));
+        let url = format!("http://{}", server.local_addr()).parse().unwrap();
+
+        let server = server.with_graceful_shutdown(stop.stopped());
+        tokio::spawn(async move {
+            if let Err(error) = server.await {
+                log::error!("The mock server has failed: {}", error);
+            }
+        });
+
+        Self { url, state, stop }
+    }
+
+    /// Returns the URL of this server.
+    #[must_use]
+    pub fn url(&self) -> Url {
+        self.url.clone()
+    }
+
+    /// Returns a bot that sends requests to this server.
+    ///
+    /// Failed requests of this bot are not retried.
+    #[must_use]
+    pub fn bot(&self) -> Bot {
+        BotBuilder::new()
+            .token(MOCK_TOKEN)
+            .client(build_sound_bot())
+            .api_url(self.url())
+            .build()
+    }
+
+    /// Responds to the next `method` request with `result`.
+    ///
+    /// Responses to the same method are returned in the order they were added.
+    pub fn respond<T>(&self, method: &str, result: T)
+    where
+        T: Serialize,
+    {
+        let result = serde_json::to_value(result).expect("serializing a mock response");
+        self.push_response(method, json!({ "ok": true, "result": result }));
+    }
+
+    /// Responds to the next `method` request with an error.
+    ///
+    /// `description` is parsed into [`ApiErrorKind`], e.g. `Bad Request: chat
+    /// not found`.
+    ///
+    /// [`ApiErrorKind`]: crate::ApiErrorKind
+    pub fn respond_error(&self, method: &str, error_code: u16, description: &str) {
+        self.push_response(
+            method,
+            json!({ "ok": false, "error_code": error_code, "description": description }),
+        );
+    }
+
+    /// Returns all the requests received so far, the oldest first.
+    #[must_use]
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Returns the requests received so far and forgets them.
+    pub fn take_requests(&self) -> Vec<MockRequest> {
+        std::mem::take(&mut self.state.lock().unwrap().requests)
+    }
+
+    fn push_response(&self, method: &str, response: Value) {
+        let mut state = self.state.lock().unwrap();
+        state.responses.entry(method.to_owned()).or_default().push_back(response);
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stop.stop();
+    }
+}
+
+async fn handle_request(
+    req: HttpRequest<Body>,
+    state: Arc<Mutex<State>>,
+) -> Result<Response<Body>, Infallible> {
+    let method = req.uri().path().rsplit('/').next().unwrap_or_default().to_owned();
+    let content_type = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+
+    let body = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(body) => body,
+        Err(error) => {
+            log::error!("Cannot read a mock request: {}", error);
+            return Ok(Response::new(Body::empty()));
+        }
+    };
+
+    let params = match content_type.split("boundary=").nth(1) {
+        Some(boundary) => parse_multipart(&body, boundary.trim_matches('"')),
+        None => serde_json::from_slice(&body).unwrap_or(Value::Null),
+    };
+
+    let mut state = state.lock().unwrap();
+    let response = match state.responses.get_mut(&method).and_then(VecDeque::pop_front) {
+        Some(response) => response,
+        None => json!({ "ok": true, "result": default_result(&mut state, &method, &params) }),
+    };
+    state.requests.push(MockRequest { method, params });
+
+    Ok(Response::builder()
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(response.to_string()))
+        .unwrap())
+}
+
+fn default_result(state: &mut State, method: &str, params: &Value) -> Value {
+    match method {
+        "getMe" => {
+            let mut me = bot_user();
+            me["can_join_groups"] = json!(true);
+            me["can_read_all_group_messages"] = json!(false);
+            me["supports_inline_queries"] = json!(false);
+            me
+        }
+        "sendMediaGroup" => json!([]),
+        _ if sends_message(method) => {
+            state.last_message_id += 1;
+
+            // `chat_id` is a string in multipart requests.
+            let chat_id = params["chat_id"]
+                .as_i64()
+                .or_else(|| params["chat_id"].as_str().and_then(|id| id.parse().ok()))
+                .unwrap_or_default();
+            let chat = if chat_id < 0 {
+                json!({ "id": chat_id, "type": "group", "title": "Group" })
+            } else {
+                json!({ "id": chat_id, "type": "private", "first_name": "User" })
+            };
+            let text = params["text"].as_str().or_else(|| params["caption"].as_str());
+
+            json!({
+                "message_id": state.last_message_id,
+                "date": 0,
+                "chat": chat,
+                "from": bot_user(),
+                "text": text.unwrap_or_default(),
+            })
+        }
+        _ => json!(true),
+    }
+}
+
+fn bot_user() -> Value {
+    json!({ "id": MOCK_BOT_ID, "is_bot": true, "first_name": "Mock", "username": "mock_bot" })
+}
+
+/// Text fields of multipart requests, which are encoded as JSON.
+const JSON_FIELDS: &[&str] = &["reply_markup", "media", "mask_position"];
+
+fn parse_multipart(body: &[u8], boundary: &str) -> Value {
+    let delimiter = format!("--{}", boundary);
+    let mut params = serde_json::Map::new();
+
+    for part in split(body, delimiter.as_bytes()) {
+        let part = part.strip_prefix(b"\r\n").unwrap_or(part);
+        let part = part.strip_suffix(b"\r\n").unwrap_or(part);
+
+        let (headers, content) = match find(part, b"\r\n\r\n") {
+            Some(i) => (&part[..i], &part[i + 4..]),
+            None => continue,
+        };
+        let headers = String::from_utf8_lossy(headers);
+
+        let name = match header_param(&headers, "name") {
+            Some(name) => name,
+            None => continue,
+        };
+        let value = match header_param(&headers, "filename") {
+            Some(file_name) => json!({ "file_name": file_name, "size": content.len() }),
+            None => {
+                let text = String::from_utf8_lossy(content).into_owned();
+                if JSON_FIELDS.contains(&name.as_str()) {
+                    serde_json::from_str(&text).unwrap_or(Value::String(text))
+                } else {
+                    Value::String(text)
+                }
+            }
+        };
+
+        params.insert(name, value);
+    }
+
+    Value::Object(params)
+}
+
+fn split<'a>(mut haystack: &'a [u8], needle: &[u8]) -> Vec<&'a [u8]> {
+    let mut parts = Vec::new();
+
+    while let Some(i) = find(haystack, needle) {
+        parts.push(&haystack[..i]);
+        haystack = &haystack[i + needle.len()..];
+    }
+
+    parts.push(haystack);
+    parts
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+fn header_param(headers: &str, name: &str) -> Option<String> {
+    let prefix = format!("{}=\"", name);
+
+    headers.lines().flat_map(|line| line.split(';')).map(str::trim).find_map(|param| {
+        let value = param.strip_prefix(&prefix)?;
+        Some(value.strip_suffix('"').unwrap_or(value).to_owned())
+    })
+}
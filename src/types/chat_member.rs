@@ -187,6 +187,69 @@ impl ChatMember {
         self.can_add_web_page_previews = Some(val);
         self
     }
+
+    /// Returns `true` if the member is the creator or an administrator of
+    /// the chat.
+    #[must_use]
+    pub fn is_admin(&self) -> bool {
+        matches!(self.status, ChatMemberStatus::Creator | ChatMemberStatus::Administrator)
+    }
+
+    /// Returns `true` if the member has `right`.
+    ///
+    /// The creator of a chat has all the rights, and members, which aren't
+    /// administrators, have none of them.
+    #[must_use]
+    pub fn can(&self, right: AdminRight) -> bool {
+        let flag = match right {
+            AdminRight::ChangeInfo => self.can_change_info,
+            AdminRight::PostMessages => self.can_post_messages,
+            AdminRight::EditMessages => self.can_edit_messages,
+            AdminRight::DeleteMessages => self.can_delete_messages,
+            AdminRight::InviteUsers => self.can_invite_users,
+            AdminRight::RestrictMembers => self.can_restrict_members,
+            AdminRight::PinMessages => self.can_pin_messages,
+            AdminRight::PromoteMembers => self.can_promote_members,
+        };
+
+        match self.status {
+            ChatMemberStatus::Creator => true,
+            ChatMemberStatus::Administrator => flag.unwrap_or(false),
+            _ => false,
+        }
+    }
+}
+
+/// A right of an administrator of a chat.
+///
+/// See [`ChatMember::can`].
+///
+/// [`ChatMember::can`]: crate::types::ChatMember::can
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum AdminRight {
+    /// Change the chat title, photo and other settings.
+    ChangeInfo,
+
+    /// Post in the channel, channels only.
+    PostMessages,
+
+    /// Edit messages of other users, channels only.
+    EditMessages,
+
+    /// Delete messages of other users.
+    DeleteMessages,
+
+    /// Invite new users to the chat.
+    InviteUsers,
+
+    /// Restrict, ban or unban chat members.
+    RestrictMembers,
+
+    /// Pin messages, supergroups only.
+    PinMessages,
+
+    /// Add new administrators.
+    PromoteMembers,
 }
 
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
@@ -259,4 +322,26 @@ mod tests {
         let actual = serde_json::from_str::<ChatMember>(&json).unwrap();
         assert_eq!(actual, expected)
     }
+
+    #[test]
+    fn rights() {
+        let user = User::new(1, false, "User");
+
+        let creator = ChatMember::new(user.clone(), ChatMemberStatus::Creator);
+        assert!(creator.is_admin());
+        assert!(creator.can(AdminRight::PromoteMembers));
+
+        let admin = ChatMember::new(user.clone(), ChatMemberStatus::Administrator)
+            .can_restrict_members(true)
+            .can_pin_messages(false);
+        assert!(admin.is_admin());
+        assert!(admin.can(AdminRight::RestrictMembers));
+        assert!(!admin.can(AdminRight::PinMessages));
+        assert!(!admin.can(AdminRight::DeleteMessages));
+
+        // Restricted members have `can_*` flags too, but not administrator's.
+        let member = ChatMember::new(user, ChatMemberStatus::Restricted).can_pin_messages(true);
+        assert!(!member.is_admin());
+        assert!(!member.can(AdminRight::PinMessages));
+    }
 }
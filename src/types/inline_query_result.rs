@@ -53,6 +53,35 @@ pub enum InlineQueryResult {
     Voice(InlineQueryResultVoice),
 }
 
+impl InlineQueryResult {
+    /// Returns the unique identifier of this result.
+    #[must_use]
+    pub fn id(&self) -> &str {
+        match self {
+            Self::CachedAudio(result) => &result.id,
+            Self::CachedDocument(result) => &result.id,
+            Self::CachedGif(result) => &result.id,
+            Self::CachedMpeg4Gif(result) => &result.id,
+            Self::CachedPhoto(result) => &result.id,
+            Self::CachedSticker(result) => &result.id,
+            Self::CachedVideo(result) => &result.id,
+            Self::CachedVoice(result) => &result.id,
+            Self::Article(result) => &result.id,
+            Self::Audio(result) => &result.id,
+            Self::Contact(result) => &result.id,
+            Self::Game(result) => &result.id,
+            Self::Document(result) => &result.id,
+            Self::Gif(result) => &result.id,
+            Self::Location(result) => &result.id,
+            Self::Mpeg4Gif(result) => &result.id,
+            Self::Photo(result) => &result.id,
+            Self::Venue(result) => &result.id,
+            Self::Video(result) => &result.id,
+            Self::Voice(result) => &result.id,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::types::{
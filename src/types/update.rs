@@ -7,6 +7,7 @@ use crate::types::{
     PreCheckoutQuery, ShippingQuery, User,
 };
 use serde_json::Value;
+use std::convert::TryInto;
 
 /// This [object] represents an incoming update.
 ///
@@ -67,6 +68,27 @@ impl Update {
             }
         }
     }
+
+    /// Parses `value` into `Update`, falling back to [`UpdateKind::Unknown`]
+    /// if failed.
+    ///
+    /// Returns `None` if `value` doesn't even contain a valid `update_id`.
+    ///
+    /// [`UpdateKind::Unknown`]: crate::types::UpdateKind::Unknown
+    pub fn parse_or_unknown(value: Value) -> Option<Self> {
+        match Self::try_parse(&value) {
+            Ok(update) => Some(update),
+            Err(_) => Self::unknown(value),
+        }
+    }
+
+    /// Wraps `value`, which cannot be parsed, into [`UpdateKind::Unknown`].
+    ///
+    /// [`UpdateKind::Unknown`]: crate::types::UpdateKind::Unknown
+    pub(crate) fn unknown(value: Value) -> Option<Self> {
+        let id = value.get("update_id")?.as_i64()?.try_into().ok()?;
+        Some(Self { id, kind: UpdateKind::Unknown(value) })
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -115,6 +137,37 @@ pub enum UpdateKind {
     /// A user changed their answer in a non-anonymous poll. Bots receive new
     /// votes only in polls that were sent by the bot itself.
     PollAnswer(PollAnswer),
+
+    /// An update that cannot be parsed, e.g. of a kind added in a newer
+    /// version of the Bot API.
+    ///
+    /// Contains the whole update as raw JSON (including `update_id`).
+    #[serde(skip_deserializing)]
+    Unknown(Value),
+}
+
+impl UpdateKind {
+    /// Returns a name of this kind, as in the Bot API (e.g.
+    /// `edited_message`), or `unknown` for [`UpdateKind::Unknown`].
+    ///
+    /// [`UpdateKind::Unknown`]: crate::types::UpdateKind::Unknown
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            UpdateKind::Message(_) => "message",
+            UpdateKind::EditedMessage(_) => "edited_message",
+            UpdateKind::ChannelPost(_) => "channel_post",
+            UpdateKind::EditedChannelPost(_) => "edited_channel_post",
+            UpdateKind::InlineQuery(_) => "inline_query",
+            UpdateKind::ChosenInlineResult(_) => "chosen_inline_result",
+            UpdateKind::CallbackQuery(_) => "callback_query",
+            UpdateKind::ShippingQuery(_) => "shipping_query",
+            UpdateKind::PreCheckoutQuery(_) => "pre_checkout_query",
+            UpdateKind::Poll(_) => "poll",
+            UpdateKind::PollAnswer(_) => "poll_answer",
+            UpdateKind::Unknown(_) => "unknown",
+        }
+    }
 }
 
 impl Update {
@@ -318,4 +371,18 @@ mod test {
 
         serde_json::from_str::<Update>(json).unwrap();
     }
+
+    #[test]
+    fn unknown_update() {
+        let value = serde_json::json!({
+            "update_id": 42,
+            "my_chat_member": { "chat": { "id": 1 } }
+        });
+
+        assert_eq!(
+            Update::parse_or_unknown(value.clone()),
+            Some(Update { id: 42, kind: UpdateKind::Unknown(value) })
+        );
+        assert_eq!(Update::parse_or_unknown(serde_json::json!({ "message": {} })), None);
+    }
 }
@@ -0,0 +1,142 @@
+use super::CallbackDataStore;
+use futures::future::BoxFuture;
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::Infallible,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// A store of callback data, which keeps it in RAM.
+///
+/// By default, data is kept forever. With [`InMemCallbackDataStore::with_ttl`],
+/// data is evicted after the TTL, so that the store doesn't grow without
+/// bound.
+///
+/// ## Note
+/// All the data will be lost after you restart your bot, so buttons with
+/// stored data sent before the restart stop working.
+///
+/// [`InMemCallbackDataStore::with_ttl`]:
+/// crate::utils::callback_data::InMemCallbackDataStore::with_ttl
+#[derive(Debug, Default)]
+pub struct InMemCallbackDataStore {
+    ttl: Option<Duration>,
+    entries: Mutex<Entries>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    data: HashMap<String, Entry>,
+
+    /// Keys in the order they have been saved, if there's a TTL.
+    saved: VecDeque<String>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    data: String,
+    saved: Instant,
+}
+
+impl InMemCallbackDataStore {
+    #[must_use]
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Creates a store, which keeps data for `ttl` after it has been saved.
+    ///
+    /// A button with stored data becomes invalid after the TTL:
+    /// [`CallbackDataCodec::decode`] returns [`CallbackDataError::NotFound`].
+    ///
+    /// [`CallbackDataCodec::decode`]:
+    /// crate::utils::callback_data::CallbackDataCodec::decode
+    /// [`CallbackDataError::NotFound`]:
+    /// crate::utils::callback_data::CallbackDataError::NotFound
+    #[must_use]
+    pub fn with_ttl(ttl: Duration) -> Arc<Self> {
+        Arc::new(Self { ttl: Some(ttl), entries: Mutex::default() })
+    }
+
+    fn is_expired(&self, entry: &Entry, now: Instant) -> bool {
+        matches!(self.ttl, Some(ttl) if now.duration_since(entry.saved) >= ttl)
+    }
+}
+
+impl CallbackDataStore for InMemCallbackDataStore {
+    type Error = Infallible;
+
+    fn save_data(
+        self: Arc<Self>,
+        key: String,
+        data: String,
+    ) -> BoxFuture<'static, Result<(), Self::Error>> {
+        Box::pin(async move {
+            let now = Instant::now();
+            let mut entries = self.entries.lock().unwrap();
+            let Entries { data: stored, saved } = &mut *entries;
+
+            // Keys are saved in the order of expiration.
+            while let Some(oldest) = saved.front() {
+                match stored.get(oldest) {
+                    Some(entry) if !self.is_expired(entry, now) => break,
+                    _ => {}
+                }
+                stored.remove(oldest);
+                saved.pop_front();
+            }
+
+            if self.ttl.is_some() {
+                saved.push_back(key.clone());
+            }
+            stored.insert(key, Entry { data, saved: now });
+            Ok(())
+        })
+    }
+
+    fn load_data(
+        self: Arc<Self>,
+        key: String,
+    ) -> BoxFuture<'static, Result<Option<String>, Self::Error>> {
+        Box::pin(async move {
+            let entries = self.entries.lock().unwrap();
+            let data = entries
+                .data
+                .get(&key)
+                .filter(|entry| !self.is_expired(entry, Instant::now()))
+                .map(|entry| entry.data.clone());
+            Ok(data)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn expired_data_is_evicted() {
+        let store = InMemCallbackDataStore::with_ttl(Duration::from_millis(50));
+        Arc::clone(&store).save_data("first".to_owned(), "1".to_owned()).await.unwrap();
+
+        tokio::time::delay_for(Duration::from_millis(60)).await;
+        Arc::clone(&store).save_data("second".to_owned(), "2".to_owned()).await.unwrap();
+
+        let entries = store.entries.lock().unwrap();
+        assert_eq!(entries.data.keys().collect::<Vec<_>>(), ["second"]);
+        assert_eq!(entries.saved, ["second"]);
+    }
+
+    #[tokio::test]
+    async fn data_is_kept_without_ttl() {
+        let store = InMemCallbackDataStore::new();
+        Arc::clone(&store).save_data("key".to_owned(), "data".to_owned()).await.unwrap();
+
+        assert_eq!(
+            Arc::clone(&store).load_data("key".to_owned()).await.unwrap(),
+            Some("data".to_owned())
+        );
+        assert!(store.entries.lock().unwrap().saved.is_empty());
+    }
+}
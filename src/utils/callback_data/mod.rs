@@ -0,0 +1,404 @@
+//! Typed callback data.
+//!
+//! [`CallbackData`] can be put into an inline keyboard button and parsed back
+//! from a [`CallbackQuery`]. A type, which implements [`Serialize`] and
+//! [`Deserialize`] (e.g. via `#[derive(Serialize, Deserialize)]`), becomes
+//! [`CallbackData`] via an empty impl of [`SerdeCallbackData`]. Such callback
+//! data is compact: it's JSON, where structs are serialized as arrays of
+//! their fields, e.g. `{"Vote":[3,1]}` for
+//! `Action::Vote { poll: 3, option: 1 }`. For an even more compact form,
+//! implement [`CallbackData`] by hand.
+//!
+//! There's no `#[derive(CallbackData)]` (like `#[derive(BotCommand)]`): the
+//! compact form is produced by the serde derives, so that field attributes
+//! like `#[serde(rename = "...")]` and `#[serde(with = "...")]` keep working
+//! and a derive in `teloxide-macros` would only duplicate them.
+//!
+//! Telegram limits callback data to 64 bytes. [`CallbackDataCodec`] keeps
+//! longer data in a [`CallbackDataStore`] and puts only a short key into a
+//! button instead, so that payloads of any size can be used. Stores can
+//! evict data after a TTL (see [`InMemCallbackDataStore::with_ttl`] and
+//! [`RedisCallbackDataStore::open_with_ttl`]), after which such buttons
+//! become invalid.
+//!
+//! Since fields are identified by their positions, attributes, which skip
+//! fields conditionally (like `#[serde(skip_serializing_if = "...")]`), and
+//! internally tagged and untagged enums aren't supported.
+//!
+//! ## Example
+//! ```no_run
+//! use serde::{Deserialize, Serialize};
+//! use teloxide::{
+//!     prelude::*,
+//!     types::{CallbackQuery, InlineKeyboardMarkup},
+//!     utils::callback_data::{CallbackDataCodec, InMemCallbackDataStore, SerdeCallbackData},
+//! };
+//!
+//! #[derive(Serialize, Deserialize)]
+//! enum Action {
+//!     Vote { poll: u32, option: u8 },
+//!     Close(u32),
+//! }
+//!
+//! impl SerdeCallbackData for Action {}
+//!
+//! # async fn run() {
+//! let codec = CallbackDataCodec::new(InMemCallbackDataStore::new());
+//!
+//! let button = codec.button("Vote", &Action::Vote { poll: 3, option: 1 }).await.unwrap();
+//! let markup = InlineKeyboardMarkup::default().append_row(vec![button]);
+//! // Send a message with `markup`...
+//!
+//! Dispatcher::new(Bot::from_env())
+//!     .callback_queries_handler(move |rx: DispatcherHandlerRx<CallbackQuery>| {
+//!         rx.callback_data(codec).for_each(|(cx, action): (_, Action)| async move {
+//!             match action {
+//!                 Action::Vote { poll, option } => { /* ... */ }
+//!                 Action::Close(poll) => { /* ... */ }
+//!             }
+//!         })
+//!     })
+//!     .dispatch()
+//!     .await;
+//! # }
+//! ```
+//!
+//! [`Serialize`]: serde::Serialize
+//! [`Deserialize`]: serde::Deserialize
+//! [`CallbackData`]: crate::utils::callback_data::CallbackData
+//! [`SerdeCallbackData`]: crate::utils::callback_data::SerdeCallbackData
+//! [`CallbackQuery`]: crate::types::CallbackQuery
+//! [`CallbackDataCodec`]: crate::utils::callback_data::CallbackDataCodec
+//! [`CallbackDataStore`]: crate::utils::callback_data::CallbackDataStore
+//! [`InMemCallbackDataStore::with_ttl`]:
+//! crate::utils::callback_data::InMemCallbackDataStore::with_ttl
+//! [`RedisCallbackDataStore::open_with_ttl`]:
+//! crate::utils::callback_data::RedisCallbackDataStore::open_with_ttl
+
+mod compact;
+mod in_mem_callback_data_store;
+
+#[cfg(feature = "redis-storage")]
+mod redis_callback_data_store;
+
+pub use in_mem_callback_data_store::InMemCallbackDataStore;
+#[cfg(feature = "redis-storage")]
+pub use redis_callback_data_store::RedisCallbackDataStore;
+
+use std::{
+    fmt::{self, Debug},
+    sync::Arc,
+};
+
+use futures::future::BoxFuture;
+use rand::Rng;
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+use crate::types::InlineKeyboardButton;
+
+use compact::Compact;
+
+/// The maximum length of callback data in bytes, allowed by Telegram.
+pub const MAX_CALLBACK_DATA_LEN: usize = 64;
+
+/// A prefix of keys of stored callback data.
+///
+/// JSON never starts with it, so keys and data cannot be confused.
+const KEY_PREFIX: char = '#';
+
+/// Data, which can be put into an inline keyboard button.
+///
+/// It's implemented for all the types, which implement [`SerdeCallbackData`].
+/// See the [module-level documentation] for the details.
+///
+/// ## Example
+/// ```
+/// use serde::Serialize;
+/// use teloxide::utils::callback_data::CallbackData;
+///
+/// // Serializable types can still be encoded in their own way.
+/// #[derive(Debug, PartialEq, Serialize)]
+/// struct Page(u32);
+///
+/// impl CallbackData for Page {
+///     fn encode(&self) -> Result<String, serde_json::Error> {
+///         Ok(format!("p{}", self.0))
+///     }
+///
+///     fn decode(data: &str) -> Result<Self, serde_json::Error> {
+///         let page = data.strip_prefix('p').and_then(|page| page.parse().ok());
+///         page.map(Page).ok_or_else(|| serde::de::Error::custom("Not a page"))
+///     }
+/// }
+///
+/// assert_eq!(Page::decode(&Page(3).encode().unwrap()).unwrap(), Page(3));
+/// ```
+///
+/// [`SerdeCallbackData`]: crate::utils::callback_data::SerdeCallbackData
+/// [module-level documentation]: crate::utils::callback_data
+pub trait CallbackData: Sized {
+    /// Serializes the data into the compact form.
+    ///
+    /// Note that the result can be longer than [`MAX_CALLBACK_DATA_LEN`].
+    ///
+    /// [`MAX_CALLBACK_DATA_LEN`]: crate::utils::callback_data::MAX_CALLBACK_DATA_LEN
+    fn encode(&self) -> Result<String, serde_json::Error>;
+
+    /// Parses data, serialized via [`CallbackData::encode`].
+    ///
+    /// [`CallbackData::encode`]: crate::utils::callback_data::CallbackData::encode
+    fn decode(data: &str) -> Result<Self, serde_json::Error>;
+}
+
+/// Marks a type, which is [`CallbackData`] in the compact JSON form of its
+/// [`Serialize`] and [`Deserialize`] impls.
+///
+/// See the [module-level documentation] for the details.
+///
+/// [`CallbackData`]: crate::utils::callback_data::CallbackData
+/// [`Serialize`]: serde::Serialize
+/// [`Deserialize`]: serde::Deserialize
+/// [module-level documentation]: crate::utils::callback_data
+pub trait SerdeCallbackData: Serialize + DeserializeOwned {}
+
+impl<T> CallbackData for T
+where
+    T: SerdeCallbackData,
+{
+    fn encode(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(&Compact(self))
+    }
+
+    fn decode(data: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(data)
+    }
+}
+
+/// A storage of callback data, which doesn't fit into buttons.
+///
+/// For a storage based on a simple hash map, see [`InMemCallbackDataStore`].
+///
+/// [`InMemCallbackDataStore`]: crate::utils::callback_data::InMemCallbackDataStore
+pub trait CallbackDataStore {
+    type Error;
+
+    /// Saves `data` under `key`.
+    fn save_data(
+        self: Arc<Self>,
+        key: String,
+        data: String,
+    ) -> BoxFuture<'static, Result<(), Self::Error>>;
+
+    /// Returns data saved under `key`, if any.
+    fn load_data(
+        self: Arc<Self>,
+        key: String,
+    ) -> BoxFuture<'static, Result<Option<String>, Self::Error>>;
+}
+
+/// An error returned from [`CallbackDataCodec`].
+///
+/// [`CallbackDataCodec`]: crate::utils::callback_data::CallbackDataCodec
+#[derive(Debug, Error)]
+pub enum CallbackDataError<E>
+where
+    E: Debug,
+{
+    #[error("Cannot serialize/parse callback data: {0}")]
+    Serde(#[source] serde_json::Error),
+
+    #[error("Callback data with the key {0} is not found")]
+    NotFound(String),
+
+    #[error("A callback data store has failed: {0:?}")]
+    Store(E),
+}
+
+/// Serializes and parses [`CallbackData`], keeping data longer than
+/// [`MAX_CALLBACK_DATA_LEN`] in a [`CallbackDataStore`].
+///
+/// [`CallbackData`]: crate::utils::callback_data::CallbackData
+/// [`MAX_CALLBACK_DATA_LEN`]: crate::utils::callback_data::MAX_CALLBACK_DATA_LEN
+/// [`CallbackDataStore`]: crate::utils::callback_data::CallbackDataStore
+pub struct CallbackDataCodec<S> {
+    store: Arc<S>,
+}
+
+impl<S> CallbackDataCodec<S>
+where
+    S: CallbackDataStore,
+    S::Error: Debug,
+{
+    #[must_use]
+    pub fn new(store: Arc<S>) -> Self {
+        Self { store }
+    }
+
+    /// Serializes `data` into a string, which fits into a button.
+    pub async fn encode<T>(&self, data: &T) -> Result<String, CallbackDataError<S::Error>>
+    where
+        T: CallbackData,
+    {
+        let data = data.encode().map_err(CallbackDataError::Serde)?;
+        if data.len() <= MAX_CALLBACK_DATA_LEN {
+            return Ok(data);
+        }
+
+        let key = format!("{}{:016x}", KEY_PREFIX, rand::thread_rng().gen::<u64>());
+        Arc::clone(&self.store)
+            .save_data(key.clone(), data)
+            .await
+            .map_err(CallbackDataError::Store)?;
+        Ok(key)
+    }
+
+    /// Parses data of a button, serialized via
+    /// [`CallbackDataCodec::encode`].
+    ///
+    /// [`CallbackDataCodec::encode`]:
+    /// crate::utils::callback_data::CallbackDataCodec::encode
+    pub async fn decode<T>(&self, data: &str) -> Result<T, CallbackDataError<S::Error>>
+    where
+        T: CallbackData,
+    {
+        if !data.starts_with(KEY_PREFIX) {
+            return T::decode(data).map_err(CallbackDataError::Serde);
+        }
+
+        let stored = Arc::clone(&self.store)
+            .load_data(data.to_owned())
+            .await
+            .map_err(CallbackDataError::Store)?
+            .ok_or_else(|| CallbackDataError::NotFound(data.to_owned()))?;
+        T::decode(&stored).map_err(CallbackDataError::Serde)
+    }
+
+    /// Creates a button with `text` and `data`.
+    pub async fn button<N, T>(
+        &self,
+        text: N,
+        data: &T,
+    ) -> Result<InlineKeyboardButton, CallbackDataError<S::Error>>
+    where
+        N: Into<String>,
+        T: CallbackData,
+    {
+        Ok(InlineKeyboardButton::callback(text.into(), self.encode(data).await?))
+    }
+}
+
+impl<S> Clone for CallbackDataCodec<S> {
+    fn clone(&self) -> Self {
+        Self { store: Arc::clone(&self.store) }
+    }
+}
+
+impl<S> Debug for CallbackDataCodec<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CallbackDataCodec")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dispatching::{Dependencies, DispatcherHandlerRxExt, UpdateWithCx},
+        testing::{MockCallbackQuery, MockServer},
+    };
+    use futures::{stream, StreamExt};
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Action {
+        Like,
+        Close(u32),
+        Vote { poll: u32, option: u8 },
+        Edit { id: u32, text: Option<String>, tags: Vec<Tag> },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Tag {
+        name: String,
+    }
+
+    impl SerdeCallbackData for Action {}
+
+    #[test]
+    fn data_is_compact() {
+        let cases = vec![
+            (Action::Like, r#""Like""#),
+            (Action::Close(3), r#"{"Close":3}"#),
+            (Action::Vote { poll: 3, option: 1 }, r#"{"Vote":[3,1]}"#),
+            (
+                Action::Edit { id: 1, text: None, tags: vec![Tag { name: "a".to_owned() }] },
+                r#"{"Edit":[1,null,[["a"]]]}"#,
+            ),
+        ];
+
+        for (action, encoded) in cases {
+            assert_eq!(action.encode().unwrap(), encoded);
+            assert_eq!(Action::decode(encoded).unwrap(), action);
+        }
+    }
+
+    #[tokio::test]
+    async fn long_data_is_stored() {
+        let codec = CallbackDataCodec::new(InMemCallbackDataStore::new());
+
+        let short = Action::Vote { poll: 3, option: 1 };
+        assert_eq!(codec.encode(&short).await.unwrap(), r#"{"Vote":[3,1]}"#);
+
+        let long = Action::Edit { id: 1, text: Some("a".repeat(100)), tags: vec![] };
+        let key = codec.encode(&long).await.unwrap();
+        assert!(key.len() <= MAX_CALLBACK_DATA_LEN);
+        assert_eq!(codec.decode::<Action>(&key).await.unwrap(), long);
+
+        assert!(matches!(
+            codec.decode::<Action>("#unknown").await,
+            Err(CallbackDataError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn stored_data_expires() {
+        let codec = CallbackDataCodec::new(InMemCallbackDataStore::with_ttl(
+            std::time::Duration::from_millis(50),
+        ));
+
+        let long = Action::Edit { id: 1, text: Some("a".repeat(100)), tags: vec![] };
+        let key = codec.encode(&long).await.unwrap();
+        assert_eq!(codec.decode::<Action>(&key).await.unwrap(), long);
+
+        tokio::time::delay_for(std::time::Duration::from_millis(60)).await;
+        assert!(matches!(codec.decode::<Action>(&key).await, Err(CallbackDataError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn extracts_data_from_queries() {
+        let bot = MockServer::start().bot();
+        let codec = CallbackDataCodec::new(InMemCallbackDataStore::new());
+        let long = Action::Edit { id: 2, text: Some("a".repeat(100)), tags: vec![] };
+        let key = codec.encode(&long).await.unwrap();
+
+        let queries = vec![
+            MockCallbackQuery::new(r#"{"Close":1}"#).build(),
+            MockCallbackQuery::new("garbage").build(),
+            MockCallbackQuery::new(key).build(),
+        ];
+        let extracted: Vec<Action> = stream::iter(queries)
+            .map(move |query| UpdateWithCx {
+                bot: bot.clone(),
+                update: query,
+                dependencies: Dependencies::new(),
+                completion: Default::default(),
+            })
+            .callback_data(codec)
+            .map(|(_, action)| action)
+            .collect()
+            .await;
+
+        assert_eq!(extracted, [Action::Close(1), long]);
+    }
+}
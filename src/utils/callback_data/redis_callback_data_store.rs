@@ -0,0 +1,98 @@
+use super::CallbackDataStore;
+use futures::future::BoxFuture;
+use redis::{AsyncCommands, IntoConnectionInfo, RedisError};
+use std::{ops::DerefMut, sync::Arc, time::Duration};
+use tokio::sync::Mutex;
+
+/// A store of callback data based on [Redis](https://redis.io/).
+///
+/// Data is kept under keys with a common prefix. By default, it's kept
+/// forever. With [`RedisCallbackDataStore::open_with_ttl`], keys are set
+/// with an expiration time (`SET ... EX`).
+///
+/// [`RedisCallbackDataStore::open_with_ttl`]:
+/// crate::utils::callback_data::RedisCallbackDataStore::open_with_ttl
+pub struct RedisCallbackDataStore {
+    conn: Mutex<redis::aio::Connection>,
+    prefix: String,
+    ttl: Option<Duration>,
+}
+
+impl RedisCallbackDataStore {
+    /// Connects to Redis, where data is kept under keys starting with
+    /// `prefix`.
+    pub async fn open<P>(url: impl IntoConnectionInfo, prefix: P) -> Result<Arc<Self>, RedisError>
+    where
+        P: Into<String>,
+    {
+        Self::connect(url, prefix.into(), None).await
+    }
+
+    /// Connects to Redis, where data is kept under keys starting with
+    /// `prefix` for `ttl` after it has been saved.
+    ///
+    /// A button with stored data becomes invalid after the TTL:
+    /// [`CallbackDataCodec::decode`] returns [`CallbackDataError::NotFound`].
+    /// Redis expires keys with a precision of seconds, so `ttl` is rounded up
+    /// to whole seconds (at least one).
+    ///
+    /// [`CallbackDataCodec::decode`]:
+    /// crate::utils::callback_data::CallbackDataCodec::decode
+    /// [`CallbackDataError::NotFound`]:
+    /// crate::utils::callback_data::CallbackDataError::NotFound
+    pub async fn open_with_ttl<P>(
+        url: impl IntoConnectionInfo,
+        prefix: P,
+        ttl: Duration,
+    ) -> Result<Arc<Self>, RedisError>
+    where
+        P: Into<String>,
+    {
+        Self::connect(url, prefix.into(), Some(ttl)).await
+    }
+
+    async fn connect(
+        url: impl IntoConnectionInfo,
+        prefix: String,
+        ttl: Option<Duration>,
+    ) -> Result<Arc<Self>, RedisError> {
+        Ok(Arc::new(Self {
+            conn: Mutex::new(redis::Client::open(url)?.get_async_connection().await?),
+            prefix,
+            ttl,
+        }))
+    }
+}
+
+impl CallbackDataStore for RedisCallbackDataStore {
+    type Error = RedisError;
+
+    fn save_data(
+        self: Arc<Self>,
+        key: String,
+        data: String,
+    ) -> BoxFuture<'static, Result<(), Self::Error>> {
+        Box::pin(async move {
+            let key = format!("{}{}", self.prefix, key);
+            let mut conn = self.conn.lock().await;
+
+            match self.ttl {
+                None => conn.deref_mut().set(key, data).await,
+                Some(ttl) => {
+                    let seconds = (ttl.as_secs() + u64::from(ttl.subsec_nanos() > 0)).max(1);
+                    conn.deref_mut().set_ex(key, data, seconds as usize).await
+                }
+            }
+        })
+    }
+
+    fn load_data(
+        self: Arc<Self>,
+        key: String,
+    ) -> BoxFuture<'static, Result<Option<String>, Self::Error>> {
+        Box::pin(async move {
+            let key = format!("{}{}", self.prefix, key);
+            self.conn.lock().await.deref_mut().get(key).await
+        })
+    }
+}
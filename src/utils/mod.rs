@@ -1,5 +1,6 @@
 //! Some useful utilities.
 
+pub mod callback_data;
 mod client_from_env;
 pub mod command;
 pub mod html;